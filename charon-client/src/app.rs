@@ -7,7 +7,7 @@ use std::{borrow::Cow, sync::Arc};
 use charond::domain::CharonEvent;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use eyre::OptionExt;
-use maiko::{ActorId, Envelope};
+use maiko::{ActorId, Envelope, EventId};
 use ratatui::layout::Rect;
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
@@ -68,6 +68,9 @@ impl App {
         let sock = self.connect_to_daemon(&mut tui).await?;
         let (reader, writer) = sock.into_split();
         self.sock_writer = Some(BufWriter::new(writer));
+        let keyboard = self.ctx.config.keyboard_alias.clone();
+        self.send_to_daemon(&CharonEvent::GetQMKKeymap(keyboard))
+            .await?;
         let mut reader = BufReader::new(reader);
         let mut action;
 
@@ -188,7 +191,10 @@ impl App {
             Command::ResumeApp => {}
             Command::ClearScreen => tui.terminal.clear()?,
             Command::Render => self.render(tui)?,
-            Command::SendEvent(event) => self.send_to_daemon(&event).await?,
+            Command::SendEvent(event) => {
+                let id = self.send_to_daemon(&event).await?;
+                self.app_event_tx.send(AppEvent::EventSent(id))?;
+            }
             Command::Quit => action = TickAction::Quit,
             Command::ExitApp => self.switch_app("menu")?,
             Command::Upgrade => action = TickAction::Upgrade,
@@ -250,7 +256,8 @@ impl App {
         Ok(())
     }
 
-    async fn send_to_daemon(&mut self, payload: &CharonEvent) -> eyre::Result<()> {
+    /// Sends the event to the daemon, returns the id of its envelope
+    async fn send_to_daemon(&mut self, payload: &CharonEvent) -> eyre::Result<EventId> {
         let writer = self
             .sock_writer
             .as_mut()
@@ -260,7 +267,7 @@ impl App {
         writer.write_all(json.as_bytes()).await?;
         writer.write_all(b"\n").await?;
         writer.flush().await?;
-        Ok(())
+        Ok(event.id())
     }
 
     async fn run_external(
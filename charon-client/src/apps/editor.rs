@@ -2,7 +2,9 @@
 use std::{borrow::Cow, path::PathBuf, sync::Arc};
 
 use charond::domain::{CharonEvent, Mode};
+use maiko::EventId;
 use tempfile::NamedTempFile;
+use tracing::error;
 
 use crate::domain::{
     AppEvent, Command, Context,
@@ -13,6 +15,10 @@ pub struct Editor {
     ctx: Arc<Context>,
     path: PathBuf,
     should_exit: bool,
+    /// Whether the typing request was sent, but its id is not known yet
+    sending: bool,
+    /// Id of the typing request, to match the response of the daemon
+    job: Option<EventId>,
 }
 
 impl Editor {
@@ -21,6 +27,8 @@ impl Editor {
             ctx,
             path: PathBuf::new(),
             should_exit: false,
+            sending: false,
+            job: None,
         }
     }
 
@@ -53,20 +61,38 @@ impl ExternalApp for Editor {
 
     async fn on_start(&mut self) -> eyre::Result<()> {
         self.should_exit = false;
+        self.sending = false;
+        self.job = None;
         let tmp = NamedTempFile::new()?;
         self.path = tmp.into_temp_path().keep()?; // closes handle, keeps file alive
         Ok(())
     }
 
     async fn process_result(&mut self) -> Option<Command> {
+        self.sending = true;
         Some(Command::SendEvent(CharonEvent::SendFile(
             self.path_to_string(),
             true,
+            None,
         )))
     }
 
     async fn handle_event(&mut self, event: &AppEvent) -> Option<Command> {
-        if matches!(event, AppEvent::Backend(CharonEvent::TextSent)) {
+        let ended = match event {
+            AppEvent::EventSent(id) if self.sending => {
+                self.sending = false;
+                self.job = Some(*id);
+                false
+            }
+            AppEvent::Backend(CharonEvent::TextSent(id)) => self.job == Some(*id),
+            AppEvent::Backend(CharonEvent::TypingFailed(id, err)) if self.job == Some(*id) => {
+                error!("Couldn't type the text: {err}");
+                true
+            }
+            _ => false,
+        };
+        if ended {
+            self.job = None;
             self.should_exit = true;
             return Some(Command::SendEvent(CharonEvent::ModeChange(
                 Mode::PassThrough,
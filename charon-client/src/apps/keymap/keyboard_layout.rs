@@ -123,7 +123,11 @@ impl KeyboardLayout {
 
 impl fmt::Display for KeyboardLayout {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.parts().fold(String::new(), |a, b| a + &b.0))
+        write!(
+            f,
+            "{}",
+            self.parts().fold(String::new(), |a, b| a + b.0.as_str())
+        )
     }
 }
 
@@ -11,15 +11,16 @@ use ratatui::{
     widgets::Paragraph,
 };
 use tokio::fs::read_to_string;
-use tracing::error;
+use tracing::{error, warn};
 
-use super::{KeyboardLayout, keycode_label::keycode_label, qmk_keymap::QmkKeymap};
+use super::{KeyboardLayout, keycode_label::keycode_label, qmk_keymap::load_keymap};
 use crate::domain::{AppEvent, Command, Context, traits::UiApp};
+use charond::domain::{CharonEvent, qmk::QMKKeymap};
 
 pub struct Keymap {
     ctx: Arc<Context>,
     layout: KeyboardLayout,
-    qmk_keymap: Option<QmkKeymap>,
+    qmk_keymap: Option<QMKKeymap>,
     current_layer: usize,
 }
 
@@ -42,16 +43,24 @@ impl Keymap {
             self.layout = KeyboardLayout::from_str(&layout);
         }
 
-        // Load QMK keymap
-        if let Ok(keymap) = QmkKeymap::load(&self.ctx.config.keymap_path()).await {
-            self.qmk_keymap = Some(keymap);
-            self.current_layer = 0;
-            self.apply_layer_labels();
+        // Load static QMK keymap, unless the live one has been received from the daemon
+        if self.qmk_keymap.is_none() {
+            if let Ok(keymap) = load_keymap(&self.ctx.config.keymap_path()).await {
+                self.set_keymap(keymap);
+            } else {
+                error!("Error loading keymap file");
+            }
         } else {
-            error!("Error loading keymap file");
+            self.apply_layer_labels();
         }
     }
 
+    fn set_keymap(&mut self, keymap: QMKKeymap) {
+        self.qmk_keymap = Some(keymap);
+        self.current_layer = 0;
+        self.apply_layer_labels();
+    }
+
     fn apply_layer_labels(&mut self) {
         let Some(ref keymap) = self.qmk_keymap else {
             return;
@@ -138,6 +147,17 @@ impl UiApp for Keymap {
                 None
             }
             AppEvent::Key(key) => self.handle_key(*key),
+            AppEvent::Backend(CharonEvent::QMKKeymap(keymap, alias))
+                if *alias == self.ctx.config.keyboard_alias =>
+            {
+                // keys in matrix order don't match the physical layout
+                if keymap.is_matrix_order() {
+                    warn!("Live keymap of {alias} is in matrix order (no layout_matrix), ignoring");
+                    return None;
+                }
+                self.set_keymap(keymap.clone());
+                Some(Command::Render)
+            }
             AppEvent::ShowLayer(layer) => {
                 self.set_layer(*layer as usize);
                 Some(Command::Render)
@@ -192,3 +212,45 @@ impl UiApp for Keymap {
         f.render_widget("ESC Exit".gray().into_right_aligned_line(), footer[1]);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::AppConfig;
+
+    fn keymap(layout: &str) -> QMKKeymap {
+        QMKKeymap {
+            keyboard: "test".into(),
+            keymap: "live".into(),
+            layout: layout.into(),
+            layers: vec![vec!["KC_A".into(), "KC_B".into()]],
+        }
+    }
+
+    fn live_keymap(app: &Keymap, layout: &str) -> AppEvent {
+        let alias = app.ctx.config.keyboard_alias.clone();
+        AppEvent::Backend(CharonEvent::QMKKeymap(keymap(layout), alias))
+    }
+
+    #[tokio::test]
+    async fn test_live_keymap() {
+        let ctx = Arc::new(Context {
+            config: AppConfig::default(),
+        });
+        let mut app = Keymap {
+            ctx,
+            layout: KeyboardLayout::default(),
+            qmk_keymap: None,
+            current_layer: 0,
+        };
+
+        // matrix order would scramble the layer preview
+        let event = live_keymap(&app, "matrix");
+        assert!(app.update(&event).await.is_none());
+        assert!(app.qmk_keymap.is_none());
+
+        let event = live_keymap(&app, "layout_matrix");
+        assert!(app.update(&event).await.is_some());
+        assert_eq!(Some(keymap("layout_matrix")), app.qmk_keymap);
+    }
+}
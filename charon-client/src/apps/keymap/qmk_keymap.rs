@@ -1,29 +1,12 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use serde::Deserialize;
+use charond::domain::qmk::QMKKeymap;
 use std::path::Path;
 use tokio::fs::read_to_string;
 
-#[derive(Debug, Deserialize)]
-pub struct QmkKeymap {
-    pub keyboard: String,
-    pub keymap: String,
-    #[allow(dead_code)] // useful for physical layout matching
-    pub layout: String,
-    pub layers: Vec<Vec<String>>,
-}
-
-impl QmkKeymap {
-    pub async fn load(path: &Path) -> eyre::Result<Self> {
-        let content = read_to_string(path).await?;
-        let keymap: QmkKeymap = serde_json::from_str(&content)?;
-        Ok(keymap)
-    }
-
-    pub fn layer_count(&self) -> usize {
-        self.layers.len()
-    }
-
-    pub fn layer(&self, n: usize) -> Option<&[String]> {
-        self.layers.get(n).map(|v| v.as_slice())
-    }
+/// Loads static keymap (in QMK's `keymap.json` format), used when the keymap
+/// couldn't be downloaded from the keyboard.
+pub async fn load_keymap(path: &Path) -> eyre::Result<QMKKeymap> {
+    let content = read_to_string(path).await?;
+    let keymap: QMKKeymap = serde_json::from_str(&content)?;
+    Ok(keymap)
 }
@@ -2,6 +2,7 @@
 use std::{borrow::Cow, sync::Arc};
 
 use charond::domain::{CharonEvent, Mode};
+use maiko::EventId;
 use tokio::{
     fs::{OpenOptions, read_to_string},
     io::AsyncWriteExt,
@@ -16,6 +17,10 @@ use crate::domain::{
 pub struct Password {
     ctx: Arc<Context>,
     should_exit: bool,
+    /// Whether the typing request was sent, but its id is not known yet
+    sending: bool,
+    /// Id of the typing request, to match the response of the daemon
+    job: Option<EventId>,
 }
 
 impl Password {
@@ -23,6 +28,8 @@ impl Password {
         Box::new(Password {
             ctx,
             should_exit: false,
+            sending: false,
+            job: None,
         })
     }
 
@@ -54,6 +61,8 @@ impl ExternalApp for Password {
 
     async fn on_start(&mut self) -> eyre::Result<()> {
         self.should_exit = false;
+        self.sending = false;
+        self.job = None;
         self.clear_cache().await?;
         Ok(())
     }
@@ -65,11 +74,26 @@ impl ExternalApp for Password {
             return None;
         };
 
-        Some(Command::SendEvent(CharonEvent::SendText(pwd)))
+        self.sending = true;
+        Some(Command::SendEvent(CharonEvent::SendText(pwd, None)))
     }
 
     async fn handle_event(&mut self, event: &AppEvent) -> Option<Command> {
-        if matches!(event, AppEvent::Backend(CharonEvent::TextSent)) {
+        let ended = match event {
+            AppEvent::EventSent(id) if self.sending => {
+                self.sending = false;
+                self.job = Some(*id);
+                false
+            }
+            AppEvent::Backend(CharonEvent::TextSent(id)) => self.job == Some(*id),
+            AppEvent::Backend(CharonEvent::TypingFailed(id, err)) if self.job == Some(*id) => {
+                error!("Couldn't type the text: {err}");
+                true
+            }
+            _ => false,
+        };
+        if ended {
+            self.job = None;
             self.should_exit = true;
             return Some(Command::SendEvent(CharonEvent::ModeChange(
                 Mode::PassThrough,
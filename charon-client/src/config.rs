@@ -13,6 +13,8 @@ pub struct AppConfig {
     pub clipboard_cache_file: PathBuf,
     pub keyboard_layout_file: String,
     pub keymap_file: String,
+    /// Alias of the keyboard group (as in daemon's `keyboards` config) shown by Keymap app
+    pub keyboard_alias: String,
     pub password_app: String,
     pub editor_app: String,
     pub keyboard_layouts_dir: PathBuf,
@@ -61,6 +63,7 @@ impl Default for AppConfig {
             .join("charon/clipboard-cache"),
             keyboard_layout_file: "keychron_10_ansi.txt".into(),
             keymap_file: "keychron_10_ansi.json".into(),
+            keyboard_alias: "Keychron_Q10".into(),
             keymaps_dir: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data/keymaps"),
             keyboard_layouts_dir: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data/layouts"),
             password_app: "passepartui".into(),
@@ -3,6 +3,7 @@ use std::time::Duration;
 
 use charond::domain::CharonEvent;
 use crossterm::event::KeyEvent;
+use maiko::EventId;
 use strum::Display;
 
 #[derive(Debug, Clone, PartialEq, Display)]
@@ -12,6 +13,9 @@ pub enum AppEvent {
     Tick(Duration),
     Key(KeyEvent),
     Backend(CharonEvent),
+    /// Event sent to the daemon (see `Command::SendEvent`), with the id of its envelope.
+    /// Responses of the daemon (i.e. `TextSent`) are correlated with this id.
+    EventSent(EventId),
     ShowLayer(u8),
     Quit,
     Resize(u16, u16),
@@ -54,6 +54,26 @@ impl AppManager {
                 self.is_awake = true;
                 None
             }
+            AppEvent::Backend(CharonEvent::QMKKeymap(..)) => {
+                // Keymap app needs the keymap even if it's not active
+                let cmd = self.apps.get_mut("keymap")?.update(msg).await;
+                if self.active_id == "keymap" {
+                    cmd
+                } else {
+                    None
+                }
+            }
+            AppEvent::Backend(CharonEvent::SwitchApp(app)) => {
+                let Some(app) = self.apps.keys().find(|id| **id == app.as_str()).copied() else {
+                    error!("Couldn't find app: {app}");
+                    return None;
+                };
+                self.set_active(app);
+                if let Some(app) = self.apps.get_mut(&self.active_id) {
+                    app.update(&AppEvent::Activate).await;
+                }
+                Some(Command::Render)
+            }
             m => {
                 if !self.is_awake {
                     return None;
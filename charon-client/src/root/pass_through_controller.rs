@@ -66,7 +66,10 @@ impl PassThroughController {
     /// Process an event and return the app ID to switch to, if any
     pub fn handle_event(&mut self, event: &AppEvent) -> Option<&'static str> {
         match event {
-            AppEvent::Backend(CharonEvent::QMKEvent(QMKEvent::LayerChange(layer, is_default))) => {
+            AppEvent::Backend(CharonEvent::QMKEvent(
+                QMKEvent::LayerChange(layer, is_default),
+                _,
+            )) => {
                 // Ignore default layer changes (e.g., QWERTY <-> Colemak)
                 if *is_default {
                     return None;
@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! End-to-end latency of key events: `KeyScanner` → `Pipeline` → `KeyWriter`,
+//! with mock input and HID devices. Run with:
+//!
+//! ```sh
+//! cargo bench -p charond --features testing --bench latency_bench
+//! ```
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as StdMutex},
+    time::{Duration, Instant},
+};
+
+use charond::{
+    actor::{KeyScanner, KeyWriter},
+    adapter::mock::{EventDeviceMock, EventDeviceState, HIDDeviceMock, HIDDeviceState},
+    config::CharonConfig,
+    domain::{ActorState, CharonEvent, HidKeyCode, Mode, Topic, stats::LatencyTrace},
+    processor::ProcessorRegistry,
+};
+use evdev::KeyCode;
+use maiko::{Envelope, Supervisor};
+use tokio::{sync::Mutex, time::sleep};
+
+const KEYS: [KeyCode; 20] = [
+    KeyCode::KEY_Q,
+    KeyCode::KEY_W,
+    KeyCode::KEY_E,
+    KeyCode::KEY_R,
+    KeyCode::KEY_T,
+    KeyCode::KEY_Y,
+    KeyCode::KEY_U,
+    KeyCode::KEY_I,
+    KeyCode::KEY_O,
+    KeyCode::KEY_P,
+    KeyCode::KEY_A,
+    KeyCode::KEY_S,
+    KeyCode::KEY_D,
+    KeyCode::KEY_F,
+    KeyCode::KEY_G,
+    KeyCode::KEY_H,
+    KeyCode::KEY_J,
+    KeyCode::KEY_K,
+    KeyCode::KEY_L,
+    KeyCode::KEY_Z,
+];
+
+/// Collects latency traces of the key events (per-stage breakdown)
+struct TraceCollector {
+    traces: Arc<StdMutex<Vec<LatencyTrace>>>,
+}
+
+impl maiko::Actor for TraceCollector {
+    type Event = CharonEvent;
+
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        if let CharonEvent::LatencyTrace(trace) = envelope.event()
+            && let Ok(mut traces) = self.traces.lock()
+        {
+            traces.push(trace.clone());
+        }
+        Ok(())
+    }
+}
+
+struct Bench {
+    sup: Supervisor<CharonEvent, Topic>,
+    input: Arc<Mutex<EventDeviceState>>,
+    output: Arc<StdMutex<HIDDeviceState>>,
+    traces: Arc<StdMutex<Vec<LatencyTrace>>>,
+}
+
+impl Bench {
+    async fn start() -> eyre::Result<Self> {
+        let config = CharonConfig {
+            latency_tracing: true,
+            ..Default::default()
+        };
+        let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+        let mut sup = Supervisor::default();
+
+        let input = EventDeviceMock::default();
+        let input_state = input.state().clone();
+        sup.add_actor(
+            "KeyScanner",
+            |ctx| KeyScanner::new(ctx, state.clone(), input, "bench".into()),
+            [Topic::System, Topic::Keyboard],
+        )?;
+
+        let registry = ProcessorRegistry::default();
+        sup.add_actor(
+            "KeyEventPipeline",
+            |ctx| {
+                registry
+                    .build_pipeline(ctx, &state)
+                    .expect("Couldn't build the pipeline")
+            },
+            [Topic::System, Topic::KeyInput],
+        )?;
+
+        let output = HIDDeviceMock::default();
+        let output_state = output.state().clone();
+        sup.add_actor(
+            "KeyWriter",
+            |ctx| KeyWriter::new(ctx, state.clone(), output),
+            [Topic::System, Topic::KeyOutput],
+        )?;
+
+        let traces = Arc::new(StdMutex::new(Vec::new()));
+        let collector = TraceCollector {
+            traces: traces.clone(),
+        };
+        sup.add_actor("TraceCollector", |_| collector, [Topic::Debug])?;
+
+        sup.start().await?;
+        sleep(Duration::from_millis(50)).await;
+        Ok(Self {
+            sup,
+            input: input_state,
+            output: output_state,
+            traces,
+        })
+    }
+
+    fn reset(&self) {
+        self.output.lock().unwrap().reports.clear();
+        self.traces.lock().unwrap().clear();
+    }
+
+    /// Presses and releases each of `keys`, one key every `interval`
+    async fn type_keys(&self, keys: &[KeyCode], interval: Duration) {
+        for key in keys {
+            {
+                let mut input = self.input.lock().await;
+                input.simulate_key_press(*key);
+                input.simulate_key_release(*key);
+            }
+            if !interval.is_zero() {
+                sleep(interval).await;
+            }
+        }
+        EventDeviceState::drain(&self.input).await;
+    }
+
+    /// Waits until `count` reports are written (or timeout)
+    async fn wait_for_reports(&self, count: usize) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while self.output.lock().unwrap().reports.len() < count && Instant::now() < deadline {
+            sleep(Duration::from_millis(1)).await;
+        }
+        // let the traces arrive
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Returns the value at given percentile (0-100) of sorted values
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted[(sorted.len() - 1) * p / 100]
+}
+
+fn format_nanos(nanos: u64) -> String {
+    format!("{:>9.1}µs", nanos as f64 / 1000.0)
+}
+
+fn print_stats(name: &str, mut values: Vec<u64>) {
+    values.sort_unstable();
+    println!(
+        "  {name:<28} p50 {}  p99 {}  max {}  (n={})",
+        format_nanos(percentile(&values, 50)),
+        format_nanos(percentile(&values, 99)),
+        format_nanos(values.last().copied().unwrap_or_default()),
+        values.len()
+    );
+}
+
+/// Prints end-to-end latency (evdev event to HID write) and per-stage breakdown
+fn report(bench: &Bench, name: &str, elapsed: Duration) {
+    let traces = bench.traces.lock().unwrap();
+    let reports = bench.output.lock().unwrap().reports.len();
+    println!("{name}");
+    print_stats("total", traces.iter().map(LatencyTrace::total).collect());
+
+    let mut stages: Vec<String> = Vec::new();
+    let mut durations: HashMap<String, Vec<u64>> = HashMap::new();
+    for trace in traces.iter() {
+        for (stage, duration) in trace.stage_durations() {
+            if !stages.iter().any(|s| s == stage) {
+                stages.push(stage.to_string());
+            }
+            durations.entry(stage.into()).or_default().push(duration);
+        }
+    }
+    for stage in stages {
+        print_stats(&stage, durations.remove(&stage).unwrap_or_default());
+    }
+    println!(
+        "  throughput: {reports} reports in {:.1}ms ({:.0} reports/s)\n",
+        elapsed.as_secs_f64() * 1000.0,
+        reports as f64 / elapsed.as_secs_f64()
+    );
+}
+
+/// Checks that every key reached the host
+fn verify(bench: &Bench, keys: &[KeyCode]) {
+    let reports = bench.output.lock().unwrap();
+    for key in keys {
+        let code = HidKeyCode::try_from(key).expect("Unsupported key") as u8;
+        assert!(
+            reports.reports.iter().any(|(_, r)| r[2..].contains(&code)),
+            "Key {key:?} not reported"
+        );
+    }
+}
+
+async fn run(bench: &Bench, name: &str, rounds: usize, interval: Duration, pause: Duration) {
+    bench.reset();
+    let started = Instant::now();
+    for _ in 0..rounds {
+        bench.type_keys(&KEYS, interval).await;
+        sleep(pause).await;
+    }
+    bench.wait_for_reports(rounds * KEYS.len() * 2).await;
+    let elapsed = started.elapsed().saturating_sub(pause * rounds as u32);
+    verify(bench, &KEYS);
+    report(bench, name, elapsed);
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let mut bench = Bench::start().await?;
+
+    // warm-up
+    run(
+        &bench,
+        "warm-up",
+        1,
+        Duration::from_millis(2),
+        Duration::ZERO,
+    )
+    .await;
+
+    run(
+        &bench,
+        "single keys (1 key / 5ms)",
+        5,
+        Duration::from_millis(5),
+        Duration::from_millis(20),
+    )
+    .await;
+    run(
+        &bench,
+        "bursts (20 keys / 10ms)",
+        20,
+        Duration::from_micros(500),
+        Duration::from_millis(50),
+    )
+    .await;
+    run(
+        &bench,
+        "bursts (20 keys at once)",
+        20,
+        Duration::ZERO,
+        Duration::from_millis(50),
+    )
+    .await;
+
+    bench.sup.stop().await?;
+    Ok(())
+}
@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::HashMap;
+
+use maiko::{Context, StepAction};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::InputConfig,
+    domain::{ActorState, CharonEvent, DeviceChange},
+    port::DeviceWatcher,
+    util::evdev::is_configured_device,
+};
+
+/// Watches input devices being plugged in and out, and informs the rest of the system
+/// about configured keyboards with `KeyboardAttached` / `KeyboardDetached` events
+/// (carrying keyboard alias). Key scanners use these events to (re)open or release
+/// their devices.
+///
+/// Each alias is attached to a single device at a time (the one its scanner uses),
+/// even if more devices match it (i.e. the auto-detected `KeyScanner` when no keyboard
+/// was discovered at startup). Other matching devices are ignored, and the first one
+/// still present takes over when the attached device is unplugged.
+///
+/// Keyboards are discovered once, at startup (see `CharonConfig::get_config_per_keyboard`),
+/// and scanners can't be added later: a new keyboard plugged in in auto mode isn't picked up
+/// until charond is restarted.
+pub struct DeviceMonitor<W: DeviceWatcher> {
+    ctx: Context<CharonEvent>,
+    state: ActorState,
+    watcher: W,
+
+    /// Keyboard alias and input config (as used by corresponding KeyScanner)
+    keyboards: Vec<(String, InputConfig)>,
+
+    /// Aliases of attached devices, by device name (at most one device per alias).
+    /// The device is gone when detached, so it can't be matched by its capabilities anymore.
+    attached: HashMap<String, String>,
+}
+
+impl<W: DeviceWatcher> DeviceMonitor<W> {
+    /// Keyboards must be the ones the key scanners were created for
+    /// (see `CharonConfig::get_config_per_keyboard`), so their aliases match.
+    pub fn new(
+        ctx: Context<CharonEvent>,
+        state: ActorState,
+        watcher: W,
+        keyboards: Vec<(String, InputConfig)>,
+    ) -> Self {
+        Self {
+            ctx,
+            state,
+            watcher,
+            keyboards,
+            attached: HashMap::new(),
+        }
+    }
+
+    fn alias(&self, device_name: &str) -> Option<&str> {
+        self.keyboards
+            .iter()
+            .find(|(_, input)| is_configured_device(input, device_name))
+            .map(|(alias, _)| alias.as_str())
+    }
+
+    /// Name of the device attached under given alias
+    fn attached_device(&self, alias: &str) -> Option<&str> {
+        self.attached
+            .iter()
+            .find(|(_, attached)| *attached == alias)
+            .map(|(name, _)| name.as_str())
+    }
+
+    fn is_optional(&self, alias: &str) -> bool {
+        self.state
+            .config()
+            .device_entry(alias)
+            .is_some_and(|dev| dev.optional)
+    }
+
+    async fn handle_change(&mut self, change: DeviceChange) -> maiko::Result<()> {
+        match change {
+            DeviceChange::Attached(name) => self.attach(name).await,
+            DeviceChange::Detached(name) => self.detach(name).await,
+        }
+    }
+
+    async fn attach(&mut self, name: String) -> maiko::Result<()> {
+        let Some(alias) = self.alias(&name).map(String::from) else {
+            if matches!(self.state.config().keyboard, InputConfig::Auto)
+                && is_configured_device(&InputConfig::Auto, &name)
+            {
+                warn!("Keyboard {name} plugged in after startup is not used until restart");
+            } else {
+                debug!("Ignoring unconfigured device: {name}");
+            }
+            return Ok(());
+        };
+        if let Some(device) = self.attached_device(&alias) {
+            debug!("Keyboard {alias} already attached ({device}), ignoring {name}");
+            return Ok(());
+        }
+        info!("Keyboard attached: {alias} ({name})");
+        self.attached.insert(name, alias.clone());
+        self.ctx.send(CharonEvent::KeyboardAttached(alias)).await
+    }
+
+    async fn detach(&mut self, name: String) -> maiko::Result<()> {
+        let Some(alias) = self.attached.remove(&name) else {
+            debug!("Ignoring device not attached to any keyboard: {name}");
+            return Ok(());
+        };
+        if self.is_optional(&alias) {
+            info!("Optional keyboard detached: {alias} ({name})");
+        } else {
+            warn!("Keyboard detached: {alias} ({name})");
+        }
+        self.ctx
+            .send(CharonEvent::KeyboardDetached(alias.clone()))
+            .await?;
+
+        // another device matching the alias takes over
+        let other = self
+            .watcher
+            .devices()
+            .into_iter()
+            .find(|device| self.alias(device) == Some(alias.as_str()));
+        if let Some(other) = other {
+            info!("Keyboard attached: {alias} ({other})");
+            self.attached.insert(other, alias.clone());
+            self.ctx.send(CharonEvent::KeyboardAttached(alias)).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: DeviceWatcher> maiko::Actor for DeviceMonitor<W> {
+    type Event = CharonEvent;
+
+    async fn on_start(&mut self) -> maiko::Result<()> {
+        for name in self.watcher.devices() {
+            self.handle_change(DeviceChange::Attached(name)).await?;
+        }
+        Ok(())
+    }
+
+    async fn step(&mut self) -> maiko::Result<StepAction> {
+        match self.watcher.next_change().await {
+            Some(change) => {
+                self.handle_change(change).await?;
+                Ok(StepAction::Continue)
+            }
+            None => {
+                warn!("Device watcher stopped, hot-plug is no longer supported");
+                Ok(StepAction::Never)
+            }
+        }
+    }
+}
@@ -36,6 +36,19 @@ impl IPCServer {
             cancel_token: Arc::new(CancellationToken::new()),
         }
     }
+
+    /// Replies to the client with all the key bindings. The reply goes straight to
+    /// the session, as the broker doesn't deliver actor's own events back to it.
+    async fn send_bindings(&mut self) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let event = CharonEvent::Bindings(self.state.config().action_bindings());
+        let envelope = Envelope::new(event, self.ctx.actor_id().clone());
+        if let Err(e) = session.sender.send(Arc::new(envelope)).await {
+            tracing::warn!("Failed to send bindings to session: {e}");
+        }
+    }
 }
 
 impl maiko::Actor for IPCServer {
@@ -54,6 +67,7 @@ impl maiko::Actor for IPCServer {
                 info!("Client requested to change mode to: {mode}");
                 self.state.set_mode(*mode).await;
             }
+            CharonEvent::ListBindings => self.send_bindings().await,
             _ => {}
         }
         Ok(())
@@ -1,12 +1,13 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use std::collections::HashSet;
+use std::{collections::HashMap, io::ErrorKind};
 
-use crate::domain::{CharonEvent, Mode};
+use crate::domain::{CharonEvent, Mode, stats::timestamp};
 use maiko::{Context, Envelope, StepAction};
 
-use crate::{domain::ActorState, port::EventDevice};
-use evdev::{EventSummary, InputEvent};
-use tracing::{debug, error, warn};
+use crate::{config::keyboard::KeyboardRole, domain::ActorState, port::EventDevice};
+use evdev::{EventSummary, InputEvent, KeyCode};
+use tokio::time::{Duration, Instant, sleep_until};
+use tracing::{debug, error, info, warn};
 
 /// The key actor of Charon, that scans evdev (input device) on Linux side
 /// and sends each captured event to the rest of the system.
@@ -15,6 +16,14 @@ use tracing::{debug, error, warn};
 /// or not (in-app). The intention is that if in pass-through mode
 /// the key events should be send only to the host, while when in in-app mode
 /// the keyboard is available to Charon device.
+/// The device may come and go (see `DeviceMonitor`): the scanner (re)connects
+/// on `KeyboardAttached` event and releases the device on `KeyboardDetached`.
+/// Keyboards in macro-pad role are always grabbed, and keyboards in in-app-only role
+/// are never grabbed, regardless the mode.
+/// Keys pressed according to the events read so far are reconciled with the device's
+/// key state before grab/ungrab, and keys held for `stuck_key_timeout` without autorepeat
+/// are released, unless the device reports them as still pressed - so a lost release
+/// event doesn't leave a key stuck on the host.
 pub struct KeyScanner<D: EventDevice> {
     ctx: Context<CharonEvent>,
 
@@ -33,8 +42,12 @@ pub struct KeyScanner<D: EventDevice> {
     /// Grab/ungrab intention (actual switch happens when all keys are released)
     should_handle_grab: Option<Mode>,
 
-    /// Keeps currently pressed key codes. Used for clean grab/ungrab of input device.
-    keyboard_state: HashSet<u16>,
+    /// Keeps currently pressed key codes, with time of the last press (or autorepeat).
+    /// Used for clean grab/ungrab of input device.
+    keyboard_state: HashMap<u16, Instant>,
+
+    /// Time after which a held key is considered stuck (see `check_held_keys`)
+    stuck_key_timeout: Option<Duration>,
 }
 
 impl<D: EventDevice> KeyScanner<D> {
@@ -44,13 +57,15 @@ impl<D: EventDevice> KeyScanner<D> {
         input: D,
         keyboard_name: String,
     ) -> Self {
+        let timeout = state.config().stuck_key_timeout;
         KeyScanner {
             ctx,
             state,
             input,
             keyboard_name,
             should_handle_grab: None,
-            keyboard_state: HashSet::new(),
+            keyboard_state: HashMap::new(),
+            stuck_key_timeout: (timeout > 0).then(|| Duration::from_millis(timeout)),
         }
     }
 
@@ -59,7 +74,7 @@ impl<D: EventDevice> KeyScanner<D> {
             // meaning of value: 0 - key release, 1 - key press, 2 - key repeat
             EventSummary::Key(_, key, value) => match value {
                 1 | 2 => {
-                    self.keyboard_state.insert(key.code());
+                    self.keyboard_state.insert(key.code(), Instant::now());
                     CharonEvent::KeyPress(key, self.keyboard_name.clone())
                 }
                 0 => {
@@ -78,10 +93,178 @@ impl<D: EventDevice> KeyScanner<D> {
             }
         };
 
-        self.ctx.send(payload).await
+        let envelope = Envelope::new(payload, self.ctx.actor_id().clone());
+        let tracer = self.state.tracer();
+        if tracer.is_enabled() {
+            let id = envelope.meta().id();
+            tracer.start(id, &self.keyboard_name, timestamp(key_event.timestamp()));
+            tracer.mark(id, "scanner");
+        }
+        self.ctx.send_envelope(envelope).await
+    }
+
+    fn is_optional(&self) -> bool {
+        self.state
+            .config()
+            .device_entry(&self.keyboard_name)
+            .is_some_and(|dev| dev.optional)
+    }
+
+    fn connect(&mut self) -> bool {
+        match self.input.connect() {
+            Ok(()) => {
+                info!("Keyboard connected: {}", self.keyboard_name);
+                true
+            }
+            Err(e) if self.is_optional() => {
+                debug!(
+                    "Optional keyboard {} not available: {e}",
+                    self.keyboard_name
+                );
+                false
+            }
+            Err(e) => {
+                warn!(
+                    "Keyboard {} not available ({e}), waiting for it to be connected",
+                    self.keyboard_name
+                );
+                false
+            }
+        }
+    }
+
+    /// Releases the device and sends release event for every key that was held
+    /// at the moment of disconnection, so no key gets stuck on the host.
+    async fn disconnect(&mut self) -> maiko::Result<()> {
+        self.input.disconnect();
+        self.should_handle_grab = None;
+        for code in std::mem::take(&mut self.keyboard_state).into_keys() {
+            self.release(code).await?;
+        }
+        Ok(())
+    }
+
+    async fn release(&mut self, code: u16) -> maiko::Result<()> {
+        self.ctx
+            .send(CharonEvent::KeyRelease(
+                KeyCode::new(code),
+                self.keyboard_name.clone(),
+            ))
+            .await
+    }
+
+    /// Reconciles pressed keys with the device's key state: keys released without
+    /// the scanner noticing are released, keys pressed without the scanner noticing
+    /// are tracked (so the grab waits until they are released).
+    async fn reconcile(&mut self) -> maiko::Result<()> {
+        let pressed = match self.input.key_state() {
+            Ok(keys) => keys,
+            Err(e) => {
+                debug!("Couldn't read key state of {}: {e}", self.keyboard_name);
+                return Ok(());
+            }
+        };
+        let now = Instant::now();
+        let released: Vec<u16> = self
+            .keyboard_state
+            .keys()
+            .filter(|code| !pressed.iter().any(|key| key.code() == **code))
+            .copied()
+            .collect();
+        for code in released {
+            warn!(
+                "Key {:?} of {} is not pressed anymore, releasing",
+                KeyCode::new(code),
+                self.keyboard_name
+            );
+            self.keyboard_state.remove(&code);
+            self.release(code).await?;
+        }
+        for key in pressed {
+            self.keyboard_state.entry(key.code()).or_insert(now);
+        }
+        Ok(())
+    }
+
+    /// Time at which the longest held key is considered stuck
+    fn stuck_key_deadline(&self) -> Option<Instant> {
+        let timeout = self.stuck_key_timeout?;
+        self.keyboard_state
+            .values()
+            .min()
+            .map(|time| *time + timeout)
+    }
+
+    /// Releases keys held without autorepeat for `stuck_key_timeout`, unless the device
+    /// reports them as pressed (i.e. keyboards that don't autorepeat).
+    /// Keys are never released without knowing the device's key state: the check is
+    /// disabled for devices that can't report it, and postponed if reading it fails.
+    async fn check_held_keys(&mut self) -> maiko::Result<()> {
+        let Some(timeout) = self.stuck_key_timeout else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let pressed = match self.input.key_state() {
+            Ok(keys) => keys,
+            Err(e) if e.kind() == ErrorKind::Unsupported => {
+                debug!(
+                    "Keyboard {} can't report key state, held keys are not checked",
+                    self.keyboard_name
+                );
+                self.stuck_key_timeout = None;
+                return Ok(());
+            }
+            Err(e) => {
+                debug!("Couldn't read key state of {}: {e}", self.keyboard_name);
+                self.keyboard_state
+                    .values_mut()
+                    .for_each(|time| *time = now);
+                return Ok(());
+            }
+        };
+        let mut stuck = Vec::new();
+        for (code, time) in self.keyboard_state.iter_mut() {
+            if now < *time + timeout {
+                continue;
+            }
+            match pressed.iter().any(|key| key.code() == *code) {
+                true => *time = now,
+                false => stuck.push(*code),
+            }
+        }
+        for code in stuck {
+            warn!(
+                "Key {:?} of {} held for {}ms without autorepeat, releasing",
+                KeyCode::new(code),
+                self.keyboard_name,
+                timeout.as_millis()
+            );
+            self.keyboard_state.remove(&code);
+            self.release(code).await?;
+        }
+        self.handle_pending_grab().await
+    }
+
+    /// Grabs/ungrabs the device, if requested before and all keys are released
+    async fn handle_pending_grab(&mut self) -> maiko::Result<()> {
+        if let Some(mode) = self.should_handle_grab
+            && self.keyboard_state.is_empty()
+        {
+            self.toggle_grabbing(&mode).await?;
+        }
+        Ok(())
     }
 
-    fn toggle_grabbing(&mut self, mode: &Mode) {
+    async fn toggle_grabbing(&mut self, mode: &Mode) -> maiko::Result<()> {
+        if !self.input.is_connected() {
+            return Ok(());
+        }
+        self.reconcile().await?;
+        let mode = match self.state.config().keyboard_role(&self.keyboard_name) {
+            KeyboardRole::PassThrough => mode,
+            KeyboardRole::MacroPad => &Mode::PassThrough,
+            KeyboardRole::InAppOnly => &Mode::InApp,
+        };
         debug!(
             "Toggling device grabbing: switching to {mode}, keys currently pressed: {:?}",
             self.keyboard_state
@@ -95,6 +278,7 @@ impl<D: EventDevice> KeyScanner<D> {
         } else {
             self.should_handle_grab = Some(*mode);
         }
+        Ok(())
     }
 
     fn grab(&mut self) {
@@ -124,14 +308,29 @@ impl<D: EventDevice> maiko::Actor for KeyScanner<D> {
     type Event = CharonEvent;
 
     async fn on_start(&mut self) -> maiko::Result<()> {
-        self.toggle_grabbing(&self.state.mode().await);
-        Ok(())
+        if !self.input.is_connected() {
+            self.connect();
+        }
+        let mode = self.state.mode().await;
+        self.toggle_grabbing(&mode).await
     }
 
     async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
         match envelope.event() {
             CharonEvent::ModeChange(mode) => {
-                self.toggle_grabbing(mode);
+                self.toggle_grabbing(mode).await?;
+            }
+            CharonEvent::KeyboardAttached(name) if *name == self.keyboard_name => {
+                if !self.input.is_connected() && self.connect() {
+                    let mode = self.state.mode().await;
+                    self.toggle_grabbing(&mode).await?;
+                }
+            }
+            CharonEvent::KeyboardDetached(name) if *name == self.keyboard_name => {
+                if self.input.is_connected() {
+                    info!("Keyboard disconnected: {}", self.keyboard_name);
+                    self.disconnect().await?;
+                }
             }
             other => {
                 debug!("Unhandled event: {:?}", other);
@@ -141,15 +340,34 @@ impl<D: EventDevice> maiko::Actor for KeyScanner<D> {
     }
 
     async fn step(&mut self) -> maiko::Result<StepAction> {
-        while let Some(event) = self.input.next_event().await {
+        if !self.input.is_connected() {
+            return Ok(StepAction::AwaitEvent);
+        }
+
+        loop {
+            let event = match self.stuck_key_deadline() {
+                Some(deadline) => tokio::select! {
+                    event = self.input.next_event() => event,
+                    _ = sleep_until(deadline) => {
+                        self.check_held_keys().await?;
+                        continue;
+                    }
+                },
+                None => self.input.next_event().await,
+            };
+            let Some(event) = event else {
+                break;
+            };
             self.handle_device_event(event).await?;
 
             // grab/ungrab only when all keys are released
-            if self.should_handle_grab.is_some() && self.keyboard_state.is_empty() {
-                if let Some(mode) = self.should_handle_grab {
-                    self.toggle_grabbing(&mode);
-                }
-            }
+            self.handle_pending_grab().await?;
+        }
+
+        if !self.input.is_connected() {
+            warn!("Lost connection with keyboard {}", self.keyboard_name);
+            self.disconnect().await?;
+            return Ok(StepAction::AwaitEvent);
         }
         Ok(StepAction::Yield)
     }
@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use crate::domain::{ActorState, CharonEvent};
+use maiko::{Context, Envelope, Meta, StepAction};
+use std::{io, sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+
+use super::ReportBuffer;
+use crate::port::HIDDevice;
+
+/// Error returned by the gadget (f_hid) when USB is not configured,
+/// i.e. the host is off, suspended or the cable is disconnected.
+const ESHUTDOWN: i32 = 108;
+
+/// Returns true if the error means that the host can't receive reports at the moment
+/// (as opposed to errors that are specific to a single report).
+fn is_host_unavailable(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::BrokenPipe | io::ErrorKind::NotConnected
+    ) || err.raw_os_error() == Some(ESHUTDOWN)
+}
+
+/// Writes HID reports to the host (via USB gadget device).
+/// When the host becomes unavailable, the reports are kept in a bounded [`ReportBuffer`]
+/// (according to the configured policy) and replayed once the host is back.
+pub struct KeyWriter<D: HIDDevice> {
+    ctx: Context<CharonEvent>,
+    state: ActorState,
+    device: D,
+    prev_sender: Arc<str>,
+    buffer: ReportBuffer,
+    host_available: bool,
+    retry_interval: Duration,
+}
+
+impl<D: HIDDevice> KeyWriter<D> {
+    pub fn new(ctx: Context<CharonEvent>, state: ActorState, device: D) -> Self {
+        let config = state.config();
+        Self {
+            ctx,
+            state: state.clone(),
+            device,
+            prev_sender: "".into(),
+            buffer: ReportBuffer::new(config.report_buffer_policy, config.report_buffer_size),
+            host_available: true,
+            retry_interval: Duration::from_millis(config.host_retry_interval),
+        }
+    }
+
+    fn send_report(&mut self, report: &[u8; 8], sender: &str) {
+        if self.prev_sender.as_ref() != sender {
+            self.reset();
+            self.prev_sender = Arc::from(sender);
+        }
+        self.write(report);
+    }
+
+    fn reset(&mut self) {
+        if !self.buffer.is_empty() {
+            self.buffer.push(&[0u8; 8]);
+        } else if let Err(err) = self.device.reset() {
+            self.handle_write_error(err, &[0u8; 8]);
+        }
+    }
+
+    /// Sends report to the device, unless there are some pending reports already
+    /// (in such case the report is queued to preserve the order).
+    fn write(&mut self, report: &[u8; 8]) {
+        if !self.buffer.is_empty() {
+            self.buffer.push(report);
+            return;
+        }
+        match self.device.send_report(report) {
+            Ok(()) => self.set_host_available(true),
+            Err(err) => self.handle_write_error(err, report),
+        }
+    }
+
+    fn handle_write_error(&mut self, err: io::Error, report: &[u8; 8]) {
+        if is_host_unavailable(&err) {
+            self.set_host_available(false);
+            self.buffer.push(report);
+        } else {
+            error!("Error while sending HID report: {err}");
+        }
+    }
+
+    /// Replays buffered reports. Stops at first failure, leaving the remaining
+    /// reports in the buffer. The replay is always followed by an empty report,
+    /// which releases all keys on the host side and - when the buffer is empty -
+    /// works as a probe of host availability.
+    fn flush(&mut self) {
+        let mut count = 0;
+        while let Some(report) = self.buffer.front() {
+            match self.device.send_report(report) {
+                Ok(()) => {
+                    self.buffer.pop();
+                    count += 1;
+                }
+                Err(err) if is_host_unavailable(&err) => return,
+                Err(err) => {
+                    error!("Error while replaying HID report: {err}");
+                    self.buffer.pop();
+                }
+            }
+        }
+        match self.device.reset() {
+            Err(err) if is_host_unavailable(&err) => return,
+            Err(err) => error!("Error reseting HID device: {err}"),
+            Ok(()) => {}
+        }
+        if count > 0 {
+            info!("Replayed {count} buffered HID reports");
+        }
+        self.set_host_available(true);
+    }
+
+    fn set_host_available(&mut self, available: bool) {
+        if self.host_available == available {
+            return;
+        }
+        self.host_available = available;
+        if available {
+            let dropped = self.buffer.take_dropped();
+            info!("Host is available again ({dropped} reports dropped while unavailable)");
+        } else {
+            warn!("Host is unavailable, HID reports will be buffered");
+        }
+    }
+
+    /// Reports the report as sent, correlated with the input event it was produced from
+    /// (so the whole key-to-report time can be measured), and the latency trace of the event.
+    #[inline]
+    async fn send_telemetry(&mut self, meta: &Meta) -> maiko::Result<()> {
+        let Some(correlation_id) = meta.correlation_id() else {
+            return Ok(());
+        };
+        self.ctx
+            .send_with_correlation(CharonEvent::ReportSent, correlation_id)
+            .await?;
+        if let Some(trace) = self.state.tracer().finish(correlation_id, "hid_write") {
+            self.ctx.send(CharonEvent::LatencyTrace(trace)).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: HIDDevice> maiko::Actor for KeyWriter<D> {
+    type Event = CharonEvent;
+
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        match envelope.event() {
+            CharonEvent::HidReport(report) => {
+                if let Some(id) = envelope.meta().correlation_id() {
+                    self.state.tracer().mark(id, "key_writer");
+                }
+                self.send_report(report, envelope.meta().actor_name());
+                self.send_telemetry(envelope.meta()).await?;
+            }
+            CharonEvent::ModeChange(_) => self.reset(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn step(&mut self) -> maiko::Result<StepAction> {
+        if !self.host_available {
+            self.flush();
+        }
+        if self.host_available {
+            Ok(StepAction::AwaitEvent)
+        } else {
+            Ok(StepAction::Backoff(self.retry_interval))
+        }
+    }
+
+    async fn on_shutdown(&mut self) -> maiko::Result<()> {
+        if !self.buffer.is_empty() {
+            warn!(
+                "Shutting down with {} undelivered HID reports",
+                self.buffer.len()
+            );
+        }
+        if let Err(err) = self.device.reset() {
+            error!("Error reseting HID device: {err}");
+        }
+        Ok(())
+    }
+}
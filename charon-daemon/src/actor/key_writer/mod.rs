@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+mod key_writer_actor;
+mod report_buffer;
+
+pub use key_writer_actor::KeyWriter;
+pub use report_buffer::ReportBuffer;
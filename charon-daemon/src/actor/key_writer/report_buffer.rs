@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::VecDeque;
+
+use crate::{config::ReportBufferPolicy, domain::Modifiers};
+
+/// Returns true if the report contains only keys that produce text (letters, numbers,
+/// punctuation, space, enter, tab and backspace), optionally with shift.
+/// Empty report (all keys released) is considered a typing report as well.
+fn is_typing_report(report: &[u8; 8]) -> bool {
+    let shift = Modifiers::LEFT_SHIFT.value() | Modifiers::RIGHT_SHIFT.value();
+    if report[0] & !shift != 0 {
+        return false;
+    }
+    report[2..].iter().all(|&key| {
+        matches!(
+            key,
+            0x00            // no key
+            | 0x04..=0x28   // a-z, 1-0, enter
+            | 0x2A..=0x38   // backspace, tab, space, punctuation
+            | 0x64 // non-US backslash
+        )
+    })
+}
+
+/// Bounded FIFO of HID reports that couldn't be delivered to the host.
+/// Which reports are accepted depends on the [`ReportBufferPolicy`].
+/// When the buffer is full, new reports are rejected, so the replayed text
+/// is always a prefix of what has been typed.
+pub struct ReportBuffer {
+    policy: ReportBufferPolicy,
+    capacity: usize,
+    reports: VecDeque<[u8; 8]>,
+    dropped: usize,
+}
+
+impl ReportBuffer {
+    pub fn new(policy: ReportBufferPolicy, capacity: usize) -> Self {
+        Self {
+            policy,
+            capacity,
+            reports: VecDeque::with_capacity(capacity),
+            dropped: 0,
+        }
+    }
+
+    /// Adds report to the buffer. Returns false if the report has been rejected
+    /// (due to policy or buffer being full).
+    pub fn push(&mut self, report: &[u8; 8]) -> bool {
+        let accepted = match self.policy {
+            ReportBufferPolicy::Drop => false,
+            ReportBufferPolicy::Buffer => true,
+            ReportBufferPolicy::TypingOnly => is_typing_report(report),
+        };
+        if !accepted {
+            self.dropped += 1;
+            return false;
+        }
+
+        // key repeats produce identical reports, that are meaningless for the host
+        if self.reports.back() == Some(report) {
+            return true;
+        }
+
+        if self.reports.len() >= self.capacity {
+            self.dropped += 1;
+            return false;
+        }
+
+        self.reports.push_back(*report);
+        true
+    }
+
+    pub fn front(&self) -> Option<&[u8; 8]> {
+        self.reports.front()
+    }
+
+    pub fn pop(&mut self) -> Option<[u8; 8]> {
+        self.reports.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reports.is_empty()
+    }
+
+    /// Returns number of rejected reports and resets the counter.
+    pub fn take_dropped(&mut self) -> usize {
+        std::mem::take(&mut self.dropped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KEY_A: [u8; 8] = [0, 0, 0x04, 0, 0, 0, 0, 0];
+    const SHIFT_A: [u8; 8] = [2, 0, 0x04, 0, 0, 0, 0, 0];
+    const CTRL_A: [u8; 8] = [1, 0, 0x04, 0, 0, 0, 0, 0];
+    const KEY_F1: [u8; 8] = [0, 0, 0x3A, 0, 0, 0, 0, 0];
+    const EMPTY: [u8; 8] = [0; 8];
+
+    #[test]
+    fn test_drop_policy_rejects_everything() {
+        let mut buffer = ReportBuffer::new(ReportBufferPolicy::Drop, 8);
+        assert!(!buffer.push(&KEY_A));
+        assert!(!buffer.push(&EMPTY));
+        assert!(buffer.is_empty());
+        assert_eq!(2, buffer.take_dropped());
+        assert_eq!(0, buffer.take_dropped());
+    }
+
+    #[test]
+    fn test_buffer_policy_keeps_order() {
+        let mut buffer = ReportBuffer::new(ReportBufferPolicy::Buffer, 8);
+        buffer.push(&CTRL_A);
+        buffer.push(&EMPTY);
+        buffer.push(&KEY_F1);
+        assert_eq!(3, buffer.len());
+        assert_eq!(Some(CTRL_A), buffer.pop());
+        assert_eq!(Some(EMPTY), buffer.pop());
+        assert_eq!(Some(KEY_F1), buffer.pop());
+        assert_eq!(None, buffer.pop());
+    }
+
+    #[test]
+    fn test_typing_only_policy() {
+        let mut buffer = ReportBuffer::new(ReportBufferPolicy::TypingOnly, 8);
+        assert!(buffer.push(&KEY_A));
+        assert!(buffer.push(&EMPTY));
+        assert!(buffer.push(&SHIFT_A));
+        assert!(!buffer.push(&CTRL_A));
+        assert!(!buffer.push(&KEY_F1));
+        assert_eq!(3, buffer.len());
+        assert_eq!(2, buffer.take_dropped());
+    }
+
+    #[test]
+    fn test_repeated_reports_are_collapsed() {
+        let mut buffer = ReportBuffer::new(ReportBufferPolicy::Buffer, 8);
+        buffer.push(&KEY_A);
+        buffer.push(&KEY_A);
+        buffer.push(&KEY_A);
+        buffer.push(&EMPTY);
+        buffer.push(&KEY_A);
+        assert_eq!(3, buffer.len());
+    }
+
+    #[test]
+    fn test_full_buffer_rejects_new_reports() {
+        let mut buffer = ReportBuffer::new(ReportBufferPolicy::Buffer, 2);
+        assert!(buffer.push(&KEY_A));
+        assert!(buffer.push(&EMPTY));
+        assert!(!buffer.push(&SHIFT_A));
+        assert_eq!(2, buffer.len());
+        assert_eq!(Some(&KEY_A), buffer.front());
+        assert_eq!(1, buffer.take_dropped());
+    }
+}
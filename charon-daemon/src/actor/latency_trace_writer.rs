@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{path::Path, time::Duration};
+
+use maiko::{Envelope, StepAction};
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+};
+use tracing::{error, info};
+
+use crate::{domain::CharonEvent, error::CharonError};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Dumps latency traces of key events to a file, one JSON object per line
+/// (see `charond --latency-trace`).
+pub struct LatencyTraceWriter {
+    file: BufWriter<File>,
+    count: usize,
+}
+
+impl LatencyTraceWriter {
+    pub async fn new(path: &Path) -> Result<Self, CharonError> {
+        let file = File::create(path).await?;
+        info!("Writing latency traces to {}", path.display());
+        Ok(Self {
+            file: BufWriter::new(file),
+            count: 0,
+        })
+    }
+}
+
+impl maiko::Actor for LatencyTraceWriter {
+    type Event = CharonEvent;
+
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        if let CharonEvent::LatencyTrace(trace) = envelope.event() {
+            let mut line = serde_json::to_string(trace).unwrap_or_default();
+            line.push('\n');
+            if let Err(err) = self.file.write_all(line.as_bytes()).await {
+                error!("Couldn't write latency trace: {err}");
+            }
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    async fn step(&mut self) -> maiko::Result<StepAction> {
+        if let Err(err) = self.file.flush().await {
+            error!("Couldn't flush latency traces: {err}");
+        }
+        Ok(StepAction::Backoff(FLUSH_INTERVAL))
+    }
+
+    async fn on_shutdown(&mut self) -> maiko::Result<()> {
+        if let Err(err) = self.file.flush().await {
+            error!("Couldn't flush latency traces: {err}");
+        }
+        info!("{} latency traces written", self.count);
+        Ok(())
+    }
+}
@@ -1,19 +1,25 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+mod device_monitor;
 pub mod ipc_bridge;
 mod key_scanner;
 mod key_writer;
+mod latency_trace_writer;
 mod pipeline;
 mod power_manager;
 mod qmk;
 mod telemetry;
+mod transcriber;
 mod typing_stats;
 mod typist;
 
+pub use device_monitor::DeviceMonitor;
 pub use key_scanner::KeyScanner;
 pub use key_writer::KeyWriter;
+pub use latency_trace_writer::LatencyTraceWriter;
 pub use pipeline::Pipeline;
 pub use power_manager::PowerManager;
 pub use qmk::QMK;
 pub use telemetry::Telemetry;
+pub use transcriber::Transcriber;
 pub use typing_stats::TypingStats;
 pub use typist::Typist;
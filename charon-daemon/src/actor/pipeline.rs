@@ -1,49 +1,137 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use crate::domain::CharonEvent;
-use maiko::{Context, Envelope, Meta};
+use std::collections::HashMap;
+
+use crate::domain::{CharonEvent, stats::LatencyTracer};
+use maiko::{Context, Envelope, EventId, Meta, StepAction};
+use tokio::time::Instant;
 
 use crate::domain::traits::Processor;
 
+type Processors = Vec<Box<dyn Processor + Send + Sync>>;
+
+/// Passes events through a chain of processors and publishes the result.
+/// Key events of keyboards with a dedicated route (see `with_route`) go through
+/// that route's processors; all other events use the default chain.
+/// Processors may also ask to be called back at a given time (see `Processor::deadline`).
+/// With latency tracing enabled, time spent in each processor is recorded.
 pub struct Pipeline {
     ctx: Context<CharonEvent>,
-    processors: Vec<Box<dyn Processor + Send + Sync>>,
+    processors: Processors,
+    routes: HashMap<String, Processors>,
+    tracer: LatencyTracer,
 }
 
 impl Pipeline {
-    pub fn new(
-        ctx: Context<CharonEvent>,
-        processors: Vec<Box<dyn Processor + Send + Sync>>,
-    ) -> Self {
-        Self { ctx, processors }
+    pub fn new(ctx: Context<CharonEvent>, processors: Processors) -> Self {
+        Self {
+            ctx,
+            processors,
+            routes: HashMap::new(),
+            tracer: LatencyTracer::default(),
+        }
     }
 
-    async fn process(&mut self, event: &CharonEvent, meta: &Meta) -> maiko::Result<()> {
-        let mut events = vec![event.clone()];
+    pub fn with_tracer(mut self, tracer: LatencyTracer) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Adds a dedicated chain of processors for key events of the keyboard with given alias
+    pub fn with_route(mut self, keyboard: String, processors: Processors) -> Self {
+        self.routes.insert(keyboard, processors);
+        self
+    }
 
-        for proc in &mut self.processors.iter_mut() {
-            let mut next_events = Vec::new();
-            for event in events {
-                let mut out = proc.process(event, meta.clone()).await;
-                next_events.append(&mut out);
+    fn processors_for<'a>(
+        processors: &'a mut Processors,
+        routes: &'a mut HashMap<String, Processors>,
+        event: &CharonEvent,
+    ) -> &'a mut Processors {
+        match event {
+            CharonEvent::KeyPress(_, keyboard) | CharonEvent::KeyRelease(_, keyboard) => {
+                match routes.get_mut(keyboard) {
+                    Some(route) => route,
+                    None => processors,
+                }
             }
-            events = next_events;
+            _ => processors,
         }
+    }
 
+    async fn process(&mut self, event: &CharonEvent, meta: &Meta) -> maiko::Result<()> {
         let correlation_id = meta.correlation_id().unwrap_or(meta.id());
-        for event in events {
-            self.ctx
-                .send_envelope(Envelope::<CharonEvent>::with_correlation(
-                    event,
-                    self.ctx.actor_id().clone(),
-                    correlation_id,
-                ))
-                .await?;
+        self.tracer.mark(correlation_id, "pipeline");
+        let chain = Self::processors_for(&mut self.processors, &mut self.routes, event);
+        let trace = Some((&self.tracer, correlation_id));
+        let events = run_chain(chain, vec![event.clone()], meta, trace).await;
+        self.publish(events, Some(correlation_id)).await
+    }
+
+    /// Calls back processors whose deadline has passed. The events they produce
+    /// go through the remaining processors of the chain.
+    async fn handle_timeouts(&mut self, now: Instant) -> maiko::Result<()> {
+        let meta = Meta::new(self.ctx.actor_id().clone(), None);
+        let mut events = Vec::new();
+        for chain in std::iter::once(&mut self.processors).chain(self.routes.values_mut()) {
+            for idx in 0..chain.len() {
+                if chain[idx]
+                    .deadline()
+                    .is_some_and(|deadline| deadline <= now)
+                {
+                    let out = chain[idx].on_timeout().await;
+                    events.extend(run_chain(&mut chain[idx + 1..], out, &meta, None).await);
+                }
+            }
         }
+        self.publish(events, None).await
+    }
 
+    fn next_deadline(&self) -> Option<Instant> {
+        std::iter::once(&self.processors)
+            .chain(self.routes.values())
+            .flatten()
+            .filter_map(|proc| proc.deadline())
+            .min()
+    }
+
+    async fn publish(
+        &self,
+        events: Vec<CharonEvent>,
+        correlation_id: Option<EventId>,
+    ) -> maiko::Result<()> {
+        for event in events {
+            let envelope = match correlation_id {
+                Some(id) => Envelope::with_correlation(event, self.ctx.actor_id().clone(), id),
+                None => Envelope::new(event, self.ctx.actor_id().clone()),
+            };
+            self.ctx.send_envelope(envelope).await?;
+        }
         Ok(())
     }
 }
 
+/// Passes events through the processors. If `trace` is given, the end of each processor
+/// is recorded in the trace.
+async fn run_chain(
+    processors: &mut [Box<dyn Processor + Send + Sync>],
+    mut events: Vec<CharonEvent>,
+    meta: &Meta,
+    trace: Option<(&LatencyTracer, EventId)>,
+) -> Vec<CharonEvent> {
+    for proc in processors.iter_mut() {
+        let mut next_events = Vec::new();
+        for event in events {
+            let mut out = proc.process(event, meta.clone()).await;
+            next_events.append(&mut out);
+        }
+        events = next_events;
+        if let Some((tracer, id)) = trace {
+            tracer.mark(id, proc.name());
+        }
+    }
+    events
+}
+
 impl maiko::Actor for Pipeline {
     type Event = CharonEvent;
 
@@ -51,4 +139,13 @@ impl maiko::Actor for Pipeline {
         self.process(envelope.event(), envelope.meta()).await?;
         Ok(())
     }
+
+    async fn step(&mut self) -> maiko::Result<StepAction> {
+        let now = Instant::now();
+        self.handle_timeouts(now).await?;
+        match self.next_deadline() {
+            Some(deadline) => Ok(StepAction::Backoff(deadline.saturating_duration_since(now))),
+            None => Ok(StepAction::AwaitEvent),
+        }
+    }
 }
@@ -1,24 +1,80 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use crate::domain::{CharonEvent, qmk::QMKEvent};
-use maiko::{Context, StepAction};
-use tracing::debug;
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use crate::{domain::ActorState, port::QmkDevice};
+use crate::domain::{
+    CharonEvent,
+    qmk::{QMKEvent, QMKKeymap, QMKKeymapBuilder, QMKRequest},
+};
+use maiko::{Context, Envelope, EventId, StepAction};
+use tokio::time::{Instant, timeout_at};
+use tracing::{debug, error, info, warn};
 
-#[allow(dead_code)]
-pub struct QMK<Q: QmkDevice> {
+use crate::{domain::ActorState, port::KeyboardSideChannel};
+
+/// Request sent to the keyboard, waiting for the response.
+/// Requests with no correlation id are sent by the actor itself (i.e. to download keymap)
+/// and their responses are not published.
+struct PendingRequest {
+    request: QMKRequest,
+    correlation_id: Option<EventId>,
+    deadline: Instant,
+}
+
+/// Handles side channel of a single keyboard (keyboard group with `raw_hid_enabled`
+/// or `serial_port`). All events are tagged with the group alias.
+pub struct QMK<Q: KeyboardSideChannel> {
     ctx: Context<CharonEvent>,
     state: ActorState,
+    alias: String,
     device: Q,
+    request_timeout: Duration,
+    reconnect_interval: Duration,
+    pending: VecDeque<PendingRequest>,
+    keymap: Option<QMKKeymap>,
+    keymap_builder: Option<QMKKeymapBuilder>,
 }
 
-#[allow(dead_code)]
-impl<Q: QmkDevice> QMK<Q> {
-    pub fn new(ctx: Context<CharonEvent>, state: ActorState, device: Q) -> Self {
-        Self { ctx, state, device }
+impl<Q: KeyboardSideChannel> QMK<Q> {
+    pub fn new(ctx: Context<CharonEvent>, state: ActorState, alias: String, device: Q) -> Self {
+        let request_timeout = Duration::from_millis(state.config().qmk_request_timeout);
+        let reconnect_interval = Duration::from_millis(state.config().qmk_reconnect_interval);
+        Self {
+            ctx,
+            state,
+            alias,
+            device,
+            request_timeout,
+            reconnect_interval,
+            pending: VecDeque::new(),
+            keymap: None,
+            keymap_builder: None,
+        }
     }
 
     async fn process_qmk_event(&mut self, qmk_event: QMKEvent) -> maiko::Result {
+        if let Some(pos) = self
+            .pending
+            .iter()
+            .position(|p| p.request.is_response(&qmk_event))
+            && let Some(pending) = self.pending.remove(pos)
+        {
+            return match pending.correlation_id {
+                Some(id) => {
+                    self.ctx
+                        .send_with_correlation(
+                            CharonEvent::QMKEvent(qmk_event, self.alias.clone()),
+                            id,
+                        )
+                        .await
+                }
+                None => self.process_keymap_response(qmk_event).await,
+            };
+        }
+
         let event = match qmk_event {
             QMKEvent::ToggleMode => {
                 let new_mode = self.state.mode().await.toggle();
@@ -30,19 +86,188 @@ impl<Q: QmkDevice> QMK<Q> {
                 self.state.set_mode(mode).await;
                 CharonEvent::ModeChange(mode)
             }
-            e => CharonEvent::QMKEvent(e),
+            e => CharonEvent::QMKEvent(e, self.alias.clone()),
         };
         self.ctx.send(event).await
     }
+
+    async fn send_request(&mut self, request: &QMKRequest, correlation_id: Option<EventId>) {
+        debug!("Sending QMK request to {}: {request:?}", self.alias);
+        if let Err(err) = self.device.send_request(request).await {
+            warn!("Couldn't send QMK request {request:?}: {err}");
+            return;
+        }
+        self.pending.push_back(PendingRequest {
+            request: request.clone(),
+            correlation_id,
+            deadline: Instant::now() + self.request_timeout,
+        });
+    }
+
+    async fn expire_requests(&mut self) -> maiko::Result {
+        let now = Instant::now();
+        while self.pending.front().is_some_and(|p| p.deadline <= now)
+            && let Some(pending) = self.pending.pop_front()
+        {
+            warn!("QMK request timed out: {:?}", pending.request);
+            match pending.correlation_id {
+                Some(id) => {
+                    self.ctx
+                        .send_with_correlation(
+                            CharonEvent::QMKRequestTimeout(pending.request, self.alias.clone()),
+                            id,
+                        )
+                        .await?
+                }
+                None => {
+                    if self.keymap_builder.take().is_some() {
+                        error!("Keymap download aborted");
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> maiko::Result<StepAction> {
+        if let Err(err) = self.device.connect().await {
+            debug!("{}: {err}", self.alias);
+            return Ok(StepAction::Backoff(self.reconnect_interval));
+        }
+        info!("QMK keyboard connected: {}", self.alias);
+        // Refresh keymap, as the keyboard could have been reflashed
+        self.send_request(&QMKRequest::KeyboardInfo, None).await;
+        Ok(StepAction::Continue)
+    }
+
+    fn disconnected(&mut self) {
+        warn!("QMK keyboard disconnected: {}", self.alias);
+        self.pending.clear();
+        self.keymap_builder = None;
+    }
+
+    async fn process_keymap_response(&mut self, qmk_event: QMKEvent) -> maiko::Result {
+        match qmk_event {
+            QMKEvent::KeyboardInfo { rows, cols, .. } if rows == 0 || cols == 0 => {
+                info!("Keyboard doesn't expose its matrix, keymap not downloaded");
+            }
+            QMKEvent::KeyboardInfo { rows, cols, layers } => {
+                info!("Downloading keymap: {layers} layers, {rows}x{cols} matrix");
+                self.keymap_builder = Some(QMKKeymapBuilder::new(rows, cols, layers));
+            }
+            QMKEvent::LayerChunk {
+                layer,
+                offset,
+                keycodes,
+            } => {
+                if let Some(builder) = self.keymap_builder.as_mut()
+                    && !builder.add_chunk(layer, offset, &keycodes)
+                {
+                    warn!("Unexpected layer chunk: layer {layer}, offset {offset}");
+                }
+            }
+            _ => {}
+        }
+
+        let Some(builder) = self.keymap_builder.as_ref() else {
+            return Ok(());
+        };
+        if let Some(request) = builder.next_request() {
+            self.send_request(&request, None).await;
+            return Ok(());
+        }
+
+        if let Some(builder) = self.keymap_builder.take() {
+            let keymap = builder.build(&self.alias, self.layout_matrix());
+            info!("Keymap downloaded: {} layers", keymap.layer_count());
+            write_keymap(&self.keymap_file(), &keymap).await;
+            self.keymap = Some(keymap.clone());
+            self.ctx
+                .send(CharonEvent::QMKKeymap(keymap, self.alias.clone()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn layout_matrix(&self) -> Option<&[[u8; 2]]> {
+        self.state
+            .config()
+            .keyboard_group(&self.alias)
+            .and_then(|group| group.layout_matrix.as_deref())
+    }
+
+    fn keymap_file(&self) -> PathBuf {
+        self.state
+            .config()
+            .qmk_keymap_dir
+            .join(format!("{}.json", self.alias))
+    }
+}
+
+async fn load_keymap(file: &Path) -> eyre::Result<QMKKeymap> {
+    let data = tokio::fs::read_to_string(file).await?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+async fn write_keymap(file: &Path, keymap: &QMKKeymap) {
+    if let Ok(txt) = serde_json::to_string(keymap)
+        && let Err(err) = tokio::fs::write(file, txt).await
+    {
+        error!("Couldn't write keymap file: {err}");
+    }
 }
 
-impl<Q: QmkDevice> maiko::Actor for QMK<Q> {
+impl<Q: KeyboardSideChannel> maiko::Actor for QMK<Q> {
     type Event = CharonEvent;
 
+    async fn on_start(&mut self) -> maiko::Result {
+        match load_keymap(&self.keymap_file()).await {
+            Ok(keymap) => self.keymap = Some(keymap),
+            Err(err) => warn!("Couldn't load cached keymap of {}: {err}", self.alias),
+        }
+        Ok(())
+    }
+
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        match envelope.event() {
+            CharonEvent::QMKRequest(request, alias) if *alias == self.alias => {
+                self.send_request(request, Some(envelope.meta().id())).await
+            }
+            CharonEvent::GetQMKKeymap(alias) if *alias == self.alias => {
+                if let Some(keymap) = &self.keymap {
+                    self.ctx
+                        .send_with_correlation(
+                            CharonEvent::QMKKeymap(keymap.clone(), self.alias.clone()),
+                            envelope.meta().id(),
+                        )
+                        .await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     async fn step(&mut self) -> maiko::Result<StepAction> {
-        while let Some(qmk_event) = self.device.read_event().await? {
-            self.process_qmk_event(qmk_event).await?;
+        if !self.device.is_connected() {
+            return self.connect().await;
+        }
+
+        // Requests are queued in order of sending, so the first one expires first
+        let read = match self.pending.front() {
+            Some(pending) => timeout_at(pending.deadline, self.device.read_event()).await,
+            None => Ok(self.device.read_event().await),
+        };
+        match read {
+            Ok(Ok(Some(qmk_event))) => self.process_qmk_event(qmk_event).await?,
+            Ok(Ok(None)) => return Ok(StepAction::Yield),
+            Ok(Err(err)) if !self.device.is_connected() => {
+                debug!("{err}");
+                self.disconnected();
+            }
+            Ok(Err(err)) => warn!("Invalid message from {}: {err}", self.alias),
+            Err(_elapsed) => self.expire_requests().await?,
         }
-        Ok(StepAction::Yield)
+        Ok(StepAction::Continue)
     }
 }
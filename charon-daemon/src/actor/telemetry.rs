@@ -1,14 +1,20 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use crate::{domain::CharonEvent, port::Metrics};
+use crate::{
+    domain::{CharonEvent, qmk::QMKEvent},
+    port::Metrics,
+};
 use lru_time_cache::LruCache;
 use maiko::{Envelope, StepAction};
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 use tracing::warn;
 
 pub struct Telemetry<M: Metrics> {
     events: LruCache<u128, u64>,
     metrics: M,
     push_interval: Duration,
+    enabled: bool,
+    /// Active layer of each QMK keyboard (by group alias)
+    layers: HashMap<String, u8>,
 }
 
 impl<M: Metrics> Telemetry<M> {
@@ -17,6 +23,8 @@ impl<M: Metrics> Telemetry<M> {
             events: LruCache::with_expiry_duration_and_capacity(Duration::from_secs(10), 1024),
             metrics,
             push_interval: Duration::from_secs(15),
+            enabled: true,
+            layers: HashMap::new(),
         }
     }
 }
@@ -26,6 +34,13 @@ impl<M: Metrics> maiko::Actor for Telemetry<M> {
 
     async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result {
         let meta = envelope.meta();
+        if let CharonEvent::TelemetryEnabled(enabled) = envelope.event() {
+            self.enabled = *enabled;
+            self.events.clear();
+        }
+        if !self.enabled {
+            return Ok(());
+        }
         match envelope.event() {
             CharonEvent::KeyPress(key, keyboard) => {
                 self.events.insert(meta.id(), meta.timestamp());
@@ -34,6 +49,10 @@ impl<M: Metrics> maiko::Actor for Telemetry<M> {
             CharonEvent::KeyRelease(..) => {
                 self.events.insert(meta.id(), meta.timestamp());
             }
+            CharonEvent::KeyFiltered(key, keyboard, reason) => {
+                self.metrics
+                    .register_filtered_key_event(key, keyboard, *reason);
+            }
             CharonEvent::ReportSent => {
                 if let Some(ref source_id) = meta.correlation_id() {
                     if let Some(timestamp) = self.events.remove(source_id) {
@@ -48,6 +67,16 @@ impl<M: Metrics> maiko::Actor for Telemetry<M> {
                     );
                 }
             }
+            CharonEvent::QMKEvent(QMKEvent::LayerChange(layer, false), alias) => {
+                self.layers.insert(alias.clone(), *layer);
+            }
+            CharonEvent::QMKEvent(QMKEvent::KeyEvent(record), alias) if record.pressed => {
+                let layer = self.layers.get(alias).copied().unwrap_or_default();
+                self.metrics.register_matrix_key_event(record, alias, layer);
+            }
+            CharonEvent::LatencyTrace(trace) => {
+                self.metrics.register_latency_trace(trace);
+            }
             CharonEvent::CurrentStats(stats) => {
                 self.metrics.register_wpm(stats.wpm);
             }
@@ -57,7 +86,9 @@ impl<M: Metrics> maiko::Actor for Telemetry<M> {
     }
 
     async fn step(&mut self) -> maiko::Result<StepAction> {
-        if let Err(e) = self.metrics.flush().await {
+        if self.enabled
+            && let Err(e) = self.metrics.flush().await
+        {
             tracing::error!("Sending telemetry failed: {e}");
         }
         Ok(StepAction::Backoff(self.push_interval))
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use maiko::{Context, Envelope};
+use tracing::{error, info};
+
+use crate::{
+    domain::{ActorState, CharonEvent, ReverseKeymap, TextDecoder},
+    port::KeymapLoader,
+};
+
+/// Turns HID reports sent to the host (pass-through keys and text typed by `Typist`)
+/// into `TextTyped` events: the text the host will most likely see, according to
+/// the keymap of the active host profile (see `TextDecoder`).
+pub struct Transcriber<L: KeymapLoader> {
+    ctx: Context<CharonEvent>,
+    state: ActorState,
+    decoder: TextDecoder,
+    keymap_loader: L,
+}
+
+impl<L: KeymapLoader> Transcriber<L> {
+    pub fn new(
+        ctx: Context<CharonEvent>,
+        state: ActorState,
+        keymap: ReverseKeymap,
+        keymap_loader: L,
+    ) -> Self {
+        Self {
+            ctx,
+            state,
+            decoder: TextDecoder::new(keymap),
+            keymap_loader,
+        }
+    }
+
+    async fn switch_host_profile(&mut self, name: &str) {
+        let Some(profile) = self.state.config().host_profiles.get(name) else {
+            return error!("Unknown host profile: {name}");
+        };
+        match self.keymap_loader.load_keymap(&profile.keymap).await {
+            Ok(keymap) => {
+                info!("Transcribing with keymap {}", profile.keymap);
+                self.decoder.set_keymap((&keymap).into());
+            }
+            Err(err) => error!("Couldn't load keymap {}: {err}", profile.keymap),
+        }
+    }
+}
+
+impl<L: KeymapLoader + Send + Sync + 'static> maiko::Actor for Transcriber<L> {
+    type Event = CharonEvent;
+
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        match envelope.event() {
+            CharonEvent::HidReport(report) => {
+                let text: String = self.decoder.decode(report).into_iter().collect();
+                if !text.is_empty() {
+                    self.ctx.send(CharonEvent::TextTyped(text)).await?;
+                }
+            }
+            CharonEvent::HostProfileChange(name) => self.switch_host_profile(name).await,
+            _ => {}
+        }
+        Ok(())
+    }
+}
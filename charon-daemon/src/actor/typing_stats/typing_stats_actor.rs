@@ -1,11 +1,15 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use std::{path::Path, time::Duration};
+use std::{collections::HashMap, path::Path, time::Duration};
 
-use crate::domain::{CharonEvent, stats::CurrentStats};
+use crate::domain::{
+    CharonEvent,
+    qmk::QMKEvent,
+    stats::{CurrentStats, KeyHeatmap},
+};
 use crate::util::time::{is_today, next_midnight_instant};
 use maiko::{Context, Envelope, StepAction};
 use tokio::select;
-use tracing::error;
+use tracing::{error, warn};
 
 use super::WPMCounter;
 use crate::domain::ActorState;
@@ -16,6 +20,8 @@ pub struct TypingStats {
     wpm: WPMCounter,
     total_count: u64,
     today_count: u64,
+    /// Matrix-accurate key counts of QMK keyboards (by group alias)
+    heatmaps: HashMap<String, KeyHeatmap>,
     wpm_interval: tokio::time::Interval,
     save_interval: tokio::time::Interval,
 }
@@ -30,6 +36,7 @@ impl TypingStats {
             ctx,
             total_count: 0,
             today_count: 0,
+            heatmaps: HashMap::new(),
             wpm_interval: tokio::time::interval(wpm.period()),
             save_interval: tokio::time::interval(Duration::from_secs(
                 state.config().stats_save_interval,
@@ -62,6 +69,28 @@ impl TypingStats {
         }
     }
 
+    async fn load_heatmaps(&self, file: &Path) -> eyre::Result<HashMap<String, KeyHeatmap>> {
+        let data = tokio::fs::read_to_string(file).await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    async fn write_heatmaps(&self, file: &Path) {
+        if self.heatmaps.is_empty() {
+            return;
+        }
+        if let Ok(txt) = serde_json::to_string(&self.heatmaps)
+            && let Err(err) = tokio::fs::write(file, txt).await
+        {
+            error!("Couldn't write heatmap file: {err}");
+        }
+    }
+
+    async fn save(&self) {
+        let config = self.state.config();
+        self.write_stats(&config.stats_file, self.stats()).await;
+        self.write_heatmaps(&config.heatmap_file).await;
+    }
+
     fn stats(&self) -> CurrentStats {
         CurrentStats::new(
             self.today_count,
@@ -86,14 +115,37 @@ impl maiko::Actor for TypingStats {
                 error!("Couldn't load stats file: {err}");
             }
         }
+        match self.load_heatmaps(&self.state.config().heatmap_file).await {
+            Ok(heatmaps) => self.heatmaps = heatmaps,
+            Err(err) => warn!("Couldn't load heatmap file: {err}"),
+        }
         Ok(())
     }
 
     async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result {
-        if let CharonEvent::KeyPress(key, _) = envelope.event() {
-            self.wpm.register_key(key);
-            self.total_count += 1;
-            self.today_count += 1;
+        match envelope.event() {
+            CharonEvent::KeyPress(key, _) => {
+                self.wpm.register_key(key);
+                self.total_count += 1;
+                self.today_count += 1;
+            }
+            // QMK keyboard sends its keys over HID too, so these are counted
+            // separately, not to bump the totals twice
+            CharonEvent::QMKEvent(QMKEvent::KeyEvent(record), alias) => {
+                self.heatmaps
+                    .entry(alias.clone())
+                    .or_default()
+                    .register(record);
+            }
+            CharonEvent::GetKeyHeatmaps => {
+                self.ctx
+                    .send_with_correlation(
+                        CharonEvent::KeyHeatmaps(self.heatmaps.clone()),
+                        envelope.meta().id(),
+                    )
+                    .await?;
+            }
+            _ => {}
         }
         Ok(())
     }
@@ -105,7 +157,7 @@ impl maiko::Actor for TypingStats {
                 self.ctx.send(CharonEvent::CurrentStats(self.stats())).await?;
             }
             _ = self.save_interval.tick() => {
-                self.write_stats(&self.state.config().stats_file, self.stats()).await;
+                self.save().await;
             }
             _ = tokio::time::sleep_until(next_midnight_instant()) => {
                 self.today_count = 0;
@@ -115,8 +167,7 @@ impl maiko::Actor for TypingStats {
     }
 
     async fn on_shutdown(&mut self) -> maiko::Result {
-        self.write_stats(&self.state.config().stats_file, self.stats())
-            .await;
+        self.save().await;
         Ok(())
     }
 }
@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+mod typing_job;
+mod typist_actor;
+
+pub use typing_job::TypingJob;
+pub use typist_actor::Typist;
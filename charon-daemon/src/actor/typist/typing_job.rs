@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::VecDeque;
+
+use deunicode::deunicode_char;
+use maiko::EventId;
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::domain::{HidReport, Keymap, Keystroke, Mode, TypingRhythm, TypingStep};
+
+const RELEASED: [u8; 8] = [0; 8];
+
+/// Minimal time between two progress reports of a job
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Text (or keys) requested to be typed by a single `Send*` event.
+/// Steps are turned into keystrokes one by one, so the job can be cancelled
+/// and report its progress at any time.
+pub struct TypingJob {
+    id: EventId,
+    steps: VecDeque<TypingStep>,
+    total: usize,
+    rhythm: TypingRhythm,
+    /// Mode in which the job was requested: typing is interrupted when it changes
+    mode: Mode,
+    /// Keystrokes of the current step
+    keystrokes: VecDeque<Keystroke>,
+    /// Pause of the current step (see `TypingStep::Sleep`)
+    pause: Option<Duration>,
+    /// Whether the last report sent to the host left some keys pressed
+    pressed: bool,
+    /// File to remove when the job ends (see `CharonEvent::SendFile`)
+    file_to_remove: Option<String>,
+    reported_at: Option<Instant>,
+}
+
+impl TypingJob {
+    pub fn new(id: EventId, steps: Vec<TypingStep>, rhythm: TypingRhythm, mode: Mode) -> Self {
+        Self {
+            id,
+            total: steps.len(),
+            steps: steps.into(),
+            rhythm,
+            mode,
+            keystrokes: VecDeque::new(),
+            pause: None,
+            pressed: false,
+            file_to_remove: None,
+            reported_at: None,
+        }
+    }
+
+    pub fn with_file_to_remove(mut self, path: String) -> Self {
+        self.file_to_remove = Some(path);
+        self
+    }
+
+    pub fn id(&self) -> EventId {
+        self.id
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn file_to_remove(&self) -> Option<&str> {
+        self.file_to_remove.as_deref()
+    }
+
+    /// Next keystroke of the current step, to be confirmed by `keystroke_sent`
+    pub fn keystroke(&self) -> Option<Keystroke> {
+        self.keystrokes.front().copied()
+    }
+
+    pub fn keystroke_sent(&mut self) {
+        if let Some((report, _)) = self.keystrokes.pop_front() {
+            self.pressed = report != RELEASED;
+        }
+    }
+
+    pub fn take_pause(&mut self) -> Option<Duration> {
+        self.pause.take()
+    }
+
+    /// Turns the next step into keystrokes, returns false if all steps have been typed
+    pub fn advance(&mut self, keymap: &Keymap) -> bool {
+        let keystrokes = match self.steps.pop_front() {
+            Some(TypingStep::Char(c)) => match char_report(keymap, c) {
+                Some(report) => self.rhythm.keystrokes(c, report.into()),
+                None => {
+                    warn!("Couldn't find key mapping for char {c}");
+                    Vec::new()
+                }
+            },
+            Some(TypingStep::Shortcut(shortcut)) => self
+                .rhythm
+                .shortcut_keystrokes(HidReport::from(&shortcut).into()),
+            Some(TypingStep::Report(report)) => self.rhythm.report_keystrokes(report),
+            Some(TypingStep::Sleep(duration)) => {
+                self.pause = Some(duration);
+                self.rhythm.finish()
+            }
+            None => {
+                // keys left pressed by the rhythm (see `TypingProfile::Fast`)
+                self.keystrokes.extend(self.rhythm.finish());
+                return !self.keystrokes.is_empty();
+            }
+        };
+        self.keystrokes.extend(keystrokes);
+        true
+    }
+
+    /// Report that releases the keys left pressed, when the job is aborted
+    pub fn release(&self) -> Option<[u8; 8]> {
+        self.pressed.then_some(RELEASED)
+    }
+
+    /// Steps typed so far and in total
+    pub fn progress(&self) -> (usize, usize) {
+        let current = !self.keystrokes.is_empty() || self.pause.is_some();
+        let remaining = self.steps.len() + usize::from(current);
+        (self.total.saturating_sub(remaining), self.total)
+    }
+
+    /// Progress to report, if enough time passed since the last report
+    pub fn progress_due(&mut self, now: Instant) -> Option<(usize, usize)> {
+        if self
+            .reported_at
+            .is_some_and(|time| now.duration_since(time) < PROGRESS_INTERVAL)
+        {
+            return None;
+        }
+        self.reported_at = Some(now);
+        Some(self.progress())
+    }
+}
+
+/// Report of the char, or of its ASCII transliteration (i.e. `é` typed as `e`)
+fn char_report(keymap: &Keymap, c: char) -> Option<&HidReport> {
+    keymap.report(c).or_else(|| {
+        let decoded = deunicode_char(c).filter(|decoded| decoded.len() == 1)?;
+        keymap.report(decoded.chars().next()?)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::TypingProfile;
+
+    fn job(text: &str) -> TypingJob {
+        let steps = text.chars().map(TypingStep::Char).collect();
+        let rhythm = TypingRhythm::new(TypingProfile::Fixed, Duration::from_millis(1));
+        TypingJob::new(1, steps, rhythm, Mode::InApp)
+    }
+
+    fn keymap() -> Keymap {
+        let a = HidReport::new([0, 0, 0x04, 0, 0, 0, 0, 0]);
+        Keymap::new("test".into(), None, [('a', a)].into())
+    }
+
+    #[test]
+    fn test_progress() {
+        let keymap = keymap();
+        let mut job = job("aa");
+        assert_eq!((0, 2), job.progress());
+
+        assert!(job.advance(&keymap));
+        assert_eq!((0, 2), job.progress());
+        let (report, _) = job.keystroke().unwrap();
+        assert_eq!([0, 0, 0x04, 0, 0, 0, 0, 0], report);
+        job.keystroke_sent();
+        assert_eq!(Some(RELEASED), job.release());
+        job.keystroke_sent();
+        assert_eq!(None, job.release());
+        assert_eq!(None, job.keystroke());
+        assert_eq!((1, 2), job.progress());
+
+        assert!(job.advance(&keymap));
+        job.keystroke_sent();
+        job.keystroke_sent();
+        assert_eq!((2, 2), job.progress());
+        assert!(!job.advance(&keymap));
+    }
+
+    #[test]
+    fn test_progress_throttled() {
+        let mut job = job("aaa");
+        let now = Instant::now();
+        assert_eq!(Some((0, 3)), job.progress_due(now));
+        assert_eq!(None, job.progress_due(now + Duration::from_millis(100)));
+        assert_eq!(Some((0, 3)), job.progress_due(now + PROGRESS_INTERVAL));
+    }
+}
@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::VecDeque;
+
+use maiko::{Context, Envelope, EventId, StepAction};
+use tokio::{
+    fs::{read_to_string, remove_file},
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info, warn};
+
+use super::TypingJob;
+use crate::{
+    config::TypingProfile,
+    domain::{ActorState, CharonEvent, Keymap, TypingError, TypingRhythm, TypingStep},
+    port::KeymapLoader,
+};
+
+/// Types text on the host, using the keymap of the active host profile.
+/// Timing of the keystrokes is defined by the typing profile of the request,
+/// of the host profile or the global one (in that order, see `TypingProfile`).
+///
+/// Each request is typed as a job (identified by the id of the request event),
+/// one keystroke per step, so it reports its progress (`TypingProgress`) and can be
+/// cancelled (`CancelTyping`). Jobs requested while typing are queued. A job ends with
+/// `TextSent` or `TypingFailed`, correlated with the request. Typing is interrupted
+/// when the mode changes after the job was requested, so jobs requested in pass-through
+/// mode (by key bindings, macro pads or scripts) are typed, while the ones requested
+/// by the client in in-app mode stop when switched back to pass-through.
+/// The last recorded macro (`MacroRecorded`) is kept, to be played as a job on `PlayMacro`.
+pub struct Typist<L: KeymapLoader> {
+    ctx: Context<CharonEvent>,
+    state: ActorState,
+    interval: Duration,
+    keymap: Keymap,
+    keymap_loader: L,
+    /// Typing profile of the active host profile
+    host_typing_profile: Option<TypingProfile>,
+    /// The job being typed first, followed by the queued ones
+    jobs: VecDeque<TypingJob>,
+    /// Reports of the last recorded macro
+    recorded_macro: Vec<[u8; 8]>,
+}
+
+impl<L: KeymapLoader> Typist<L> {
+    pub fn new(
+        ctx: Context<CharonEvent>,
+        state: ActorState,
+        keymap: Keymap,
+        keymap_loader: L,
+    ) -> Self {
+        let interval = state.config().typing_interval;
+        Self {
+            ctx,
+            state,
+            interval: Duration::from_millis(interval.into()),
+            keymap,
+            keymap_loader,
+            host_typing_profile: None,
+            jobs: VecDeque::new(),
+            recorded_macro: Vec::new(),
+        }
+    }
+
+    async fn switch_host_profile(&mut self, name: &str) {
+        let Some(profile) = self.state.config().host_profiles.get(name) else {
+            return error!("Unknown host profile: {name}");
+        };
+        match self.keymap_loader.load_keymap(&profile.keymap).await {
+            Ok(keymap) => {
+                info!("Host profile {name} activated (keymap: {})", profile.keymap);
+                self.keymap = keymap;
+                self.host_typing_profile = profile.typing_profile;
+            }
+            Err(err) => error!("Couldn't load keymap {}: {err}", profile.keymap),
+        }
+    }
+
+    async fn new_job(
+        &self,
+        id: EventId,
+        steps: Vec<TypingStep>,
+        profile: Option<TypingProfile>,
+    ) -> TypingJob {
+        let profile = profile
+            .or(self.host_typing_profile)
+            .unwrap_or(self.state.config().typing_profile);
+        let rhythm = TypingRhythm::new(profile, self.interval);
+        TypingJob::new(id, steps, rhythm, self.state.mode().await)
+    }
+
+    fn queue(&mut self, job: TypingJob) {
+        if !self.jobs.is_empty() {
+            debug!("Typing job queued ({} ahead)", self.jobs.len());
+        }
+        self.jobs.push_back(job);
+    }
+
+    pub async fn send_string(&mut self, s: &str, profile: Option<TypingProfile>, id: EventId) {
+        let steps = s.chars().map(TypingStep::Char).collect();
+        let job = self.new_job(id, steps, profile).await;
+        self.queue(job);
+    }
+
+    pub async fn send_keys(
+        &mut self,
+        keys: &str,
+        profile: Option<TypingProfile>,
+        id: EventId,
+    ) -> maiko::Result<()> {
+        match TypingStep::parse_all(keys) {
+            Ok(steps) => {
+                let job = self.new_job(id, steps, profile).await;
+                self.queue(job);
+                Ok(())
+            }
+            Err(err) => {
+                error!("Couldn't type keys: {err}");
+                let error = TypingError::InvalidKeys(err.to_string());
+                self.send_correlated(CharonEvent::TypingFailed(id, error), id)
+                    .await
+            }
+        }
+    }
+
+    pub async fn send_file(
+        &mut self,
+        path: &str,
+        remove: bool,
+        profile: Option<TypingProfile>,
+        id: EventId,
+    ) -> maiko::Result<()> {
+        debug!("Typing text from file: {path}");
+        match read_to_string(path).await {
+            Ok(text) => {
+                let steps = text.chars().map(TypingStep::Char).collect();
+                let mut job = self.new_job(id, steps, profile).await;
+                if remove {
+                    job = job.with_file_to_remove(path.into());
+                }
+                self.queue(job);
+                Ok(())
+            }
+            Err(err) => {
+                error!("Couldn't read file {path}: {err}");
+                let error = TypingError::File(err.to_string());
+                self.send_correlated(CharonEvent::TypingFailed(id, error), id)
+                    .await
+            }
+        }
+    }
+
+    /// Plays the last recorded macro, releasing the keys it leaves pressed
+    pub async fn play_macro(&mut self, id: EventId) {
+        if self.recorded_macro.is_empty() {
+            warn!("No macro recorded");
+        }
+        let mut steps: Vec<_> = self
+            .recorded_macro
+            .iter()
+            .map(|report| TypingStep::Report(*report))
+            .collect();
+        if self
+            .recorded_macro
+            .last()
+            .is_some_and(|report| *report != [0; 8])
+        {
+            steps.push(TypingStep::Report([0; 8]));
+        }
+        let job = self.new_job(id, steps, None).await;
+        self.queue(job);
+    }
+
+    /// Cancels the job with given id (or all jobs), releasing keys left pressed
+    async fn cancel(&mut self, id: Option<EventId>) -> maiko::Result<()> {
+        let matches = |job: &TypingJob| id.is_none_or(|id| job.id() == id);
+        if !self.jobs.iter().any(matches) {
+            warn!("No typing job to cancel");
+            return Ok(());
+        }
+        if self.jobs.front().is_some_and(matches) {
+            self.abort(TypingError::Cancelled).await?;
+        }
+        let (cancelled, jobs): (VecDeque<_>, _) = self.jobs.drain(..).partition(matches);
+        self.jobs = jobs;
+        for job in &cancelled {
+            self.end(job, Err(TypingError::Cancelled)).await?;
+        }
+        Ok(())
+    }
+
+    /// Ends the job being typed with an error, releasing keys left pressed.
+    /// The job is removed once it's ended, so it's safe to cancel this future.
+    async fn abort(&mut self, error: TypingError) -> maiko::Result<()> {
+        let Some(job) = self.jobs.front() else {
+            return Ok(());
+        };
+        warn!("Typing aborted: {error}");
+        if let Some(report) = job.release() {
+            self.ctx.send(CharonEvent::HidReport(report)).await?;
+        }
+        self.end(job, Err(error)).await?;
+        self.jobs.pop_front();
+        Ok(())
+    }
+
+    /// Removes the file of the job (if requested) and reports the result to the client
+    async fn end(&self, job: &TypingJob, result: Result<(), TypingError>) -> maiko::Result<()> {
+        if let Some(path) = job.file_to_remove()
+            && let Err(err) = remove_file(path).await
+        {
+            warn!("Couldn't remove file {path}: {err}");
+        }
+        let id = job.id();
+        let event = match result {
+            Ok(()) => {
+                debug!("Typing completed");
+                let (sent, total) = job.progress();
+                self.send_correlated(CharonEvent::TypingProgress(id, sent, total), id)
+                    .await?;
+                CharonEvent::TextSent(id)
+            }
+            Err(error) => CharonEvent::TypingFailed(id, error),
+        };
+        self.send_correlated(event, id).await
+    }
+
+    async fn send_correlated(&self, event: CharonEvent, id: EventId) -> maiko::Result<()> {
+        self.ctx.send_with_correlation(event, id).await
+    }
+
+    /// Types the next keystroke of the current job, returns pause after it.
+    /// The state changes only after the events are sent (the future may be cancelled
+    /// by an incoming event).
+    async fn type_next(&mut self) -> maiko::Result<StepAction> {
+        let mode = self.state.mode().await;
+        let Some(job) = self.jobs.front_mut() else {
+            return Ok(StepAction::AwaitEvent);
+        };
+        if job.mode() != mode {
+            self.abort(TypingError::Interrupted).await?;
+            return Ok(StepAction::Continue);
+        }
+        if let Some((report, delay)) = job.keystroke() {
+            self.ctx.send(CharonEvent::HidReport(report)).await?;
+            job.keystroke_sent();
+            return Ok(StepAction::Backoff(delay));
+        }
+        if let Some(pause) = job.take_pause() {
+            return Ok(StepAction::Backoff(pause));
+        }
+        if job.advance(&self.keymap) {
+            let id = job.id();
+            if let Some((sent, total)) = job.progress_due(Instant::now()) {
+                self.send_correlated(CharonEvent::TypingProgress(id, sent, total), id)
+                    .await?;
+            }
+        } else if let Some(job) = self.jobs.front() {
+            self.end(job, Ok(())).await?;
+            self.jobs.pop_front();
+        }
+        Ok(StepAction::Continue)
+    }
+}
+
+impl<L: KeymapLoader + Send + Sync + 'static> maiko::Actor for Typist<L> {
+    type Event = CharonEvent;
+
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        let id = envelope.meta().id();
+        match envelope.event() {
+            CharonEvent::SendText(txt, profile) => self.send_string(txt, *profile, id).await,
+            CharonEvent::SendKeys(keys, profile) => self.send_keys(keys, *profile, id).await?,
+            CharonEvent::SendFile(path, remove, profile) => {
+                self.send_file(path, *remove, *profile, id).await?
+            }
+            CharonEvent::CancelTyping(job) => self.cancel(*job).await?,
+            CharonEvent::MacroRecorded(reports) => {
+                debug!("Macro recorded ({} reports)", reports.len());
+                self.recorded_macro = reports.clone();
+            }
+            CharonEvent::PlayMacro => self.play_macro(id).await,
+            CharonEvent::HostProfileChange(name) => self.switch_host_profile(name).await,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn step(&mut self) -> maiko::Result<StepAction> {
+        self.type_next().await
+    }
+}
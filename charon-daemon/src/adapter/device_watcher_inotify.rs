@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    collections::VecDeque,
+    fs,
+    os::fd::{AsFd, AsRawFd, RawFd},
+};
+
+use nix::{
+    errno::Errno,
+    sys::inotify::{AddWatchFlags, InitFlags, Inotify, InotifyEvent, WatchDescriptor},
+};
+use tokio::io::unix::AsyncFd;
+use tracing::{debug, warn};
+
+use crate::{
+    domain::DeviceChange,
+    port::DeviceWatcher,
+    util::evdev::{BY_ID, is_event_node},
+};
+
+const INPUT_DIR: &str = "/dev/input";
+const BY_ID_DIR_NAME: &str = "by-id";
+
+struct InotifyFd(Inotify);
+
+impl AsRawFd for InotifyFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_fd().as_raw_fd()
+    }
+}
+
+/// Watches /dev/input/by-id/ for keyboards being plugged in and out.
+/// The by-id directory is removed by udev when the last device is gone, so
+/// /dev/input is watched as well, to start watching by-id again once it's recreated.
+/// Event nodes of /dev/input are reported too, as not every keyboard has a by-id name
+/// (i.e. Bluetooth keyboards).
+pub struct DeviceWatcherInotify {
+    inotify: AsyncFd<InotifyFd>,
+    by_id: Option<WatchDescriptor>,
+    pending: VecDeque<DeviceChange>,
+}
+
+impl DeviceWatcherInotify {
+    pub fn new() -> std::io::Result<Self> {
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        inotify.add_watch(
+            INPUT_DIR,
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_DELETE,
+        )?;
+        let by_id = Self::watch_by_id(&inotify);
+        Ok(Self {
+            inotify: AsyncFd::new(InotifyFd(inotify))?,
+            by_id,
+            pending: VecDeque::new(),
+        })
+    }
+
+    fn watch_by_id(inotify: &Inotify) -> Option<WatchDescriptor> {
+        inotify
+            .add_watch(BY_ID, AddWatchFlags::IN_CREATE | AddWatchFlags::IN_DELETE)
+            .inspect_err(|err| debug!("Couldn't watch {BY_ID}: {err}"))
+            .ok()
+    }
+
+    fn handle_inotify_event(&mut self, event: InotifyEvent) {
+        let Some(name) = event.name.map(|n| n.to_string_lossy().to_string()) else {
+            if event.mask.contains(AddWatchFlags::IN_IGNORED) && Some(event.wd) == self.by_id {
+                debug!("{BY_ID} is no longer watched");
+                self.by_id = None;
+            }
+            return;
+        };
+
+        if Some(event.wd) == self.by_id || is_event_node(&name) {
+            if event.mask.contains(AddWatchFlags::IN_CREATE) {
+                self.pending.push_back(DeviceChange::Attached(name));
+            } else if event.mask.contains(AddWatchFlags::IN_DELETE) {
+                self.pending.push_back(DeviceChange::Detached(name));
+            }
+        } else if name == BY_ID_DIR_NAME
+            && self.by_id.is_none()
+            && event.mask.contains(AddWatchFlags::IN_CREATE)
+        {
+            self.by_id = Self::watch_by_id(&self.inotify.get_ref().0);
+            // devices could have been added before the watch was registered
+            self.pending
+                .extend(self.devices().into_iter().map(DeviceChange::Attached));
+        }
+    }
+}
+
+fn entry_names(dir: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+impl DeviceWatcher for DeviceWatcherInotify {
+    fn devices(&self) -> Vec<String> {
+        let event_nodes = entry_names(INPUT_DIR)
+            .into_iter()
+            .filter(|name| is_event_node(name));
+        entry_names(BY_ID).into_iter().chain(event_nodes).collect()
+    }
+
+    async fn next_change(&mut self) -> Option<DeviceChange> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Some(change);
+            }
+
+            let mut guard = self.inotify.readable().await.ok()?;
+            let events = match guard.get_inner().0.read_events() {
+                Ok(events) => events,
+                Err(Errno::EAGAIN) => {
+                    guard.clear_ready();
+                    continue;
+                }
+                Err(err) => {
+                    warn!("Failed reading inotify events: {err}");
+                    return None;
+                }
+            };
+            drop(guard);
+
+            for event in events {
+                self.handle_inotify_event(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{fs::OpenOptions, os::unix::fs::OpenOptionsExt, path::Path};
+
+use evdev::{InputEvent, KeyCode};
+use tokio::{
+    io::{AsyncWriteExt, BufWriter},
+    sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+};
+use tracing::{error, info};
+
+use super::RecordedEvent;
+use crate::port::EventDevice;
+
+/// Records all events of the wrapped input device to a file (see `RecordedEvent`
+/// for the format), so the session can be replayed with `EventDeviceReplay`.
+/// Reconnections of the device (i.e. when hot-plugged) are recorded as comments,
+/// so a single file covers the whole session.
+///
+/// Events are written by a background task, so reading the device is never blocked
+/// by the file. The file is flushed whenever there are no more events to write.
+pub struct EventDeviceRecorder<D: EventDevice> {
+    device: D,
+    lines: UnboundedSender<String>,
+}
+
+impl<D: EventDevice> EventDeviceRecorder<D> {
+    /// Must be called within Tokio runtime (the writing task is spawned).
+    /// The file is readable by the owner only, as it contains everything typed.
+    pub fn new(device: D, path: &Path, keyboard: &str) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        let file = tokio::fs::File::from_std(file);
+        let (lines, receiver) = unbounded_channel();
+        tokio::spawn(write_lines(BufWriter::new(file), receiver));
+        info!("Recording input of {keyboard} to {}", path.display());
+        let recorder = Self { device, lines };
+        recorder.record(format!("# keyboard: {keyboard}"));
+        Ok(recorder)
+    }
+
+    fn record(&self, line: String) {
+        if self.lines.send(line).is_err() {
+            error!("Couldn't record input event: recording stopped");
+        }
+    }
+}
+
+/// Writes the lines until the recorder is dropped, flushing when idle
+async fn write_lines(mut file: BufWriter<tokio::fs::File>, mut lines: UnboundedReceiver<String>) {
+    while let Some(line) = lines.recv().await {
+        let mut result = write_line(&mut file, &line).await;
+        while result.is_ok()
+            && let Ok(line) = lines.try_recv()
+        {
+            result = write_line(&mut file, &line).await;
+        }
+        if let Err(err) = result.and(file.flush().await) {
+            error!("Couldn't record input events: {err}");
+            return;
+        }
+    }
+}
+
+async fn write_line(file: &mut BufWriter<tokio::fs::File>, line: &str) -> std::io::Result<()> {
+    file.write_all(line.as_bytes()).await?;
+    file.write_all(b"\n").await
+}
+
+impl<D: EventDevice> EventDevice for EventDeviceRecorder<D> {
+    async fn next_event(&mut self) -> Option<InputEvent> {
+        let event = self.device.next_event().await?;
+        self.record(RecordedEvent::from(&event).to_string());
+        Some(event)
+    }
+
+    fn is_grabbed(&self) -> bool {
+        self.device.is_grabbed()
+    }
+
+    fn grab(&mut self) -> std::io::Result<()> {
+        self.device.grab()
+    }
+
+    fn ungrab(&mut self) -> std::io::Result<()> {
+        self.device.ungrab()
+    }
+
+    fn key_state(&self) -> std::io::Result<Vec<KeyCode>> {
+        self.device.key_state()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.device.is_connected()
+    }
+
+    fn connect(&mut self) -> std::io::Result<()> {
+        self.device.connect()?;
+        self.record("# connected".into());
+        Ok(())
+    }
+
+    fn disconnect(&mut self) {
+        self.record("# disconnected".into());
+        self.device.disconnect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{os::unix::fs::PermissionsExt, time::Duration};
+
+    use super::*;
+    use crate::adapter::EventDeviceReplay;
+
+    fn event(value: i32) -> RecordedEvent {
+        RecordedEvent {
+            time: Duration::from_micros(1_000_000_512),
+            event_type: 1,
+            code: 30,
+            value,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recording_replayed() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("charon-recorder-{}", std::process::id()));
+        let device = EventDeviceReplay::new(vec![event(1), event(0)], 0.0);
+        let mut recorder = EventDeviceRecorder::new(device, &path, "main")?;
+        recorder.next_event().await;
+        recorder.disconnect();
+        recorder.connect()?;
+        recorder.next_event().await;
+        drop(recorder);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        let recording = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(0o600, mode & 0o777);
+        assert!(recording.starts_with("# keyboard: main\n"));
+        assert!(recording.contains("# disconnected\n# connected\n"));
+        let events: Vec<_> = RecordedEvent::parse_all(&recording)?
+            .iter()
+            .map(|event| event.value)
+            .collect();
+        assert_eq!(vec![1, 0], events);
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use evdev::InputEvent;
+use tokio::time::{Duration, Instant, sleep_until};
+use tracing::info;
+
+use super::RecordedEvent;
+use crate::{error::CharonError, port::EventDevice};
+
+/// Input device that replays a recorded session (see `EventDeviceRecorder`),
+/// preserving the intervals between events, divided by `speed`
+/// (`1.0` is the real speed, `0.0` replays the events without any delay).
+/// Replayed events are timestamped with the time of replay.
+/// Once all the events are replayed, the device stays connected, but idle.
+pub struct EventDeviceReplay {
+    events: VecDeque<RecordedEvent>,
+    speed: f64,
+    /// Time of the first event: of the recording and of the replay
+    start: Option<(Duration, Instant)>,
+    grabbed: bool,
+    finished: Arc<AtomicBool>,
+}
+
+impl EventDeviceReplay {
+    pub fn new(events: Vec<RecordedEvent>, speed: f64) -> Self {
+        Self {
+            events: events.into(),
+            speed,
+            start: None,
+            grabbed: false,
+            finished: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn from_file(path: &Path, speed: f64) -> Result<Self, CharonError> {
+        let recording = std::fs::read_to_string(path)?;
+        let events = RecordedEvent::parse_all(&recording)?;
+        info!("Replaying {} events from {}", events.len(), path.display());
+        Ok(Self::new(events, speed))
+    }
+
+    /// Flag set once all the events are replayed
+    pub fn finished(&self) -> Arc<AtomicBool> {
+        self.finished.clone()
+    }
+
+    fn due_time(&mut self, event: &RecordedEvent) -> Option<Instant> {
+        if self.speed <= 0.0 {
+            return None;
+        }
+        let (first, started) = *self.start.get_or_insert((event.time, Instant::now()));
+        let offset = event.time.saturating_sub(first).div_f64(self.speed);
+        Some(started + offset)
+    }
+}
+
+impl EventDevice for EventDeviceReplay {
+    async fn next_event(&mut self) -> Option<InputEvent> {
+        let Some(event) = self.events.front().copied() else {
+            self.finished.store(true, Ordering::Relaxed);
+            return std::future::pending().await;
+        };
+        if let Some(due_time) = self.due_time(&event) {
+            sleep_until(due_time).await;
+        }
+        self.events.pop_front();
+        Some(event.to_input_event())
+    }
+
+    fn is_grabbed(&self) -> bool {
+        self.grabbed
+    }
+
+    fn grab(&mut self) -> std::io::Result<()> {
+        self.grabbed = true;
+        Ok(())
+    }
+
+    fn ungrab(&mut self) -> std::io::Result<()> {
+        self.grabbed = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(millis: u64, value: i32) -> RecordedEvent {
+        RecordedEvent {
+            time: Duration::from_secs(1_000) + Duration::from_millis(millis),
+            event_type: 1,
+            code: 30,
+            value,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_timing() {
+        let mut device = EventDeviceReplay::new(vec![event(0, 1), event(100, 0)], 2.0);
+        let finished = device.finished();
+        let started = Instant::now();
+
+        assert_eq!(1, device.next_event().await.unwrap().value());
+        assert_eq!(0, device.next_event().await.unwrap().value());
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        let next = tokio::time::timeout(Duration::from_millis(10), device.next_event()).await;
+        assert!(next.is_err());
+        assert!(finished.load(Ordering::Relaxed));
+    }
+}
@@ -1,23 +1,34 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use std::collections::VecDeque;
+use std::{collections::VecDeque, io};
 
-use evdev::{Device, InputEvent};
+use evdev::{Device, InputEvent, KeyCode};
 use tokio::io::unix::AsyncFd;
 
-use crate::port::EventDevice;
+use crate::{config::InputConfig, port::EventDevice, util::evdev::find_input_device};
 
+/// Input device (/dev/input/event*) described by the input configuration.
+/// The device is opened on `connect` and dropped as soon as it can't be read
+/// anymore (i.e. when the keyboard has been unplugged).
 pub struct EventDeviceUnix {
-    device: AsyncFd<Device>,
+    input: InputConfig,
+    device: Option<AsyncFd<Device>>,
     pending: VecDeque<InputEvent>,
 }
 
 impl EventDeviceUnix {
-    pub fn new(device: AsyncFd<Device>) -> Self {
+    pub fn new(input: InputConfig) -> Self {
         Self {
-            device,
+            input,
+            device: None,
             pending: VecDeque::new(),
         }
     }
+
+    fn device_mut(&mut self) -> io::Result<&mut AsyncFd<Device>> {
+        self.device
+            .as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))
+    }
 }
 
 impl EventDevice for EventDeviceUnix {
@@ -26,29 +37,70 @@ impl EventDevice for EventDeviceUnix {
             if let Some(ev) = self.pending.pop_front() {
                 return Some(ev);
             }
-            let mut guard = self.device.readable_mut().await.ok()?;
-            let device = guard.get_mut().get_mut();
-
-            match device.fetch_events() {
-                Ok(events) => {
-                    self.pending.extend(events);
+            let device = self.device.as_mut()?;
+            let connected = match device.readable_mut().await {
+                Ok(mut guard) => {
+                    let result = match guard.get_mut().get_mut().fetch_events() {
+                        Ok(events) => {
+                            self.pending.extend(events);
+                            true
+                        }
+                        Err(err) => err.kind() == io::ErrorKind::WouldBlock,
+                    };
+                    guard.clear_ready();
+                    result
                 }
-                Err(_) => return None,
-            }
+                Err(_) => false,
+            };
 
-            guard.clear_ready();
+            if !connected {
+                self.disconnect();
+                return None;
+            }
         }
     }
 
     fn is_grabbed(&self) -> bool {
-        self.device.get_ref().is_grabbed()
+        self.device
+            .as_ref()
+            .is_some_and(|device| device.get_ref().is_grabbed())
+    }
+
+    fn grab(&mut self) -> io::Result<()> {
+        self.device_mut()?.get_mut().grab()
+    }
+
+    fn ungrab(&mut self) -> io::Result<()> {
+        self.device_mut()?.get_mut().ungrab()
+    }
+
+    fn key_state(&self) -> io::Result<Vec<KeyCode>> {
+        let device = self
+            .device
+            .as_ref()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        Ok(device.get_ref().get_key_state()?.iter().collect())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.device.is_some()
     }
 
-    fn grab(&mut self) -> std::io::Result<()> {
-        self.device.get_mut().grab()
+    fn connect(&mut self) -> io::Result<()> {
+        let path = find_input_device(&self.input).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Device not found for {:?}", self.input),
+            )
+        })?;
+        let device = Device::open(path)?;
+        self.device = Some(AsyncFd::new(device)?);
+        self.pending.clear();
+        Ok(())
     }
 
-    fn ungrab(&mut self) -> std::io::Result<()> {
-        self.device.get_mut().ungrab()
+    fn disconnect(&mut self) {
+        self.device = None;
+        self.pending.clear();
     }
 }
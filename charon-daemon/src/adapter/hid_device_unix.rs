@@ -2,11 +2,16 @@
 use std::{
     fs::{File, OpenOptions},
     io::Write,
+    os::unix::fs::OpenOptionsExt,
     path::Path,
 };
 
+use nix::fcntl::OFlag;
+
 use crate::port::HIDDevice;
 
+/// USB HID gadget device (`/dev/hidgN`). It's opened in non-blocking mode, so a write
+/// fails with `WouldBlock` instead of blocking the writer when the host is suspended.
 pub struct HIDDeviceUnix {
     hidg: File,
 }
@@ -15,6 +20,7 @@ impl HIDDeviceUnix {
     pub fn new(path: &Path) -> Self {
         let hidg = OpenOptions::new()
             .write(true)
+            .custom_flags(OFlag::O_NONBLOCK.bits())
             .open(path)
             .expect("Failed to open HID gadget device");
 
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    sync::Notify,
+    time::{Duration, timeout},
+};
+
+use crate::{domain::DeviceChange, port::DeviceWatcher};
+
+/// Input devices present in the simulated system, and their changes not reported yet
+#[derive(Default)]
+pub struct DeviceWatcherState {
+    pub devices: Vec<String>,
+    pub changes: VecDeque<DeviceChange>,
+    /// Wakes up the watcher when a change is queued
+    pub notify: Arc<Notify>,
+}
+
+impl DeviceWatcherState {
+    pub fn simulate_attach(&mut self, name: &str) {
+        self.devices.push(name.into());
+        self.changes.push_back(DeviceChange::Attached(name.into()));
+        self.notify.notify_one();
+    }
+
+    pub fn simulate_detach(&mut self, name: &str) {
+        self.devices.retain(|device| device != name);
+        self.changes.push_back(DeviceChange::Detached(name.into()));
+        self.notify.notify_one();
+    }
+}
+
+#[derive(Default)]
+pub struct DeviceWatcherMock {
+    pub state: Arc<Mutex<DeviceWatcherState>>,
+}
+
+impl DeviceWatcherMock {
+    pub fn state(&self) -> &Arc<Mutex<DeviceWatcherState>> {
+        &self.state
+    }
+
+    fn pop_change(&self) -> Option<DeviceChange> {
+        let mut lock = self.state.lock().expect("Couldn't lock the state");
+        lock.changes.pop_front()
+    }
+}
+
+impl DeviceWatcher for DeviceWatcherMock {
+    fn devices(&self) -> Vec<String> {
+        let lock = self.state.lock().expect("Couldn't lock the state");
+        lock.devices.clone()
+    }
+
+    async fn next_change(&mut self) -> Option<DeviceChange> {
+        loop {
+            if let Some(change) = self.pop_change() {
+                return Some(change);
+            }
+            let notify = self
+                .state
+                .lock()
+                .expect("Couldn't lock the state")
+                .notify
+                .clone();
+            let _ = timeout(Duration::from_millis(1), notify.notified()).await;
+        }
+    }
+}
@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 use evdev::{EventType, InputEvent, KeyCode};
-use std::{collections::VecDeque, sync::Arc};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+};
 use tokio::{
-    sync::Mutex,
-    time::{Duration, sleep},
+    sync::{Mutex, Notify},
+    time::{Duration, sleep, timeout},
 };
 
 use crate::port::EventDevice;
@@ -13,18 +16,34 @@ pub struct EventDeviceState {
     pub grabbed: bool,
     pub grab_calls: u16,
     pub ungrab_calls: u16,
+    pub disconnected: bool,
     pub events: VecDeque<InputEvent>,
+    /// Keys pressed according to the device (see `EventDevice::key_state`)
+    pub pressed: HashSet<KeyCode>,
+    /// Simulates the device that can't report its key state
+    pub key_state_unsupported: bool,
+    /// Wakes up the reader when an event is queued
+    pub notify: Arc<Notify>,
 }
 
 impl EventDeviceState {
     pub fn simulate_key_press(&mut self, key_code: KeyCode) {
         let event = InputEvent::new_now(EventType::KEY.0, key_code.code(), 1);
+        self.pressed.insert(key_code);
         self.events.push_back(event);
+        self.notify.notify_one();
     }
 
     pub fn simulate_key_release(&mut self, key_code: KeyCode) {
         let event = InputEvent::new_now(EventType::KEY.0, key_code.code(), 0);
+        self.pressed.remove(&key_code);
         self.events.push_back(event);
+        self.notify.notify_one();
+    }
+
+    /// Releases the key without emitting the event (as if the event got lost)
+    pub fn simulate_lost_key_release(&mut self, key_code: KeyCode) {
+        self.pressed.remove(&key_code);
     }
 
     /// Waits until all queued events have been consumed.
@@ -44,13 +63,25 @@ impl EventDeviceMock {
     pub fn state(&self) -> &Arc<Mutex<EventDeviceState>> {
         &self.state
     }
+
+    async fn pop_event(&self) -> Option<InputEvent> {
+        let mut lock = self.state.lock().await;
+        if lock.disconnected {
+            return None;
+        }
+        lock.events.pop_front()
+    }
 }
 
 impl EventDevice for EventDeviceMock {
     async fn next_event(&mut self) -> Option<InputEvent> {
-        sleep(Duration::from_millis(1)).await;
-        let mut lock = self.state.lock().await;
-        return lock.events.pop_front();
+        // queued events are returned immediately, so the mock doesn't add any latency
+        if let Some(event) = self.pop_event().await {
+            return Some(event);
+        }
+        let notify = self.state.lock().await.notify.clone();
+        let _ = timeout(Duration::from_millis(1), notify.notified()).await;
+        self.pop_event().await
     }
 
     fn is_grabbed(&self) -> bool {
@@ -71,4 +102,30 @@ impl EventDevice for EventDeviceMock {
         lock.ungrab_calls += 1;
         Ok(())
     }
+
+    fn key_state(&self) -> std::io::Result<Vec<KeyCode>> {
+        let lock = self.state.try_lock().expect("Couldn't lock the state");
+        if lock.key_state_unsupported {
+            return Err(std::io::ErrorKind::Unsupported.into());
+        }
+        Ok(lock.pressed.iter().copied().collect())
+    }
+
+    fn is_connected(&self) -> bool {
+        let lock = self.state.try_lock().expect("Couldn't lock the state");
+        !lock.disconnected
+    }
+
+    fn connect(&mut self) -> std::io::Result<()> {
+        let mut lock = self.state.try_lock().expect("Couldn't lock the state");
+        lock.disconnected = false;
+        Ok(())
+    }
+
+    fn disconnect(&mut self) {
+        let mut lock = self.state.try_lock().expect("Couldn't lock the state");
+        lock.disconnected = true;
+        lock.grabbed = false;
+        lock.pressed.clear();
+    }
 }
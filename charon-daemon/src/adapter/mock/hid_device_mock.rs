@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::sync::{Arc, Mutex};
+
+use crate::{domain::stats::now, port::HIDDevice};
+
+/// Reports written to the simulated host, with the time of writing
+/// (nanoseconds since Unix epoch, comparable with evdev timestamps).
+#[derive(Default)]
+pub struct HIDDeviceState {
+    pub reports: Vec<(u64, [u8; 8])>,
+    /// Simulates the host that is off or disconnected: writes fail
+    pub host_unavailable: bool,
+    /// Simulates the suspended host: writes to the (non-blocking) device would block
+    pub host_suspended: bool,
+}
+
+impl HIDDeviceState {
+    /// Written reports, without timestamps
+    pub fn report_bytes(&self) -> Vec<[u8; 8]> {
+        self.reports.iter().map(|(_, report)| *report).collect()
+    }
+}
+
+#[derive(Default)]
+pub struct HIDDeviceMock {
+    state: Arc<Mutex<HIDDeviceState>>,
+}
+
+impl HIDDeviceMock {
+    pub fn state(&self) -> &Arc<Mutex<HIDDeviceState>> {
+        &self.state
+    }
+}
+
+impl HIDDevice for HIDDeviceMock {
+    fn send_report(&mut self, report: &[u8; 8]) -> std::io::Result<()> {
+        let time = now();
+        if let Ok(mut state) = self.state.lock() {
+            if state.host_unavailable {
+                return Err(std::io::ErrorKind::BrokenPipe.into());
+            }
+            if state.host_suspended {
+                return Err(std::io::ErrorKind::WouldBlock.into());
+            }
+            state.reports.push((time, *report));
+        }
+        Ok(())
+    }
+}
@@ -2,15 +2,27 @@ use std::sync::{Arc, Mutex};
 
 use evdev::KeyCode;
 
-use crate::{error::CharonError, port::Metrics};
+use crate::{
+    domain::{FilterReason, qmk::QMKRecord, stats::LatencyTrace},
+    error::CharonError,
+    port::Metrics,
+};
 
 pub struct MetricsState {
+    latency_traces_counter: usize,
+
     wpm_counter: usize,
     last_wpm: u16,
 
     key_events_counter: usize,
     last_key_event: KeyCode,
 
+    matrix_key_events_counter: usize,
+    last_matrix_key_event: Option<(QMKRecord, u8)>,
+
+    filtered_key_events_counter: usize,
+    last_filtered_key_event: Option<(KeyCode, FilterReason)>,
+
     key_to_report_time_counter: usize,
     last_key_to_report_time: u64,
 }
@@ -27,6 +39,20 @@ impl Metrics for MetricsMock {
         }
     }
 
+    fn register_matrix_key_event(&self, record: &QMKRecord, _keyboard: &str, layer: u8) {
+        if let Ok(mut state) = self.state.lock() {
+            state.matrix_key_events_counter += 1;
+            state.last_matrix_key_event = Some((*record, layer));
+        }
+    }
+
+    fn register_filtered_key_event(&self, key: &KeyCode, _keyboard: &str, reason: FilterReason) {
+        if let Ok(mut state) = self.state.lock() {
+            state.filtered_key_events_counter += 1;
+            state.last_filtered_key_event = Some((*key, reason));
+        }
+    }
+
     fn register_key_to_report_time(&self, time: u64) {
         if let Ok(mut state) = self.state.lock() {
             state.key_to_report_time_counter += 1;
@@ -34,6 +60,12 @@ impl Metrics for MetricsMock {
         }
     }
 
+    fn register_latency_trace(&self, _trace: &LatencyTrace) {
+        if let Ok(mut state) = self.state.lock() {
+            state.latency_traces_counter += 1;
+        }
+    }
+
     fn register_wpm(&self, wpm: u16) {
         if let Ok(mut state) = self.state.lock() {
             state.wpm_counter += 1;
@@ -1,6 +1,14 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+mod device_watcher_mock;
 mod event_device_mock;
+mod hid_device_mock;
 mod metricks_mock;
+mod side_channel_mock;
+mod via_keyboard_mock;
 
+pub use device_watcher_mock::*;
 pub use event_device_mock::*;
+pub use hid_device_mock::*;
 pub use metricks_mock::*;
+pub use side_channel_mock::*;
+pub use via_keyboard_mock::*;
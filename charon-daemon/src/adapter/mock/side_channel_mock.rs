@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{collections::VecDeque, sync::Arc};
+use tokio::{
+    sync::Mutex,
+    time::{Duration, sleep},
+};
+
+use crate::{
+    domain::qmk::{QMKEvent, QMKRequest},
+    error::CharonError,
+    port::KeyboardSideChannel,
+};
+
+#[derive(Default)]
+pub struct SideChannelState {
+    pub connected: bool,
+    pub connect_calls: u16,
+    /// Requests sent to the keyboard
+    pub requests: Vec<QMKRequest>,
+    events: VecDeque<QMKEvent>,
+}
+
+impl SideChannelState {
+    /// Simulates event sent by the keyboard
+    pub fn send_event(&mut self, event: QMKEvent) {
+        self.events.push_back(event);
+    }
+
+    /// Simulates keyboard being unplugged
+    pub fn disconnect(&mut self) {
+        self.connected = false;
+    }
+}
+
+#[derive(Default)]
+pub struct SideChannelMock {
+    state: Arc<Mutex<SideChannelState>>,
+    // Kept outside of the state, so `is_connected` never waits for the lock
+    connected: bool,
+}
+
+impl SideChannelMock {
+    pub fn state(&self) -> &Arc<Mutex<SideChannelState>> {
+        &self.state
+    }
+}
+
+impl KeyboardSideChannel for SideChannelMock {
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    async fn connect(&mut self) -> Result<(), CharonError> {
+        let mut lock = self.state.lock().await;
+        lock.connect_calls += 1;
+        lock.connected = true;
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn read_event(&mut self) -> Result<Option<QMKEvent>, CharonError> {
+        loop {
+            {
+                let mut lock = self.state.lock().await;
+                if !lock.connected {
+                    self.connected = false;
+                    return Err(CharonError::QMKError("Mock keyboard disconnected".into()));
+                }
+                if let Some(event) = lock.events.pop_front() {
+                    return Ok(Some(event));
+                }
+            }
+            sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    async fn send_request(&mut self, request: &QMKRequest) -> Result<(), CharonError> {
+        self.state.lock().await.requests.push(request.clone());
+        Ok(())
+    }
+}
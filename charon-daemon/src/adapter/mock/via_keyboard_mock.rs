@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{collections::VecDeque, sync::Arc};
+use tokio::{
+    sync::Mutex,
+    time::{Duration, sleep},
+};
+
+use crate::{error::CharonError, port::RawHidDevice};
+
+/// Simulated keyboard with VIA firmware: responds to the VIA commands used by `ViaDevice`.
+pub struct ViaKeyboardState {
+    pub rows: u8,
+    pub cols: u8,
+    pub layers: Vec<Vec<u16>>,
+    pub matrix: Vec<u32>,
+    pub connected: bool,
+    pub commands: Vec<[u8; 32]>,
+    responses: VecDeque<[u8; 32]>,
+}
+
+impl ViaKeyboardState {
+    pub fn press(&mut self, row: u8, col: u8) {
+        self.matrix[row as usize] |= 1 << col;
+    }
+
+    pub fn release(&mut self, row: u8, col: u8) {
+        self.matrix[row as usize] &= !(1 << col);
+    }
+
+    fn respond(&mut self, command: &[u8; 32]) -> [u8; 32] {
+        let mut response = *command;
+        match command[0] {
+            0x01 => response[1..3].copy_from_slice(&12u16.to_be_bytes()),
+            0x02 if command[1] == 0x03 => {
+                let row_size = self.cols.div_ceil(8) as usize;
+                for (row, state) in self.matrix.iter().enumerate() {
+                    let bytes = state.to_be_bytes();
+                    let start = 2 + row * row_size;
+                    response[start..start + row_size].copy_from_slice(&bytes[4 - row_size..]);
+                }
+            }
+            0x04 => {
+                let index = command[2] as usize * self.cols as usize + command[3] as usize;
+                let keycode = self.layers[command[1] as usize][index];
+                response[4..6].copy_from_slice(&keycode.to_be_bytes());
+            }
+            0x07 => {}
+            0x11 => response[1] = self.layers.len() as u8,
+            0x12 => {
+                let keycodes: Vec<u16> = self.layers.iter().flatten().copied().collect();
+                let start = u16::from_be_bytes([command[1], command[2]]) as usize / 2;
+                for i in 0..command[3] as usize / 2 {
+                    let keycode = keycodes.get(start + i).copied().unwrap_or_default();
+                    response[4 + i * 2..6 + i * 2].copy_from_slice(&keycode.to_be_bytes());
+                }
+            }
+            _ => response[0] = 0xFF,
+        }
+        response
+    }
+}
+
+pub struct ViaKeyboardMock {
+    state: Arc<Mutex<ViaKeyboardState>>,
+}
+
+impl ViaKeyboardMock {
+    pub fn new(rows: u8, cols: u8, layers: Vec<Vec<u16>>) -> Self {
+        let state = ViaKeyboardState {
+            rows,
+            cols,
+            layers,
+            matrix: vec![0; rows as usize],
+            connected: false,
+            commands: Vec::new(),
+            responses: VecDeque::new(),
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn state(&self) -> &Arc<Mutex<ViaKeyboardState>> {
+        &self.state
+    }
+}
+
+impl RawHidDevice for ViaKeyboardMock {
+    fn is_connected(&self) -> bool {
+        let lock = self.state.try_lock().expect("Couldn't lock the state");
+        lock.connected
+    }
+
+    async fn connect(&mut self) -> Result<(), CharonError> {
+        self.state.lock().await.connected = true;
+        Ok(())
+    }
+
+    async fn read_packet(&mut self) -> Result<(usize, [u8; 32]), CharonError> {
+        loop {
+            if let Some(response) = self.state.lock().await.responses.pop_front() {
+                return Ok((32, response));
+            }
+            sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    async fn write_packet(&mut self, packet: &[u8; 32]) -> Result<(), CharonError> {
+        let mut lock = self.state.lock().await;
+        let response = lock.respond(packet);
+        lock.commands.push(*packet);
+        lock.responses.push_back(response);
+        Ok(())
+    }
+}
@@ -1,15 +1,29 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+mod device_watcher_inotify;
+mod event_device_recorder;
+mod event_device_replay;
 mod event_device_unix;
 mod hid_device_unix;
 mod keymap_loader_yaml;
 mod prometheus_metrics;
 mod qmk_async_hid_device;
+mod recorded_event;
+mod serial_device;
+mod via_device;
+mod zmk_studio_device;
 
 #[cfg(any(test, feature = "testing"))]
 pub mod mock;
 
+pub use device_watcher_inotify::DeviceWatcherInotify;
+pub use event_device_recorder::EventDeviceRecorder;
+pub use event_device_replay::EventDeviceReplay;
 pub use event_device_unix::EventDeviceUnix;
 pub use hid_device_unix::HIDDeviceUnix;
 pub use keymap_loader_yaml::KeymapLoaderYaml;
 pub use prometheus_metrics::PrometheusMetrics;
 pub use qmk_async_hid_device::QmkAsyncHidDevice;
+pub use recorded_event::RecordedEvent;
+pub use serial_device::SerialDevice;
+pub use via_device::ViaDevice;
+pub use zmk_studio_device::ZmkStudioDevice;
@@ -1,17 +1,29 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 use evdev::KeyCode;
 use prometheus::{
-    GaugeVec, Histogram, IntCounterVec, Registry, histogram_opts, labels, opts, push_metrics,
+    GaugeVec, Histogram, HistogramVec, IntCounterVec, Registry, histogram_opts, labels, opts,
+    push_metrics,
 };
 use tokio::task::spawn_blocking;
 use tracing::error;
 
-use crate::{error::CharonError, port::Metrics};
+use crate::{
+    domain::{
+        FilterReason,
+        qmk::{QMKRecord, keycode_name},
+        stats::LatencyTrace,
+    },
+    error::CharonError,
+    port::Metrics,
+};
 
 pub struct PrometheusMetrics {
     registry: Registry,
     keypress_counter: IntCounterVec,
+    matrix_keypress_counter: IntCounterVec,
+    filtered_key_counter: IntCounterVec,
     key_latency_histogram: Histogram,
+    stage_latency_histogram: HistogramVec,
     wpm_gauge: GaugeVec,
 }
 
@@ -24,25 +36,59 @@ impl PrometheusMetrics {
             &["user", "keyboard", "key", "layout"],
         )?;
 
+        let matrix_keypress_counter = IntCounterVec::new(
+            opts!(
+                "matrix_key_presses_total",
+                "Total number of key presses by matrix position (QMK keyboards)"
+            ),
+            &["user", "keyboard", "row", "col", "keycode", "layer"],
+        )?;
+
+        let filtered_key_counter = IntCounterVec::new(
+            opts!(
+                "filtered_key_events_total",
+                "Total number of key events dropped by the key filter"
+            ),
+            &["user", "keyboard", "key", "reason"],
+        )?;
+
         let key_latency_histogram = Histogram::with_opts(histogram_opts!(
             "key_latency_secs",
             "Latency between key press and report",
             vec![0.00001, 0.0001, 0.001, 0.01, 0.025, 0.05, 0.1, 0.25]
         ))?;
 
+        let stage_latency_histogram = HistogramVec::new(
+            histogram_opts!(
+                "key_stage_latency_secs",
+                "Time spent by key event in each stage, from input device to the host",
+                vec![
+                    0.000005, 0.00001, 0.000025, 0.00005, 0.0001, 0.00025, 0.0005, 0.001, 0.0025,
+                    0.005, 0.01, 0.05
+                ]
+            ),
+            &["stage"],
+        )?;
+
         let wpm_gauge = GaugeVec::new(
             opts!("wpm", "Words per minute"),
             &["user", "keyboard", "layout"],
         )?;
 
         registry.register(Box::new(keypress_counter.clone()))?;
+        registry.register(Box::new(matrix_keypress_counter.clone()))?;
+        registry.register(Box::new(filtered_key_counter.clone()))?;
         registry.register(Box::new(key_latency_histogram.clone()))?;
+        registry.register(Box::new(stage_latency_histogram.clone()))?;
         registry.register(Box::new(wpm_gauge.clone()))?;
 
         Ok(Self {
             registry,
             keypress_counter,
+            matrix_keypress_counter,
+            filtered_key_counter,
             key_latency_histogram,
+            stage_latency_histogram,
             wpm_gauge,
         })
     }
@@ -65,11 +111,47 @@ impl Metrics for PrometheusMetrics {
             .inc();
     }
 
+    fn register_matrix_key_event(&self, record: &QMKRecord, keyboard: &str, layer: u8) {
+        self.matrix_keypress_counter
+            .with_label_values(&[
+                "ytropek".into(),
+                keyboard.into(),
+                record.row.to_string(),
+                record.col.to_string(),
+                keycode_name(record.keycode),
+                layer.to_string(),
+            ])
+            .inc();
+    }
+
+    fn register_filtered_key_event(&self, key: &KeyCode, keyboard: &str, reason: FilterReason) {
+        self.filtered_key_counter
+            .with_label_values(&[
+                "ytropek".into(),
+                keyboard.into(),
+                self.key_name(key),
+                reason.to_string(),
+            ])
+            .inc();
+    }
+
     fn register_key_to_report_time(&self, time: u64) {
         self.key_latency_histogram
             .observe((time as f64) / 1_000_000_000.0);
     }
 
+    fn register_latency_trace(&self, trace: &LatencyTrace) {
+        let secs = |nanos: u64| (nanos as f64) / 1_000_000_000.0;
+        for (stage, duration) in trace.stage_durations() {
+            self.stage_latency_histogram
+                .with_label_values(&[stage])
+                .observe(secs(duration));
+        }
+        self.stage_latency_histogram
+            .with_label_values(&["total"])
+            .observe(secs(trace.total()));
+    }
+
     fn register_wpm(&self, wpm: u16) {
         self.wpm_gauge
             .with_label_values(&["ytropek", "KeychronQ10", "qwerty"])
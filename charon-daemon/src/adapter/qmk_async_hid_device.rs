@@ -1,47 +1,40 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use crate::domain::qmk::QMKEvent;
-use async_hid::{AsyncHidRead, DeviceReaderWriter, HidBackend};
+use crate::domain::qmk::{QMKEvent, QMKRequest};
+use async_hid::{AsyncHidRead, AsyncHidWrite, DeviceReaderWriter, HidBackend};
 use futures_lite::StreamExt;
 use tracing::{error, info};
 
 use crate::{
-    config::CharonConfig,
     error::CharonError,
-    port::{QmkDevice, RawHidDevice},
+    port::{KeyboardSideChannel, RawHidDevice},
 };
 
 // https://docs.qmk.fm/features/rawhid#basic-configuration
 const USAGE_PAGE: u16 = 0xFF60;
 const USAGE_ID: u16 = 0x0061;
 
+/// Raw HID device of QMK keyboard (with Charon module). The device is opened by `connect`
+/// and closed on the first read/write error, so it can be reconnected
+/// when the keyboard is plugged back.
 pub struct QmkAsyncHidDevice {
-    device: DeviceReaderWriter,
+    vendor_id: u16,
+    product_id: u16,
+    device: Option<DeviceReaderWriter>,
 }
 
 impl QmkAsyncHidDevice {
-    pub async fn async_new(config: &CharonConfig) -> Self {
-        let vendor_id = Self::vendor_id(config);
-        let product_id = Self::product_id(config);
-        let device = Self::find_device(vendor_id, product_id)
-            .await
-            .expect("RawHid device not found");
-        Self { device }
-    }
-
-    fn vendor_id(config: &CharonConfig) -> u16 {
-        config
-            .keyboard_info()
-            .expect("keyboard info must be provided")
-            .vendor_id
-            .expect("vendor_id must be provided")
+    pub fn new(vendor_id: u16, product_id: u16) -> Self {
+        Self {
+            vendor_id,
+            product_id,
+            device: None,
+        }
     }
 
-    fn product_id(config: &CharonConfig) -> u16 {
-        config
-            .keyboard_info()
-            .expect("keyboard info must be provided")
-            .product_id
-            .expect("product_id must be provided")
+    fn device(&mut self) -> Result<&mut DeviceReaderWriter, CharonError> {
+        self.device
+            .as_mut()
+            .ok_or_else(|| CharonError::QMKError("Raw HID device not connected".into()))
     }
 
     async fn find_device(vendor_id: u16, product_id: u16) -> Option<DeviceReaderWriter> {
@@ -66,18 +59,62 @@ impl QmkAsyncHidDevice {
 }
 
 impl RawHidDevice for QmkAsyncHidDevice {
+    fn is_connected(&self) -> bool {
+        self.device.is_some()
+    }
+
+    async fn connect(&mut self) -> Result<(), CharonError> {
+        self.device = Self::find_device(self.vendor_id, self.product_id).await;
+        if self.device.is_none() {
+            return Err(CharonError::QMKError(format!(
+                "Raw HID device {:04x}:{:04x} not found",
+                self.vendor_id, self.product_id
+            )));
+        }
+        Ok(())
+    }
+
     async fn read_packet(&mut self) -> Result<(usize, [u8; 32]), CharonError> {
         let mut buf = [0u8; 32];
         let size = self
-            .device
+            .device()?
             .read_input_report(&mut buf)
             .await
-            .inspect_err(|err| error!("Failed reading raw hid: {err}"))?;
-        Ok((size, buf))
+            .inspect_err(|err| error!("Failed reading raw hid: {err}"));
+        match size {
+            Ok(size) => Ok((size, buf)),
+            Err(err) => {
+                self.device = None;
+                Err(err.into())
+            }
+        }
+    }
+
+    async fn write_packet(&mut self, packet: &[u8; 32]) -> Result<(), CharonError> {
+        // First byte is report id, which is always 0 for QMK
+        let mut buf = [0u8; 33];
+        buf[1..].copy_from_slice(packet);
+        let res = self
+            .device()?
+            .write_output_report(&buf)
+            .await
+            .inspect_err(|err| error!("Failed writing raw hid: {err}"));
+        if res.is_err() {
+            self.device = None;
+        }
+        Ok(res?)
     }
 }
 
-impl QmkDevice for QmkAsyncHidDevice {
+impl KeyboardSideChannel for QmkAsyncHidDevice {
+    fn is_connected(&self) -> bool {
+        RawHidDevice::is_connected(self)
+    }
+
+    async fn connect(&mut self) -> Result<(), CharonError> {
+        RawHidDevice::connect(self).await
+    }
+
     async fn read_event(&mut self) -> Result<Option<QMKEvent>, CharonError> {
         let (size, msg) = self.read_packet().await?;
         if size == 0 {
@@ -90,4 +127,8 @@ impl QmkDevice for QmkAsyncHidDevice {
             })?;
         Ok(Some(event))
     }
+
+    async fn send_request(&mut self, request: &QMKRequest) -> Result<(), CharonError> {
+        self.write_packet(&request.to_bytes()).await
+    }
 }
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use evdev::InputEvent;
+
+use crate::error::CharonError;
+
+/// Input event as stored in recording files (see `EventDeviceRecorder`): one event per line,
+/// `<seconds>.<microseconds> <type> <code> <value>`, where time is the evdev timestamp.
+/// Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedEvent {
+    /// Time since Unix epoch
+    pub time: Duration,
+    pub event_type: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl RecordedEvent {
+    /// Parses the recording, skipping comments and empty lines
+    pub fn parse_all(recording: &str) -> Result<Vec<Self>, CharonError> {
+        recording
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(Self::from_str)
+            .collect()
+    }
+
+    /// Input event with the same type, code and value, timestamped now
+    pub fn to_input_event(&self) -> InputEvent {
+        InputEvent::new_now(self.event_type, self.code, self.value)
+    }
+}
+
+impl From<&InputEvent> for RecordedEvent {
+    fn from(event: &InputEvent) -> Self {
+        Self {
+            time: event
+                .timestamp()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+            event_type: event.event_type().0,
+            code: event.code(),
+            value: event.value(),
+        }
+    }
+}
+
+impl fmt::Display for RecordedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:06} {} {} {}",
+            self.time.as_secs(),
+            self.time.subsec_micros(),
+            self.event_type,
+            self.code,
+            self.value
+        )
+    }
+}
+
+impl FromStr for RecordedEvent {
+    type Err = CharonError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let invalid = || CharonError::InvalidRecording(line.into());
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let [time, event_type, code, value] = parts[..] else {
+            return Err(invalid());
+        };
+        let (secs, micros) = time.split_once('.').ok_or_else(invalid)?;
+        let secs: u64 = secs.parse().map_err(|_| invalid())?;
+        // fraction of a second, i.e. `.5` is 500000 microseconds
+        let micros: u32 = format!("{micros:0<6}")
+            .get(..6)
+            .and_then(|micros| micros.parse().ok())
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            time: Duration::new(secs, micros * 1000),
+            event_type: event_type.parse().map_err(|_| invalid())?,
+            code: code.parse().map_err(|_| invalid())?,
+            value: value.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_and_parse() {
+        let event = RecordedEvent {
+            time: Duration::new(1_760_000_000, 42_000),
+            event_type: 1,
+            code: 30,
+            value: 1,
+        };
+        assert_eq!("1760000000.000042 1 30 1", event.to_string());
+        assert_eq!(event, event.to_string().parse().unwrap());
+
+        let recording = "# keyboard: main\n\n1.5 4 4 30\n1.000600 1 30 0\n";
+        let events = RecordedEvent::parse_all(recording).unwrap();
+        assert_eq!(2, events.len());
+        assert_eq!(Duration::from_millis(1500), events[0].time);
+
+        assert!("1.0 1 30".parse::<RecordedEvent>().is_err());
+        assert!("now 1 30 1".parse::<RecordedEvent>().is_err());
+    }
+}
@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::path::PathBuf;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+use tracing::{error, info};
+
+use crate::{
+    domain::{
+        Mode,
+        qmk::{QMKEvent, QMKRecord, QMKRequest},
+    },
+    error::CharonError,
+    port::KeyboardSideChannel,
+};
+
+/// Ignored by CDC-ACM devices, but required to open the port
+const BAUD_RATE: u32 = 115_200;
+
+type Reader = Lines<BufReader<ReadHalf<SerialStream>>>;
+type Writer = WriteHalf<SerialStream>;
+
+/// Keyboard side channel over serial (CDC-ACM) port, using Charon line protocol:
+/// one message per line, space separated values (see `docs/qmk.md`).
+/// The port is closed on the first read/write error, so it can be reconnected.
+pub struct SerialDevice {
+    path: PathBuf,
+    port: Option<(Reader, Writer)>,
+}
+
+impl SerialDevice {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, port: None }
+    }
+
+    fn port(&mut self) -> Result<&mut (Reader, Writer), CharonError> {
+        self.port
+            .as_mut()
+            .ok_or_else(|| CharonError::QMKError("Serial port not connected".into()))
+    }
+}
+
+impl KeyboardSideChannel for SerialDevice {
+    fn is_connected(&self) -> bool {
+        self.port.is_some()
+    }
+
+    async fn connect(&mut self) -> Result<(), CharonError> {
+        let stream = tokio_serial::new(self.path.to_string_lossy(), BAUD_RATE)
+            .open_native_async()
+            .map_err(|err| {
+                CharonError::QMKError(format!("Couldn't open {}: {err}", self.path.display()))
+            })?;
+        info!("Serial port opened: {}", self.path.display());
+        let (reader, writer) = tokio::io::split(stream);
+        self.port = Some((BufReader::new(reader).lines(), writer));
+        Ok(())
+    }
+
+    async fn read_event(&mut self) -> Result<Option<QMKEvent>, CharonError> {
+        // `next_line` is cancel safe, so no data is lost when the read is interrupted
+        let line = match self.port()?.0.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                self.port = None;
+                return Err(CharonError::QMKError("Serial port closed".into()));
+            }
+            Err(err) => {
+                error!("Failed reading serial port: {err}");
+                self.port = None;
+                return Err(err.into());
+            }
+        };
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        parse_line(&line).map(Some)
+    }
+
+    async fn send_request(&mut self, request: &QMKRequest) -> Result<(), CharonError> {
+        let line = format!("{}\n", format_request(request));
+        let writer = &mut self.port()?.1;
+        let res = writer.write_all(line.as_bytes()).await;
+        if let Err(err) = res {
+            error!("Failed writing serial port: {err}");
+            self.port = None;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+}
+
+fn format_request(request: &QMKRequest) -> String {
+    match request {
+        QMKRequest::Echo(nonce) => format!("echo {nonce}"),
+        QMKRequest::KeyboardInfo => "info".into(),
+        QMKRequest::LayerChunk { layer, offset } => format!("chunk {layer} {offset}"),
+        QMKRequest::SetRgb {
+            enabled,
+            mode,
+            hue,
+            saturation,
+            value,
+        } => format!("rgb {} {mode} {hue} {saturation} {value}", *enabled as u8),
+        QMKRequest::SetDefaultLayer(layer) => format!("set_default_layer {layer}"),
+    }
+}
+
+fn parse_line(line: &str) -> Result<QMKEvent, CharonError> {
+    let invalid = || CharonError::QMKError(format!("Invalid message: {line}"));
+    let mut parts = line.split_whitespace();
+    let name = parts.next().ok_or_else(invalid)?;
+    let args: Vec<&str> = parts.collect();
+    let arg = |i: usize| args.get(i).copied().ok_or_else(invalid);
+    let num = |i: usize| arg(i).and_then(|v| parse_number(v).ok_or_else(invalid));
+    let byte = |i: usize| num(i).and_then(|v| u8::try_from(v).map_err(|_| invalid()));
+
+    let event = match name {
+        "layer" => QMKEvent::LayerChange(byte(0)?, false),
+        "default_layer" => QMKEvent::LayerChange(byte(0)?, true),
+        "key" => {
+            let keycode = u16::try_from(num(0)?).map_err(|_| invalid())?;
+            QMKEvent::KeyEvent(QMKRecord::new(keycode, num(1)? == 1, byte(2)?, byte(3)?))
+        }
+        "mode" => match arg(0)? {
+            "pass-through" => QMKEvent::ModeChange(Mode::PassThrough),
+            "in-app" => QMKEvent::ModeChange(Mode::InApp),
+            _ => return Err(invalid()),
+        },
+        "toggle_mode" => QMKEvent::ToggleMode,
+        "echo" => QMKEvent::Echo(QMKRequest::Echo(num(0)?).to_bytes()),
+        "info" => QMKEvent::KeyboardInfo {
+            rows: byte(0)?,
+            cols: byte(1)?,
+            layers: byte(2)?,
+        },
+        "chunk" => QMKEvent::LayerChunk {
+            layer: byte(0)?,
+            offset: u16::try_from(num(1)?).map_err(|_| invalid())?,
+            keycodes: (2..args.len())
+                .map(|i| num(i).and_then(|v| u16::try_from(v).map_err(|_| invalid())))
+                .collect::<Result<_, _>>()?,
+        },
+        "rgb" => QMKEvent::RgbSet(num(0)? == 1),
+        "default_layer_set" => QMKEvent::DefaultLayerSet(byte(0)?),
+        _ => return Err(invalid()),
+    };
+    Ok(event)
+}
+
+/// Parses decimal or hex (`0x` prefixed) number
+fn parse_number(value: &str) -> Option<u32> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            QMKEvent::LayerChange(2, false),
+            parse_line("layer 2").unwrap()
+        );
+        assert_eq!(
+            QMKEvent::LayerChange(1, true),
+            parse_line("default_layer 1\r").unwrap()
+        );
+        assert_eq!(
+            QMKEvent::KeyEvent(QMKRecord::new(0x5221, true, 3, 11)),
+            parse_line("key 0x5221 1 3 11").unwrap()
+        );
+        assert_eq!(
+            QMKEvent::ModeChange(Mode::InApp),
+            parse_line("mode in-app").unwrap()
+        );
+        assert_eq!(QMKEvent::ToggleMode, parse_line("toggle_mode").unwrap());
+        assert_eq!(
+            QMKEvent::LayerChunk {
+                layer: 1,
+                offset: 12,
+                keycodes: vec![4, 5, 0x5221]
+            },
+            parse_line("chunk 1 12 4 5 0x5221").unwrap()
+        );
+
+        assert!(parse_line("layer").is_err());
+        assert!(parse_line("layer 256").is_err());
+        assert!(parse_line("mode sleepy").is_err());
+        assert!(parse_line("unknown 1").is_err());
+    }
+
+    #[test]
+    fn test_responses() {
+        let requests = [
+            QMKRequest::Echo(7),
+            QMKRequest::KeyboardInfo,
+            QMKRequest::LayerChunk {
+                layer: 1,
+                offset: 24,
+            },
+            QMKRequest::SetDefaultLayer(2),
+        ];
+        let responses = [
+            "echo 7",
+            "info 6 17 4",
+            "chunk 1 24 4 5 6",
+            "default_layer_set 2",
+        ];
+        for (request, response) in requests.iter().zip(responses) {
+            let event = parse_line(response).unwrap();
+            assert!(request.is_response(&event), "{}", format_request(request));
+        }
+        assert_eq!(
+            "rgb 1 4 120 255 200",
+            format_request(&QMKRequest::SetRgb {
+                enabled: true,
+                mode: 4,
+                hue: 120,
+                saturation: 255,
+                value: 200
+            })
+        );
+    }
+}
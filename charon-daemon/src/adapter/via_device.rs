@@ -0,0 +1,417 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::{HashMap, VecDeque};
+
+use tokio::time::{Duration, Instant, sleep_until};
+use tracing::warn;
+
+use crate::{
+    domain::qmk::{LAYER_CHUNK_SIZE, QMKEvent, QMKRecord, QMKRequest},
+    error::CharonError,
+    port::{KeyboardSideChannel, RawHidDevice},
+};
+
+// https://github.com/the-via/app/blob/main/src/utils/keyboard-api.ts
+const VIA_GET_PROTOCOL_VERSION: u8 = 0x01;
+const VIA_GET_KEYBOARD_VALUE: u8 = 0x02;
+const VIA_SWITCH_MATRIX_STATE: u8 = 0x03;
+const VIA_DYNAMIC_KEYMAP_GET_KEYCODE: u8 = 0x04;
+const VIA_CUSTOM_SET_VALUE: u8 = 0x07;
+const VIA_DYNAMIC_KEYMAP_GET_LAYER_COUNT: u8 = 0x11;
+const VIA_DYNAMIC_KEYMAP_GET_BUFFER: u8 = 0x12;
+const VIA_UNHANDLED: u8 = 0xFF;
+
+const VIA_RGB_MATRIX_CHANNEL: u8 = 0x03;
+const VIA_RGB_MATRIX_BRIGHTNESS: u8 = 0x01;
+const VIA_RGB_MATRIX_EFFECT: u8 = 0x02;
+const VIA_RGB_MATRIX_COLOR: u8 = 0x04;
+
+const KC_TRNS: u16 = 0x0001;
+
+/// Max number of switch matrix bytes returned by VIA in a single report
+const VIA_MATRIX_STATE_SIZE: u8 = 28;
+
+/// Max number of matrix columns: VIA reports rows of QMK's `matrix_row_t` (up to 32 bits)
+const MAX_COLS: u8 = 32;
+
+/// Max number of packets read while waiting for a response
+/// (stale responses of cancelled requests are skipped)
+const MAX_READS: usize = 8;
+
+/// Adapter for keyboards running stock VIA or Vial firmware (no Charon module).
+/// Charon's requests are translated into VIA commands, so the keymap can be downloaded
+/// the same way as from Charon firmware. Key events and layer changes are not sent by
+/// VIA, so the switch matrix is polled instead, and the current layer is tracked from
+/// the layer keycodes (MO, LT, TG, TO, DF) of pressed keys.
+pub struct ViaDevice<R: RawHidDevice> {
+    device: R,
+    rows: u8,
+    cols: u8,
+    poll_interval: Duration,
+    next_poll: Instant,
+    events: VecDeque<QMKEvent>,
+    matrix: Vec<u32>,
+    pressed: HashMap<(u8, u8), u16>,
+    layer_state: u32,
+    default_layer: u8,
+}
+
+impl<R: RawHidDevice> ViaDevice<R> {
+    pub fn new(
+        device: R,
+        rows: u8,
+        cols: u8,
+        poll_interval: Duration,
+    ) -> Result<Self, CharonError> {
+        if cols == 0 || cols > MAX_COLS {
+            return Err(CharonError::QMKError(format!(
+                "Invalid matrix_size: {cols} columns (VIA supports 1 to {MAX_COLS})"
+            )));
+        }
+        // Switch matrix state must fit into a single packet
+        let polled_rows = rows.min(VIA_MATRIX_STATE_SIZE / cols.div_ceil(8));
+        if polled_rows < rows {
+            warn!("Matrix too big for VIA, only {polled_rows} rows will be polled");
+        }
+        Ok(Self {
+            device,
+            rows,
+            cols,
+            poll_interval,
+            next_poll: Instant::now(),
+            events: VecDeque::new(),
+            matrix: vec![0; polled_rows as usize],
+            pressed: HashMap::new(),
+            layer_state: 0,
+            default_layer: 0,
+        })
+    }
+
+    /// Sends VIA command and waits for its response (that starts with the same command id).
+    async fn exchange(&mut self, command: &[u8]) -> Result<[u8; 32], CharonError> {
+        let mut packet = [0u8; 32];
+        packet[..command.len()].copy_from_slice(command);
+        self.device.write_packet(&packet).await?;
+        for _ in 0..MAX_READS {
+            let (_, response) = self.device.read_packet().await?;
+            if response[0] == command[0] {
+                return Ok(response);
+            }
+            if response[0] == VIA_UNHANDLED {
+                return Err(CharonError::QMKError(format!(
+                    "VIA command {:#04x} not supported",
+                    command[0]
+                )));
+            }
+        }
+        Err(CharonError::QMKError(format!(
+            "No response to VIA command {:#04x}",
+            command[0]
+        )))
+    }
+
+    async fn layer_chunk(&mut self, layer: u8, offset: u16) -> Result<QMKEvent, CharonError> {
+        let key_count = self.rows as usize * self.cols as usize;
+        let count = LAYER_CHUNK_SIZE.min(key_count.saturating_sub(offset as usize));
+        // Dynamic keymap buffer holds big-endian keycodes of all layers, one after another
+        let start = ((layer as usize * key_count + offset as usize) * 2) as u16;
+        let [hi, lo] = start.to_be_bytes();
+        let response = self
+            .exchange(&[VIA_DYNAMIC_KEYMAP_GET_BUFFER, hi, lo, (count * 2) as u8])
+            .await?;
+        let mut keycodes = vec![0; LAYER_CHUNK_SIZE];
+        for (i, keycode) in keycodes.iter_mut().take(count).enumerate() {
+            *keycode = u16::from_be_bytes([response[4 + i * 2], response[5 + i * 2]]);
+        }
+        Ok(QMKEvent::LayerChunk {
+            layer,
+            offset,
+            keycodes,
+        })
+    }
+
+    async fn set_rgb(
+        &mut self,
+        enabled: bool,
+        mode: u8,
+        hue: u8,
+        sat: u8,
+        value: u8,
+    ) -> Result<(), CharonError> {
+        let brightness = if enabled { value } else { 0 };
+        let channel = VIA_RGB_MATRIX_CHANNEL;
+        self.exchange(&[
+            VIA_CUSTOM_SET_VALUE,
+            channel,
+            VIA_RGB_MATRIX_BRIGHTNESS,
+            brightness,
+        ])
+        .await?;
+        if enabled {
+            self.exchange(&[VIA_CUSTOM_SET_VALUE, channel, VIA_RGB_MATRIX_EFFECT, mode])
+                .await?;
+            self.exchange(&[
+                VIA_CUSTOM_SET_VALUE,
+                channel,
+                VIA_RGB_MATRIX_COLOR,
+                hue,
+                sat,
+            ])
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn keycode(&mut self, layer: u8, row: u8, col: u8) -> Result<u16, CharonError> {
+        let response = self
+            .exchange(&[VIA_DYNAMIC_KEYMAP_GET_KEYCODE, layer, row, col])
+            .await?;
+        Ok(u16::from_be_bytes([response[4], response[5]]))
+    }
+
+    fn highest_layer(&self) -> u8 {
+        let state = self.layer_state | (1 << self.default_layer);
+        (31 - state.leading_zeros()) as u8
+    }
+
+    /// Keycode of the key, as resolved by QMK: from the highest active layer
+    /// where the key is not transparent.
+    async fn resolve_keycode(&mut self, row: u8, col: u8) -> Result<u16, CharonError> {
+        let state = self.layer_state | (1 << self.default_layer);
+        for layer in (0..32u8).rev().filter(|l| state & (1 << l) != 0) {
+            let keycode = self.keycode(layer, row, col).await?;
+            if keycode != KC_TRNS {
+                return Ok(keycode);
+            }
+        }
+        Ok(0)
+    }
+
+    async fn poll_matrix(&mut self) -> Result<(), CharonError> {
+        let response = self
+            .exchange(&[VIA_GET_KEYBOARD_VALUE, VIA_SWITCH_MATRIX_STATE])
+            .await?;
+        let row_size = self.cols.div_ceil(8) as usize;
+        for row in 0..self.matrix.len() {
+            let start = 2 + row * row_size;
+            let state = response[start..start + row_size]
+                .iter()
+                .fold(0u32, |acc, byte| (acc << 8) | *byte as u32);
+            let changed = state ^ self.matrix[row];
+            self.matrix[row] = state;
+            for col in (0..self.cols).filter(|col| changed & (1 << col) != 0) {
+                self.key_event(row as u8, col, state & (1 << col) != 0)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn key_event(&mut self, row: u8, col: u8, pressed: bool) -> Result<(), CharonError> {
+        let keycode = if pressed {
+            let keycode = self.resolve_keycode(row, col).await?;
+            self.pressed.insert((row, col), keycode);
+            keycode
+        } else {
+            self.pressed.remove(&(row, col)).unwrap_or_default()
+        };
+        self.events.push_back(QMKEvent::KeyEvent(QMKRecord::new(
+            keycode, pressed, row, col,
+        )));
+        self.track_layer(keycode, pressed);
+        Ok(())
+    }
+
+    fn track_layer(&mut self, keycode: u16, pressed: bool) {
+        let layer = (keycode & 0x1F) as u8;
+        let prev = self.highest_layer();
+        match keycode {
+            // MO(layer)
+            0x5220..=0x523F if pressed => self.layer_state |= 1 << layer,
+            0x5220..=0x523F => self.layer_state &= !(1 << layer),
+            // LT(layer, kc) - tap and hold can't be told apart, so it's handled like MO
+            0x4000..=0x4FFF => {
+                let layer = (keycode >> 8) & 0x0F;
+                if pressed {
+                    self.layer_state |= 1 << layer;
+                } else {
+                    self.layer_state &= !(1 << layer);
+                }
+            }
+            // TG(layer)
+            0x5260..=0x527F if pressed => self.layer_state ^= 1 << layer,
+            // TO(layer)
+            0x5200..=0x521F if pressed => self.layer_state = 1 << layer,
+            // DF(layer)
+            0x5240..=0x525F if pressed => {
+                self.default_layer = layer;
+                self.events.push_back(QMKEvent::LayerChange(layer, true));
+                return;
+            }
+            _ => return,
+        }
+        let current = self.highest_layer();
+        if current != prev {
+            self.events.push_back(QMKEvent::LayerChange(current, false));
+        }
+    }
+}
+
+impl<R: RawHidDevice> KeyboardSideChannel for ViaDevice<R> {
+    fn is_connected(&self) -> bool {
+        self.device.is_connected()
+    }
+
+    async fn connect(&mut self) -> Result<(), CharonError> {
+        self.device.connect().await?;
+        self.matrix.iter_mut().for_each(|row| *row = 0);
+        self.pressed.clear();
+        self.layer_state = 0;
+        Ok(())
+    }
+
+    async fn read_event(&mut self) -> Result<Option<QMKEvent>, CharonError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+            sleep_until(self.next_poll).await;
+            self.next_poll = Instant::now() + self.poll_interval;
+            self.poll_matrix().await?;
+        }
+    }
+
+    async fn send_request(&mut self, request: &QMKRequest) -> Result<(), CharonError> {
+        let event = match *request {
+            QMKRequest::Echo(_) => {
+                self.exchange(&[VIA_GET_PROTOCOL_VERSION]).await?;
+                QMKEvent::Echo(request.to_bytes())
+            }
+            QMKRequest::KeyboardInfo => {
+                let response = self.exchange(&[VIA_DYNAMIC_KEYMAP_GET_LAYER_COUNT]).await?;
+                QMKEvent::KeyboardInfo {
+                    rows: self.rows,
+                    cols: self.cols,
+                    layers: response[1],
+                }
+            }
+            QMKRequest::LayerChunk { layer, offset } => self.layer_chunk(layer, offset).await?,
+            QMKRequest::SetRgb {
+                enabled,
+                mode,
+                hue,
+                saturation,
+                value,
+            } => {
+                self.set_rgb(enabled, mode, hue, saturation, value).await?;
+                QMKEvent::RgbSet(true)
+            }
+            QMKRequest::SetDefaultLayer(_) => {
+                return Err(CharonError::QMKError(
+                    "Setting default layer is not supported by VIA".into(),
+                ));
+            }
+        };
+        self.events.push_back(event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::adapter::mock::ViaKeyboardMock;
+
+    fn keyboard() -> ViaKeyboardMock {
+        // 2 layers of 2x3 keyboard; the last key is MO(1)
+        ViaKeyboardMock::new(
+            2,
+            3,
+            vec![
+                vec![0x04, 0x05, 0x06, 0x07, 0x08, 0x5221],
+                vec![0x1E, KC_TRNS, 0x20, 0x21, 0x22, KC_TRNS],
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_keymap_requests() {
+        let mut via = ViaDevice::new(keyboard(), 2, 3, Duration::from_millis(1)).unwrap();
+        via.connect().await.unwrap();
+
+        via.send_request(&QMKRequest::KeyboardInfo).await.unwrap();
+        let info = via.read_event().await.unwrap();
+        assert_eq!(
+            Some(QMKEvent::KeyboardInfo {
+                rows: 2,
+                cols: 3,
+                layers: 2
+            }),
+            info
+        );
+
+        via.send_request(&QMKRequest::LayerChunk {
+            layer: 1,
+            offset: 0,
+        })
+        .await
+        .unwrap();
+        let Some(QMKEvent::LayerChunk { keycodes, .. }) = via.read_event().await.unwrap() else {
+            panic!("Expected layer chunk");
+        };
+        assert_eq!(
+            &[0x1E, KC_TRNS, 0x20, 0x21, 0x22, KC_TRNS, 0],
+            &keycodes[..7]
+        );
+
+        let result = via.send_request(&QMKRequest::SetDefaultLayer(1)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_polling_and_layers() {
+        let keyboard = keyboard();
+        let state = keyboard.state().clone();
+        let mut via = ViaDevice::new(keyboard, 2, 3, Duration::from_millis(1)).unwrap();
+        via.connect().await.unwrap();
+
+        state.lock().await.press(1, 2); // MO(1)
+        let record = |keycode, pressed, row, col| {
+            Some(QMKEvent::KeyEvent(QMKRecord::new(
+                keycode, pressed, row, col,
+            )))
+        };
+        assert_eq!(record(0x5221, true, 1, 2), via.read_event().await.unwrap());
+        assert_eq!(
+            Some(QMKEvent::LayerChange(1, false)),
+            via.read_event().await.unwrap()
+        );
+
+        // transparent key falls back to the default layer
+        state.lock().await.press(0, 1);
+        assert_eq!(record(0x05, true, 0, 1), via.read_event().await.unwrap());
+
+        state.lock().await.release(1, 2);
+        assert_eq!(record(0x5221, false, 1, 2), via.read_event().await.unwrap());
+        assert_eq!(
+            Some(QMKEvent::LayerChange(0, false)),
+            via.read_event().await.unwrap()
+        );
+
+        // released key reports the keycode it was pressed with
+        state.lock().await.release(0, 1);
+        assert_eq!(record(0x05, false, 0, 1), via.read_event().await.unwrap());
+    }
+
+    #[test]
+    fn test_matrix_size() {
+        let interval = Duration::from_millis(1);
+        // 3 bytes per row, 28 bytes per report
+        let via = ViaDevice::new(keyboard(), 12, 20, interval).unwrap();
+        assert_eq!(9, via.matrix.len());
+        let via = ViaDevice::new(keyboard(), 6, 32, interval).unwrap();
+        assert_eq!(6, via.matrix.len());
+
+        assert!(ViaDevice::new(keyboard(), 6, 33, interval).is_err());
+        assert!(ViaDevice::new(keyboard(), 6, 0, interval).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+use tracing::{debug, error, info};
+
+use crate::{
+    domain::qmk::{QMKEvent, QMKRequest},
+    error::CharonError,
+    port::KeyboardSideChannel,
+};
+
+// https://zmk.dev/docs/development/studio-rpc-protocol
+const FRAME_SOF: u8 = 0xAB;
+const FRAME_ESC: u8 = 0xAC;
+const FRAME_EOF: u8 = 0xAD;
+
+/// Ignored by CDC-ACM devices, but required to open the port
+const BAUD_RATE: u32 = 115_200;
+
+// Protobuf field numbers of used messages (zmk-studio-messages: studio.proto,
+// core.proto, keymap.proto)
+const REQUEST_ID: u32 = 1;
+const SUBSYSTEM_META: u32 = 2;
+const SUBSYSTEM_CORE: u32 = 3;
+const SUBSYSTEM_KEYMAP: u32 = 5;
+const RESPONSE_REQUEST_RESPONSE: u32 = 1;
+const CORE_GET_LOCK_STATE: u32 = 2;
+const KEYMAP_GET_KEYMAP: u32 = 1;
+const KEYMAP_LAYERS: u32 = 1;
+
+/// Keyboard side channel to ZMK keyboard with Studio RPC enabled (`CONFIG_ZMK_STUDIO`),
+/// over serial (CDC-ACM) port.
+///
+/// Studio RPC doesn't report active layer nor key events, so only requests are supported:
+/// `Echo` (answered with lock state) and `KeyboardInfo` (number of layers; the matrix
+/// is not exposed, so keymap is not downloaded). Layer changes of ZMK keyboards require
+/// Charon module and line protocol (`SerialDevice`).
+pub struct ZmkStudioDevice {
+    path: PathBuf,
+    port: Option<SerialStream>,
+    decoder: FrameDecoder,
+    frames: VecDeque<Vec<u8>>,
+    next_request_id: u32,
+    pending: HashMap<u32, QMKRequest>,
+}
+
+impl ZmkStudioDevice {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            port: None,
+            decoder: FrameDecoder::default(),
+            frames: VecDeque::new(),
+            next_request_id: 1,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn port(&mut self) -> Result<&mut SerialStream, CharonError> {
+        self.port
+            .as_mut()
+            .ok_or_else(|| CharonError::QMKError("Serial port not connected".into()))
+    }
+
+    fn process_frame(&mut self, frame: &[u8]) -> Result<Option<QMKEvent>, CharonError> {
+        let Some(response) = field(frame, RESPONSE_REQUEST_RESPONSE)?.and_then(Value::bytes) else {
+            // Notifications (lock state, unsaved changes) are not used
+            debug!("ZMK Studio notification ignored");
+            return Ok(None);
+        };
+        let request_id = field(response, REQUEST_ID)?
+            .and_then(Value::varint)
+            .unwrap_or_default() as u32;
+        let Some(request) = self.pending.remove(&request_id) else {
+            debug!("Unexpected ZMK Studio response: {request_id}");
+            return Ok(None);
+        };
+        if field(response, SUBSYSTEM_META)?.is_some() {
+            return Err(CharonError::QMKError(format!(
+                "ZMK Studio request failed (keyboard locked?): {request:?}"
+            )));
+        }
+
+        let event = match request {
+            QMKRequest::Echo(_) => QMKEvent::Echo(request.to_bytes()),
+            QMKRequest::KeyboardInfo => {
+                let keymap = field(response, SUBSYSTEM_KEYMAP)?
+                    .and_then(Value::bytes)
+                    .map(|keymap| field(keymap, KEYMAP_GET_KEYMAP))
+                    .transpose()?
+                    .flatten()
+                    .and_then(Value::bytes)
+                    .unwrap_or_default();
+                let layers = fields(keymap)?
+                    .iter()
+                    .filter(|(number, _)| *number == KEYMAP_LAYERS)
+                    .count();
+                QMKEvent::KeyboardInfo {
+                    rows: 0,
+                    cols: 0,
+                    layers: layers as u8,
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+}
+
+impl KeyboardSideChannel for ZmkStudioDevice {
+    fn is_connected(&self) -> bool {
+        self.port.is_some()
+    }
+
+    async fn connect(&mut self) -> Result<(), CharonError> {
+        let stream = tokio_serial::new(self.path.to_string_lossy(), BAUD_RATE)
+            .open_native_async()
+            .map_err(|err| {
+                CharonError::QMKError(format!("Couldn't open {}: {err}", self.path.display()))
+            })?;
+        info!("ZMK Studio port opened: {}", self.path.display());
+        self.port = Some(stream);
+        self.decoder = FrameDecoder::default();
+        self.frames.clear();
+        self.pending.clear();
+        Ok(())
+    }
+
+    async fn read_event(&mut self) -> Result<Option<QMKEvent>, CharonError> {
+        if let Some(frame) = self.frames.pop_front() {
+            return self.process_frame(&frame);
+        }
+        let mut buf = [0u8; 64];
+        // `read` is cancel safe, and the received bytes are buffered by the decoder
+        let size = match self.port()?.read(&mut buf).await {
+            Ok(0) => Err(CharonError::QMKError("Serial port closed".into())),
+            Ok(size) => Ok(size),
+            Err(err) => {
+                error!("Failed reading serial port: {err}");
+                Err(err.into())
+            }
+        }
+        .inspect_err(|_| self.port = None)?;
+        for byte in &buf[..size] {
+            if let Some(frame) = self.decoder.push(*byte) {
+                self.frames.push_back(frame);
+            }
+        }
+        Ok(None)
+    }
+
+    async fn send_request(&mut self, request: &QMKRequest) -> Result<(), CharonError> {
+        let subsystem = match request {
+            QMKRequest::Echo(_) => (SUBSYSTEM_CORE, CORE_GET_LOCK_STATE),
+            QMKRequest::KeyboardInfo => (SUBSYSTEM_KEYMAP, KEYMAP_GET_KEYMAP),
+            _ => {
+                return Err(CharonError::QMKError(format!(
+                    "Request not supported by ZMK Studio: {request:?}"
+                )));
+            }
+        };
+        let request_id = self.next_request_id;
+        self.next_request_id = self.next_request_id.wrapping_add(1);
+
+        let frame = encode_frame(&encode_request(request_id, subsystem));
+        if let Err(err) = self.port()?.write_all(&frame).await {
+            error!("Failed writing serial port: {err}");
+            self.port = None;
+            return Err(err.into());
+        }
+        self.pending.insert(request_id, request.clone());
+        Ok(())
+    }
+}
+
+/// Encodes `Request { request_id, <subsystem> { <request_type> = true } }`
+fn encode_request(request_id: u32, (subsystem, request_type): (u32, u32)) -> Vec<u8> {
+    let mut inner = Vec::new();
+    put_varint_field(&mut inner, request_type, 1);
+    let mut message = Vec::new();
+    put_varint_field(&mut message, REQUEST_ID, request_id.into());
+    put_bytes_field(&mut message, subsystem, &inner);
+    message
+}
+
+fn encode_frame(message: &[u8]) -> Vec<u8> {
+    let mut frame = vec![FRAME_SOF];
+    for byte in message {
+        if matches!(*byte, FRAME_SOF | FRAME_ESC | FRAME_EOF) {
+            frame.push(FRAME_ESC);
+        }
+        frame.push(*byte);
+    }
+    frame.push(FRAME_EOF);
+    frame
+}
+
+/// Extracts messages from the stream of bytes
+#[derive(Default)]
+struct FrameDecoder {
+    frame: Option<Vec<u8>>,
+    escaped: bool,
+}
+
+impl FrameDecoder {
+    /// Returns the message, if the byte completes it.
+    fn push(&mut self, byte: u8) -> Option<Vec<u8>> {
+        if self.escaped {
+            self.escaped = false;
+            if let Some(frame) = self.frame.as_mut() {
+                frame.push(byte);
+            }
+            return None;
+        }
+        match byte {
+            FRAME_SOF => self.frame = Some(Vec::new()),
+            FRAME_ESC => self.escaped = true,
+            FRAME_EOF => return self.frame.take(),
+            _ => {
+                if let Some(frame) = self.frame.as_mut() {
+                    frame.push(byte);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> Value<'a> {
+    fn varint(self) -> Option<u64> {
+        match self {
+            Value::Varint(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn bytes(self) -> Option<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_varint_field(buf: &mut Vec<u8>, number: u32, value: u64) {
+    put_varint(buf, (number as u64) << 3);
+    put_varint(buf, value);
+}
+
+fn put_bytes_field(buf: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+    put_varint(buf, ((number as u64) << 3) | 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, CharonError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data
+            .get(*pos)
+            .ok_or_else(|| CharonError::QMKError("Truncated protobuf message".into()))?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(CharonError::QMKError("Invalid protobuf varint".into()))
+}
+
+/// Decodes all fields of protobuf message: `(field number, value)`
+fn fields(data: &[u8]) -> Result<Vec<(u32, Value<'_>)>, CharonError> {
+    let truncated = || CharonError::QMKError("Truncated protobuf message".into());
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let tag = read_varint(data, &mut pos)?;
+        let value = match tag & 0x07 {
+            0 => Value::Varint(read_varint(data, &mut pos)?),
+            2 => {
+                let len = read_varint(data, &mut pos)? as usize;
+                let bytes = data.get(pos..pos + len).ok_or_else(truncated)?;
+                pos += len;
+                Value::Bytes(bytes)
+            }
+            1 => {
+                pos += 8;
+                Value::Fixed
+            }
+            5 => {
+                pos += 4;
+                Value::Fixed
+            }
+            wire_type => {
+                return Err(CharonError::QMKError(format!(
+                    "Unsupported protobuf wire type: {wire_type}"
+                )));
+            }
+        };
+        if pos > data.len() {
+            return Err(truncated());
+        }
+        fields.push(((tag >> 3) as u32, value));
+    }
+    Ok(fields)
+}
+
+fn field(data: &[u8], number: u32) -> Result<Option<Value<'_>>, CharonError> {
+    Ok(fields(data)?
+        .into_iter()
+        .find(|(n, _)| *n == number)
+        .map(|(_, value)| value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let message = [0x08, FRAME_SOF, 0x01, FRAME_ESC, FRAME_EOF];
+        let frame = encode_frame(&message);
+        assert_eq!(10, frame.len());
+
+        // Garbage before the frame is skipped
+        let mut decoder = FrameDecoder::default();
+        let decoded: Vec<Vec<u8>> = [0x01, 0x02]
+            .iter()
+            .chain(&frame)
+            .filter_map(|byte| decoder.push(*byte))
+            .collect();
+        assert_eq!(vec![message.to_vec()], decoded);
+    }
+
+    #[test]
+    fn test_keyboard_info() {
+        let mut device = ZmkStudioDevice::new("/dev/null".into());
+        device.pending.insert(300, QMKRequest::KeyboardInfo);
+
+        assert_eq!(
+            vec![0x08, 0xAC, 0x02, 0x2A, 0x02, 0x08, 0x01],
+            encode_request(300, (SUBSYSTEM_KEYMAP, KEYMAP_GET_KEYMAP))
+        );
+
+        // Response { request_response { request_id: 300, keymap { get_keymap { layers: [2] } } } }
+        let mut layer = Vec::new();
+        put_varint_field(&mut layer, 1, 0);
+        let mut keymap = Vec::new();
+        for _ in 0..3 {
+            put_bytes_field(&mut keymap, KEYMAP_LAYERS, &layer);
+        }
+        put_varint_field(&mut keymap, 2, 8);
+        let mut keymap_response = Vec::new();
+        put_bytes_field(&mut keymap_response, KEYMAP_GET_KEYMAP, &keymap);
+        let mut request_response = Vec::new();
+        put_varint_field(&mut request_response, REQUEST_ID, 300);
+        put_bytes_field(&mut request_response, SUBSYSTEM_KEYMAP, &keymap_response);
+        let mut response = Vec::new();
+        put_bytes_field(&mut response, RESPONSE_REQUEST_RESPONSE, &request_response);
+
+        let event = device.process_frame(&response).unwrap();
+        assert_eq!(
+            Some(QMKEvent::KeyboardInfo {
+                rows: 0,
+                cols: 0,
+                layers: 3
+            }),
+            event
+        );
+        assert!(device.pending.is_empty());
+        assert_eq!(None, device.process_frame(&response).unwrap());
+    }
+}
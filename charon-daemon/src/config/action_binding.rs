@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+use super::charon_config::key_sequence;
+use crate::domain::{Action, KeySequence};
+
+/// Binds a key shortcut (or a sequence of shortcuts) to an action, i.e.:
+/// ```toml
+/// [[bindings]]
+/// shortcut = "ctrl+alt+t"
+/// action = "run-command"
+/// command = "notify-send"
+/// args = ["Hello"]
+///
+/// [[bindings]]
+/// shortcut = "f7 e"
+/// action = "switch-app"
+/// app = "editor"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionBinding {
+    #[serde(with = "key_sequence")]
+    pub shortcut: KeySequence,
+
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+impl ActionBinding {
+    pub fn new(shortcut: impl Into<KeySequence>, action: Action) -> Self {
+        Self {
+            shortcut: shortcut.into(),
+            action,
+        }
+    }
+}
@@ -1,12 +1,18 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 use serde::{Deserialize, Serialize};
-use std::{fs::read_to_string, path::PathBuf};
+use std::{collections::HashMap, fs::read_to_string, path::PathBuf};
 use tracing::{debug, warn};
 
-use super::{InputConfig, defaults};
+use super::{
+    ActionBinding, HostProfile, InputConfig, ProcessorConfig, ReportBufferPolicy, TypingProfile,
+    defaults,
+};
 use crate::{
-    config::keyboard::{KeyboardConfig, KeyboardGroup},
-    domain::KeyShortcut,
+    config::keyboard::{
+        DeviceEntry, KeyboardConfig, KeyboardGroup, KeyboardRole, KeyboardSettings,
+    },
+    domain::{Action, KeyShortcut},
+    util::evdev::discover_keyboards,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +26,20 @@ pub struct CharonConfig {
     #[serde(default = "defaults::default_typing_interval")]
     pub typing_interval: u8,
 
+    /// Timing of text typed by `Typist`, unless set by host profile or the request
+    #[serde(default)]
+    pub typing_profile: TypingProfile,
+
+    #[serde(default)]
+    pub report_buffer_policy: ReportBufferPolicy,
+
+    #[serde(default = "defaults::default_report_buffer_size")]
+    pub report_buffer_size: usize,
+
+    /// How often (in milliseconds) KeyWriter checks whether the host is back
+    #[serde(default = "defaults::default_host_retry_interval")]
+    pub host_retry_interval: u64,
+
     #[serde(default = "defaults::default_server_socket")]
     pub server_socket: PathBuf,
 
@@ -38,15 +58,71 @@ pub struct CharonConfig {
     #[serde(default = "defaults::default_awake_host_shortcut")]
     pub awake_host_shortcut: KeyShortcut,
 
+    /// Time (in milliseconds) to wait for the next step of a key sequence
+    #[serde(default = "defaults::default_sequence_timeout")]
+    pub sequence_timeout: u64,
+
+    /// Time (in milliseconds) after which a pressed key counts as held rather than tapped
+    #[serde(default = "defaults::default_hold_threshold")]
+    pub hold_threshold: u64,
+
+    /// Time (in milliseconds) after which a key held without autorepeat is checked
+    /// and released, unless the keyboard reports it as still pressed (0 disables the check).
+    /// Keyboards that can't report their key state are not checked.
+    #[serde(default = "defaults::default_stuck_key_timeout")]
+    pub stuck_key_timeout: u64,
+
+    /// Time (in milliseconds) to wait for QMK keyboard to respond to a request
+    #[serde(default = "defaults::default_qmk_request_timeout")]
+    pub qmk_request_timeout: u64,
+
+    /// Time (in milliseconds) between attempts to connect to absent QMK keyboard
+    #[serde(default = "defaults::default_qmk_reconnect_interval")]
+    pub qmk_reconnect_interval: u64,
+
+    /// Time (in milliseconds) between reads of switch matrix of VIA keyboards
+    #[serde(default = "defaults::default_via_poll_interval")]
+    pub via_poll_interval: u64,
+
+    /// Additional key shortcuts with actions they trigger
+    #[serde(default)]
+    pub bindings: Vec<ActionBinding>,
+
     #[serde(default)]
     pub host_mac_address: Option<Vec<u8>>,
 
     #[serde(default)]
     pub enable_telemetry: bool,
 
+    /// Measures time spent by key events in each stage (scanner, pipeline, processors,
+    /// HID write). Traces are exported as telemetry histograms and `LatencyTrace` events.
+    #[serde(default)]
+    pub latency_tracing: bool,
+
+    /// Decodes keys sent to the host into text, using the keymap of the active host profile,
+    /// and sends it to IPC clients (`TextTyped` events). Note that it includes passwords.
+    #[serde(default)]
+    pub text_decoding: bool,
+
     #[serde(default)]
     pub keyboards: Option<KeyboardConfig>,
 
+    /// Processors of the key event pipeline, in order (see `ProcessorRegistry`)
+    #[serde(default = "defaults::default_pipeline")]
+    pub pipeline: Vec<ProcessorConfig>,
+
+    /// Directory of user scripts (see `script` processor)
+    #[serde(default = "defaults::default_scripts_dir")]
+    pub scripts_dir: PathBuf,
+
+    /// Default time limit (in milliseconds) of a single script invocation
+    #[serde(default = "defaults::default_script_timeout")]
+    pub script_timeout: u64,
+
+    /// Per-keyboard settings (role, remapping, shortcuts), where key is keyboard alias
+    #[serde(default)]
+    pub keyboard_settings: HashMap<String, KeyboardSettings>,
+
     #[serde(default = "defaults::default_time_to_sleep")]
     pub time_to_sleep: u64,
 
@@ -59,6 +135,15 @@ pub struct CharonConfig {
     #[serde(default = "defaults::default_stats_file")]
     pub stats_file: PathBuf,
 
+    /// Key presses of QMK keyboards counted by matrix position and keycode
+    #[serde(default = "defaults::default_heatmap_file")]
+    pub heatmap_file: PathBuf,
+
+    /// Keymaps downloaded from QMK keyboards are cached in this directory
+    /// (in `<alias>.json` files)
+    #[serde(default = "defaults::default_qmk_keymap_dir")]
+    pub qmk_keymap_dir: PathBuf,
+
     #[serde(default = "defaults::default_stats_save_interval")]
     pub stats_save_interval: u64,
 
@@ -73,6 +158,10 @@ pub struct CharonConfig {
 
     #[serde(default = "defaults::default_host_keymap")]
     pub host_keymap: String,
+
+    /// Host profiles that can be switched at runtime, where key is profile name
+    #[serde(default)]
+    pub host_profiles: HashMap<String, HostProfile>,
 }
 
 impl CharonConfig {
@@ -95,6 +184,21 @@ impl CharonConfig {
                         .collect()
                 })
                 .unwrap_or_default(),
+            InputConfig::Auto => {
+                let keyboards = discover_keyboards();
+                if keyboards.is_empty() {
+                    warn!("No keyboards discovered, waiting for one to be connected");
+                    return vec![(String::from("KeyScanner"), self.clone())];
+                }
+                keyboards
+                    .into_iter()
+                    .map(|keyboard| {
+                        let mut config = self.clone();
+                        config.keyboard = keyboard.input;
+                        (keyboard.alias, config)
+                    })
+                    .collect()
+            }
             _ => {
                 let config = self.clone();
                 let name = String::from("KeyScanner");
@@ -110,6 +214,68 @@ impl CharonConfig {
         self.keyboards.as_ref().map(|kbs| kbs.groups.get(alias))?
     }
 
+    pub fn keyboard_group(&self, alias: &str) -> Option<&KeyboardGroup> {
+        self.keyboards.as_ref()?.groups.get(alias)
+    }
+
+    /// Returns keyboard groups (with their aliases) that have Raw HID enabled
+    /// and can be found by vendor and product id.
+    pub fn qmk_keyboards(&self) -> Vec<(&String, &KeyboardGroup)> {
+        let Some(keyboards) = self.keyboards.as_ref() else {
+            return Vec::new();
+        };
+        keyboards
+            .groups
+            .iter()
+            .filter(|(_, group)| {
+                group.raw_hid_enabled && group.vendor_id.is_some() && group.product_id.is_some()
+            })
+            .collect()
+    }
+
+    /// Returns keyboard groups (with their aliases) that use serial port as side channel
+    /// (only if Raw HID is not enabled).
+    pub fn serial_keyboards(&self) -> Vec<(&String, &KeyboardGroup)> {
+        let Some(keyboards) = self.keyboards.as_ref() else {
+            return Vec::new();
+        };
+        keyboards
+            .groups
+            .iter()
+            .filter(|(_, group)| !group.raw_hid_enabled && group.serial_port.is_some())
+            .collect()
+    }
+
+    pub fn device_entry(&self, alias: &str) -> Option<&DeviceEntry> {
+        self.keyboards
+            .as_ref()?
+            .groups
+            .values()
+            .flat_map(|group| group.devices.iter())
+            .find(|dev| dev.alias == alias)
+    }
+
+    /// Returns all key bindings: the system shortcuts (quit, toggle mode, awake host)
+    /// followed by the ones defined in `bindings` section.
+    pub fn action_bindings(&self) -> Vec<ActionBinding> {
+        let system = [
+            ActionBinding::new(self.quit_shortcut.clone(), Action::Quit),
+            ActionBinding::new(self.toggle_mode_shortcut.clone(), Action::ToggleMode),
+            ActionBinding::new(self.awake_host_shortcut.clone(), Action::AwakeHost),
+        ];
+        system
+            .into_iter()
+            .chain(self.bindings.iter().cloned())
+            .collect()
+    }
+
+    pub fn keyboard_role(&self, alias: &str) -> KeyboardRole {
+        self.keyboard_settings
+            .get(alias)
+            .map(|settings| settings.role)
+            .unwrap_or_default()
+    }
+
     pub fn from_file() -> eyre::Result<Self> {
         let mut path = PathBuf::new();
         path.push(std::env::var("XDG_CONFIG_HOME")?);
@@ -137,28 +303,48 @@ impl Default for CharonConfig {
             keyboard: InputConfig::default(),
             hid_keyboard: defaults::default_hid_keyboard(),
             typing_interval: defaults::default_typing_interval(),
+            typing_profile: TypingProfile::default(),
+            report_buffer_policy: ReportBufferPolicy::default(),
+            report_buffer_size: defaults::default_report_buffer_size(),
+            host_retry_interval: defaults::default_host_retry_interval(),
             server_socket: defaults::default_server_socket(),
             channel_size: defaults::default_channel_size(),
             quit_shortcut: defaults::default_quit_shortcut(),
             toggle_mode_shortcut: defaults::default_toggle_mode_shortcut(),
             awake_host_shortcut: defaults::default_awake_host_shortcut(),
+            sequence_timeout: defaults::default_sequence_timeout(),
+            hold_threshold: defaults::default_hold_threshold(),
+            stuck_key_timeout: defaults::default_stuck_key_timeout(),
+            qmk_request_timeout: defaults::default_qmk_request_timeout(),
+            qmk_reconnect_interval: defaults::default_qmk_reconnect_interval(),
+            via_poll_interval: defaults::default_via_poll_interval(),
+            bindings: Vec::new(),
             host_mac_address: None,
             enable_telemetry: false,
+            latency_tracing: false,
+            text_decoding: false,
             keyboards: None,
+            pipeline: defaults::default_pipeline(),
+            scripts_dir: defaults::default_scripts_dir(),
+            script_timeout: defaults::default_script_timeout(),
+            keyboard_settings: HashMap::new(),
             time_to_sleep: defaults::default_time_to_sleep(),
             sleep_script: None,
             awake_script: None,
             stats_file: defaults::default_stats_file(),
+            heatmap_file: defaults::default_heatmap_file(),
+            qmk_keymap_dir: defaults::default_qmk_keymap_dir(),
             stats_save_interval: defaults::default_stats_save_interval(),
             stats_wpm_slot_duration: defaults::default_stats_wpm_slot_duration(),
             stats_wpm_slot_count: defaults::default_stats_wpm_slot_count(),
             keymaps_dir: defaults::default_keymaps_dir(),
             host_keymap: defaults::default_host_keymap(),
+            host_profiles: HashMap::new(),
         }
     }
 }
 
-mod shortcut {
+pub(super) mod shortcut {
     use std::str::FromStr;
 
     use serde::Deserialize;
@@ -183,9 +369,64 @@ mod shortcut {
     }
 }
 
+pub(super) mod key_sequence {
+    use std::str::FromStr;
+
+    use serde::Deserialize;
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+
+    use crate::domain::KeySequence;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<KeySequence, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        KeySequence::from_str(&s).map_err(de::Error::custom)
+    }
+
+    pub fn serialize<S>(value: &KeySequence, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&value.to_string())
+    }
+}
+
+pub(super) mod optional_shortcut {
+    use std::str::FromStr;
+
+    use serde::Deserialize;
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+
+    use crate::domain::KeyShortcut;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<KeyShortcut>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| KeyShortcut::from_str(&s).map_err(de::Error::custom))
+            .transpose()
+    }
+
+    pub fn serialize<S>(value: &Option<KeyShortcut>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(shortcut) => serializer.serialize_str(&shortcut.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::config::keyboard::SerialProtocol;
     use toml;
 
     #[test]
@@ -197,4 +438,117 @@ mod test {
         let s = toml::to_string(&config);
         assert!(s.is_ok());
     }
+
+    #[test]
+    fn deserialize_keyboard_settings() {
+        let config: CharonConfig = toml::from_str(
+            r#"
+            [keyboard_settings.numpad]
+            role = "macro-pad"
+            macros = { a = "hello" }
+
+            [keyboard_settings.main]
+            remap = { capslock = "esc" }
+            quit_shortcut = "ctrl+alt+q"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(KeyboardRole::MacroPad, config.keyboard_role("numpad"));
+        assert_eq!(KeyboardRole::PassThrough, config.keyboard_role("main"));
+        assert_eq!(KeyboardRole::PassThrough, config.keyboard_role("unknown"));
+
+        let main = &config.keyboard_settings["main"];
+        assert!(main.has_custom_shortcuts());
+        assert_eq!(1, main.remap().unwrap().len());
+        assert_eq!(
+            1,
+            config.keyboard_settings["numpad"].macros().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn qmk_keyboards() {
+        let config: CharonConfig = toml::from_str(
+            r#"
+            keyboard = { Use = "first" }
+
+            [keyboards.first]
+            vendor_id = 0x3434
+            product_id = 0x01A1
+            raw_hid_enabled = true
+            devices = []
+
+            [keyboards.second]
+            vendor_id = 0x3434
+            product_id = 0x0333
+            raw_hid_enabled = true
+            layout_matrix = [[0, 1], [0, 0]]
+            devices = []
+
+            [keyboards.third]
+            raw_hid_enabled = true
+            devices = []
+
+            [keyboards.fourth]
+            serial_port = "/dev/ttyACM0"
+            serial_protocol = "zmk"
+            devices = []
+            "#,
+        )
+        .unwrap();
+
+        let mut aliases: Vec<&String> =
+            config.qmk_keyboards().into_iter().map(|(a, _)| a).collect();
+        aliases.sort();
+        assert_eq!(vec!["first", "second"], aliases);
+
+        let serial = config.serial_keyboards();
+        assert_eq!(1, serial.len());
+        assert_eq!("fourth", serial[0].0);
+        assert_eq!(SerialProtocol::Zmk, serial[0].1.serial_protocol);
+        assert_eq!(
+            Some(&[[0, 1], [0, 0]][..]),
+            config
+                .keyboard_group("second")
+                .unwrap()
+                .layout_matrix
+                .as_deref()
+        );
+    }
+
+    #[test]
+    fn deserialize_action_bindings() {
+        let config: CharonConfig = toml::from_str(
+            r#"
+            [[bindings]]
+            shortcut = "ctrl+alt+t"
+            action = "run-command"
+            command = "notify-send"
+            args = ["Hello"]
+
+            [[bindings]]
+            shortcut = "f9"
+            action = "macro-record"
+            "#,
+        )
+        .unwrap();
+
+        let bindings = config.action_bindings();
+        assert_eq!(5, bindings.len());
+        assert_eq!(Action::Quit, bindings[0].action);
+        assert_eq!(
+            Action::RunCommand {
+                command: "notify-send".into(),
+                args: vec!["Hello".into()]
+            },
+            bindings[3].action
+        );
+        assert_eq!(Action::MacroRecord, bindings[4].action);
+
+        // bindings are listed over IPC
+        let json = serde_json::to_string(&bindings).unwrap();
+        let parsed: Vec<ActionBinding> = serde_json::from_str(&json).unwrap();
+        assert_eq!(bindings, parsed);
+    }
 }
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 use std::path::PathBuf;
 
+use super::ProcessorConfig;
 use crate::domain::{HidKeyCode, KeyShortcut, Modifiers};
 
 pub(crate) fn default_hid_keyboard() -> PathBuf {
@@ -19,6 +20,14 @@ pub(crate) fn default_channel_size() -> usize {
     128
 }
 
+pub(crate) fn default_report_buffer_size() -> usize {
+    256
+}
+
+pub(crate) fn default_host_retry_interval() -> u64 {
+    500
+}
+
 pub fn default_quit_shortcut() -> KeyShortcut {
     KeyShortcut::new(HidKeyCode::KEY_Q, Modifiers::LEFT_CTRL)
 }
@@ -31,6 +40,30 @@ pub fn default_awake_host_shortcut() -> KeyShortcut {
     KeyShortcut::new(HidKeyCode::KEY_F8, Modifiers::NONE)
 }
 
+pub(crate) fn default_sequence_timeout() -> u64 {
+    1000
+}
+
+pub(crate) fn default_hold_threshold() -> u64 {
+    200
+}
+
+pub(crate) fn default_stuck_key_timeout() -> u64 {
+    2000
+}
+
+pub(crate) fn default_qmk_request_timeout() -> u64 {
+    1000
+}
+
+pub(crate) fn default_qmk_reconnect_interval() -> u64 {
+    5000
+}
+
+pub(crate) fn default_via_poll_interval() -> u64 {
+    20
+}
+
 pub fn default_time_to_sleep() -> u64 {
     900
 }
@@ -39,6 +72,14 @@ pub fn default_stats_file() -> PathBuf {
     PathBuf::from("/var/lib/charon/stats.json")
 }
 
+pub(crate) fn default_heatmap_file() -> PathBuf {
+    PathBuf::from("/var/lib/charon/heatmap.json")
+}
+
+pub(crate) fn default_qmk_keymap_dir() -> PathBuf {
+    PathBuf::from("/var/lib/charon")
+}
+
 pub fn default_stats_save_interval() -> u64 {
     60
 }
@@ -58,3 +99,21 @@ pub fn default_keymaps_dir() -> String {
 pub fn default_host_keymap() -> String {
     String::from("en_us")
 }
+
+pub(crate) fn default_pipeline() -> Vec<ProcessorConfig> {
+    vec![
+        ProcessorConfig::new("key-event"),
+        ProcessorConfig::new("system-shortcut"),
+    ]
+}
+
+pub(crate) fn default_scripts_dir() -> PathBuf {
+    match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) => PathBuf::from(dir).join("charon/scripts"),
+        Err(_) => PathBuf::from("/etc/charon/scripts"),
+    }
+}
+
+pub(crate) fn default_script_timeout() -> u64 {
+    20
+}
@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+use super::TypingProfile;
+
+/// Settings specific to a host (i.e. a computer with a different OS layout),
+/// that can be switched at runtime with `HostProfile` action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostProfile {
+    /// Keymap used by `Typist` (name of the file in keymaps directory)
+    pub keymap: String,
+
+    /// Timing of text typed by `Typist` (overrides the global `typing_profile`)
+    #[serde(default)]
+    pub typing_profile: Option<TypingProfile>,
+}
@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub enum InputConfig {
+    /// Keyboards discovered at startup, each with its own scanner. Keyboards plugged in
+    /// later are not picked up until restart (unless none was discovered at startup).
     #[default]
     Auto,
     Path(PathBuf),
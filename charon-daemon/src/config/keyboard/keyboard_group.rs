@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use super::DeviceEntry;
+use std::path::PathBuf;
+
+use super::{DeviceEntry, RawHidProtocol, SerialProtocol};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,5 +12,27 @@ pub struct KeyboardGroup {
     #[serde(default)]
     pub raw_hid_enabled: bool,
 
+    #[serde(default)]
+    pub raw_hid_protocol: RawHidProtocol,
+
+    /// Serial (CDC-ACM) port of the keyboard, i.e. `/dev/serial/by-id/usb-ZMK_...-if00`.
+    /// Used as side channel if Raw HID is not enabled.
+    #[serde(default)]
+    pub serial_port: Option<PathBuf>,
+
+    #[serde(default)]
+    pub serial_protocol: SerialProtocol,
+
+    /// Size of the keyboard matrix `[rows, cols]`. Required by VIA protocol,
+    /// which doesn't report it (see `matrix` in keyboard's VIA definition).
+    #[serde(default)]
+    pub matrix_size: Option<[u8; 2]>,
+
+    /// Matrix position `[row, col]` of each key of the physical layout, in the same
+    /// order as in `layouts` section of QMK's `info.json`. Used to order keys
+    /// of the keymap downloaded from QMK keyboard.
+    #[serde(default)]
+    pub layout_matrix: Option<Vec<[u8; 2]>>,
+
     pub devices: Vec<DeviceEntry>,
 }
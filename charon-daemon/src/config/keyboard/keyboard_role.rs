@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Defines how key events of a specific keyboard are handled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyboardRole {
+    /// Keys are sent to the host in pass-through mode and to Charon in in-app mode
+    #[default]
+    PassThrough,
+
+    /// Keys never reach the host (nor the client app). Instead they trigger macros
+    /// or are published as `MacroKey` events
+    MacroPad,
+
+    /// Keys never reach the host, the keyboard is used only by Charon's apps
+    InAppOnly,
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{collections::HashMap, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::KeyboardRole;
+use crate::{
+    config::{ActionBinding, ProcessorConfig, charon_config::optional_shortcut},
+    domain::{Action, HidKeyCode, KeyShortcut},
+    error::CharonError,
+};
+
+/// Settings of a single keyboard (identified by its alias).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct KeyboardSettings {
+    #[serde(default)]
+    pub role: KeyboardRole,
+
+    /// Key remapping (i.e. `capslock = "esc"`), applied before shortcuts
+    #[serde(default)]
+    pub remap: HashMap<String, String>,
+
+    /// Text typed on key press (macro-pad role only)
+    #[serde(default)]
+    pub macros: HashMap<String, String>,
+
+    /// Debounce window of the keyboard's switches in milliseconds, overriding
+    /// `debounce` option of `key-filter` processor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce: Option<u64>,
+
+    /// Key event pipeline of this keyboard, replacing the global `pipeline`
+    /// (or the chain implied by the role)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<Vec<ProcessorConfig>>,
+
+    #[serde(
+        default,
+        with = "optional_shortcut",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub quit_shortcut: Option<KeyShortcut>,
+
+    #[serde(
+        default,
+        with = "optional_shortcut",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub toggle_mode_shortcut: Option<KeyShortcut>,
+
+    #[serde(
+        default,
+        with = "optional_shortcut",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub awake_host_shortcut: Option<KeyShortcut>,
+}
+
+impl KeyboardSettings {
+    pub fn remap(&self) -> Result<HashMap<HidKeyCode, HidKeyCode>, CharonError> {
+        self.remap
+            .iter()
+            .map(|(from, to)| Ok((HidKeyCode::from_str(from)?, HidKeyCode::from_str(to)?)))
+            .collect()
+    }
+
+    pub fn macros(&self) -> Result<HashMap<HidKeyCode, String>, CharonError> {
+        self.macros
+            .iter()
+            .map(|(key, text)| Ok((HidKeyCode::from_str(key)?, text.clone())))
+            .collect()
+    }
+
+    /// Returns system shortcuts overridden for this keyboard
+    pub fn action_bindings(&self) -> Vec<ActionBinding> {
+        [
+            (&self.quit_shortcut, Action::Quit),
+            (&self.toggle_mode_shortcut, Action::ToggleMode),
+            (&self.awake_host_shortcut, Action::AwakeHost),
+        ]
+        .into_iter()
+        .filter_map(|(shortcut, action)| {
+            shortcut
+                .clone()
+                .map(|shortcut| ActionBinding::new(shortcut, action))
+        })
+        .collect()
+    }
+
+    pub fn has_custom_shortcuts(&self) -> bool {
+        self.quit_shortcut.is_some()
+            || self.toggle_mode_shortcut.is_some()
+            || self.awake_host_shortcut.is_some()
+    }
+}
@@ -2,7 +2,15 @@
 mod device_entry;
 mod keyboard_config;
 mod keyboard_group;
+mod keyboard_role;
+mod keyboard_settings;
+mod raw_hid_protocol;
+mod serial_protocol;
 
 pub use device_entry::DeviceEntry;
 pub use keyboard_config::KeyboardConfig;
 pub use keyboard_group::KeyboardGroup;
+pub use keyboard_role::KeyboardRole;
+pub use keyboard_settings::KeyboardSettings;
+pub use raw_hid_protocol::RawHidProtocol;
+pub use serial_protocol::SerialProtocol;
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Protocol used to communicate with the keyboard over Raw HID.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RawHidProtocol {
+    /// QMK firmware with Charon module (see `setup/qmk`)
+    #[default]
+    Charon,
+
+    /// Stock VIA or Vial firmware
+    Via,
+}
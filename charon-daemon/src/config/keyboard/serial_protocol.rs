@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Protocol used to communicate with the keyboard over serial (CDC-ACM) port.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SerialProtocol {
+    /// Charon line protocol (see `docs/qmk.md`), i.e. ZMK with Charon module (see `setup/zmk`)
+    #[default]
+    Charon,
+
+    /// ZMK Studio RPC
+    Zmk,
+}
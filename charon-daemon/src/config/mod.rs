@@ -1,8 +1,20 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+mod action_binding;
 mod charon_config;
 pub(crate) mod defaults;
+mod host_profile;
 mod input_config;
 pub mod keyboard;
+mod processor_config;
+mod repeat_policy;
+mod report_buffer_policy;
+mod typing_profile;
 
+pub use action_binding::ActionBinding;
 pub use charon_config::CharonConfig;
+pub use host_profile::HostProfile;
 pub use input_config::InputConfig;
+pub use processor_config::ProcessorConfig;
+pub use repeat_policy::RepeatPolicy;
+pub use report_buffer_policy::ReportBufferPolicy;
+pub use typing_profile::TypingProfile;
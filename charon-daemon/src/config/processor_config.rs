@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::error::CharonError;
+
+/// Entry of the key event pipeline: processor registered under `processor` name
+/// and its options (all other fields of the entry).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessorConfig {
+    pub processor: String,
+
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+
+    /// Aliases of keyboards the processor is used for. All keyboards, if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyboards: Option<Vec<String>>,
+
+    /// Aliases of keyboards the processor is not used for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_for: Vec<String>,
+
+    #[serde(flatten)]
+    pub options: toml::Table,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl ProcessorConfig {
+    pub fn new(processor: &str) -> Self {
+        Self {
+            processor: processor.into(),
+            enabled: true,
+            keyboards: None,
+            disabled_for: Vec::new(),
+            options: toml::Table::new(),
+        }
+    }
+
+    /// Returns true if the processor should be used for the keyboard with given alias,
+    /// or, if alias is `None`, for keyboards with no dedicated route.
+    pub fn is_enabled_for(&self, alias: Option<&str>) -> bool {
+        match alias {
+            _ if !self.enabled => false,
+            None => self.keyboards.is_none(),
+            Some(alias) => {
+                !self.disabled_for.iter().any(|a| a == alias)
+                    && self
+                        .keyboards
+                        .as_ref()
+                        .is_none_or(|keyboards| keyboards.iter().any(|a| a == alias))
+            }
+        }
+    }
+
+    /// Aliases of keyboards mentioned by this entry
+    pub fn aliases(&self) -> impl Iterator<Item = &String> {
+        self.keyboards.iter().flatten().chain(&self.disabled_for)
+    }
+
+    /// Deserializes the option, or returns `None` if it's not set.
+    pub fn option<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, CharonError> {
+        self.options
+            .get(name)
+            .map(|value| {
+                value.clone().try_into().map_err(|err| {
+                    CharonError::InvalidProcessorConfig(format!("{}.{name}: {err}", self.processor))
+                })
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_enabled_for() {
+        let config: ProcessorConfig = toml::from_str(
+            r#"
+            processor = "key-event"
+            keyboards = ["main", "numpad"]
+            disabled_for = ["numpad"]
+            remap = { capslock = "esc" }
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.is_enabled_for(Some("main")));
+        assert!(!config.is_enabled_for(Some("numpad")));
+        assert!(!config.is_enabled_for(Some("other")));
+        assert!(!config.is_enabled_for(None));
+        assert_eq!(
+            Some(HashMap::from([("capslock".into(), "esc".into())])),
+            config.option::<HashMap<String, String>>("remap").unwrap()
+        );
+        assert!(config.option::<u64>("remap").is_err());
+
+        let mut config = ProcessorConfig::new("discard");
+        assert!(config.is_enabled_for(None));
+        assert!(config.is_enabled_for(Some("main")));
+        config.enabled = false;
+        assert!(!config.is_enabled_for(Some("main")));
+    }
+}
@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Defines what `KeyFilterProcessor` does with autorepeated key presses
+/// (reported by evdev while a key is held).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepeatPolicy {
+    /// Autorepeat events are passed through
+    #[default]
+    Pass,
+
+    /// Autorepeat events are dropped
+    Suppress,
+
+    /// Autorepeat events of the device are dropped and generated by Charon,
+    /// with configured delay and rate
+    Regenerate,
+}
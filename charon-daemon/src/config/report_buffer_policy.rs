@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Defines what `KeyWriter` does with HID reports that couldn't be delivered,
+/// because the host is suspended or the USB gadget is not configured.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportBufferPolicy {
+    /// Reports are lost (no buffering at all)
+    Drop,
+
+    /// All reports are buffered and replayed once the host is back
+    Buffer,
+
+    /// Only reports produced by typing keys (letters, digits, punctuation, etc.)
+    /// are buffered. Shortcuts and other keys are dropped, so no accidental
+    /// commands are replayed on the host.
+    #[default]
+    TypingOnly,
+}
@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+use strum::EnumString;
+
+/// Defines the timing of text typed by `Typist` (see `TypingRhythm`).
+/// Can be set globally, per host profile and per request.
+#[derive(Debug, Default, EnumString, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum TypingProfile {
+    /// Each character is pressed and released, with `typing_interval` after each report
+    #[default]
+    Fixed,
+
+    /// Keys roll over: the next character is pressed without releasing the previous one,
+    /// unless it's the same key or needs different modifiers
+    Fast,
+
+    /// For slow hosts (VMs, remote desktops): modifiers are pressed and released
+    /// in separate reports, the pause adapts to the character: longer around modifiers
+    /// of shifted characters (and shortcuts), longest after a new line
+    Safe,
+
+    /// Jittered timing of a human typist, with longer pauses between words
+    Humanized,
+}
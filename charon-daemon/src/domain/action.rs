@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+use crate::config::TypingProfile;
+
+/// Action triggered by a key shortcut (see `ActionBinding`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum Action {
+    /// Stops the daemon
+    Quit,
+
+    /// Switches between pass-through and in-app mode
+    ToggleMode,
+
+    /// Sends magic packet to the host (Wake-on-LAN)
+    AwakeHost,
+
+    /// Runs a local command (without waiting for it to finish)
+    RunCommand {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+
+    /// Types the text on the host (via `Typist`), optionally with given typing profile
+    SendText {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<TypingProfile>,
+    },
+
+    /// Types text mixed with named keys, i.e. `username{Tab}password{Enter}`
+    /// (see `TypingStep::parse_all`), optionally with given typing profile
+    SendKeys {
+        keys: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<TypingProfile>,
+    },
+
+    /// Activates given app of the client (TUI)
+    SwitchApp { app: String },
+
+    /// Switches host profile (i.e. keymap used for typing)
+    HostProfile { profile: String },
+
+    /// Starts macro recording, or stops it if it's already running
+    MacroRecord,
+
+    /// Plays the last recorded macro on the host (via `Typist`)
+    MacroPlay,
+
+    /// Pauses or resumes sending telemetry
+    ToggleTelemetry,
+}
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
 
-use super::Mode;
+use super::{Mode, stats::LatencyTracer};
 use tokio::sync::RwLock;
 
 use crate::config::CharonConfig;
@@ -10,12 +13,16 @@ use crate::config::CharonConfig;
 pub struct ActorState {
     mode: Arc<RwLock<Mode>>,
     config: Arc<CharonConfig>,
+    telemetry_enabled: Arc<AtomicBool>,
+    tracer: LatencyTracer,
 }
 
 impl ActorState {
     pub fn new(mode: Mode, config: Arc<CharonConfig>) -> Self {
         Self {
             mode: Arc::new(RwLock::new(mode)),
+            telemetry_enabled: Arc::new(AtomicBool::new(config.enable_telemetry)),
+            tracer: LatencyTracer::new(config.latency_tracing),
             config,
         }
     }
@@ -32,6 +39,19 @@ impl ActorState {
         &self.config
     }
 
+    pub fn telemetry_enabled(&self) -> bool {
+        self.telemetry_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_telemetry_enabled(&self, enabled: bool) {
+        self.telemetry_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Latency tracer of key events (see `latency_tracing` config option)
+    pub fn tracer(&self) -> &LatencyTracer {
+        &self.tracer
+    }
+
     pub fn clone_mode(&self) -> Arc<RwLock<Mode>> {
         self.mode.clone()
     }
@@ -1,9 +1,16 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::HashMap;
+
 use evdev::KeyCode;
+use maiko::EventId;
 use serde::{Deserialize, Serialize};
 
-use super::{Mode, Topic};
-use super::{qmk::QMKEvent, stats::CurrentStats};
+use super::{FilterReason, Mode, Topic, TypingError};
+use super::{
+    qmk::{QMKEvent, QMKKeymap, QMKRequest},
+    stats::{CurrentStats, KeyHeatmap, LatencyTrace},
+};
+use crate::config::{ActionBinding, TypingProfile};
 
 #[derive(maiko::Event, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -12,24 +19,65 @@ pub enum CharonEvent {
     KeyPress(KeyCode, String),
     KeyRelease(KeyCode, String),
     HidReport([u8; 8]),
-    SendText(String),
-    SendFile(String, bool),
-    TextSent,
+    /// Text to type, with optional typing profile (see `TypingProfile`)
+    SendText(String, Option<TypingProfile>),
+    /// Text mixed with named keys, chords and pauses (see `TypingStep::parse_all`),
+    /// with optional typing profile
+    SendKeys(String, Option<TypingProfile>),
+    /// Path of the file to type, whether to remove it afterwards and optional typing profile
+    SendFile(String, bool, Option<TypingProfile>),
+    /// Typing job (id of the `Send*` request) completed
+    TextSent(EventId),
+    /// Steps (characters, keys and pauses) of the typing job typed so far and in total
+    TypingProgress(EventId, usize, usize),
+    /// Typing job failed, was cancelled or interrupted
+    TypingFailed(EventId, TypingError),
+    /// Cancels given typing job or all of them (the running and queued ones)
+    CancelTyping(Option<EventId>),
+    /// Text typed on the host, decoded from the HID reports (see `text_decoding`)
+    TextTyped(String),
 
     // Keyboard
     KeyboardAttached(String),
+    KeyboardDetached(String),
+    MacroKey(KeyCode, String),
+    MacroRecording(bool),
+    /// Reports of the macro just recorded, kept by `Typist` for playback
+    MacroRecorded(Vec<[u8; 8]>),
+    /// Plays the last recorded macro on the host
+    PlayMacro,
 
     // Stats and telemetry
     CurrentStats(CurrentStats),
+    GetKeyHeatmaps,
+    /// Key press counts of QMK keyboards by group alias
+    KeyHeatmaps(HashMap<String, KeyHeatmap>),
     ReportSent,
+    /// Key event dropped by the key filter (see `KeyFilterProcessor`)
+    KeyFiltered(KeyCode, String, FilterReason),
+
+    // Debugging
+    /// Timing of a key event, from the input device to the host (see `latency_tracing`)
+    LatencyTrace(LatencyTrace),
 
     // System events
     ModeChange(Mode),
     Sleep,
     WakeUp,
+    SwitchApp(String),
+    HostProfileChange(String),
+    TelemetryEnabled(bool),
+    ListBindings,
+    Bindings(Vec<ActionBinding>),
 
     // QMK
-    QMKEvent(QMKEvent),
+    QMKEvent(QMKEvent, String),
+    QMKRequest(QMKRequest, String),
+    QMKRequestTimeout(QMKRequest, String),
+    /// Requests the keymap of the keyboard group with given alias
+    GetQMKKeymap(String),
+    /// Keymap of the keyboard group with given alias
+    QMKKeymap(QMKKeymap, String),
 }
 
 impl CharonEvent {
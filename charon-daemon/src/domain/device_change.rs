@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Change of input devices present in the system.
+/// The value is device name, as in /dev/input/by-id/, or name of the event node
+/// (i.e. `event3`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceChange {
+    Attached(String),
+    Detached(String),
+}
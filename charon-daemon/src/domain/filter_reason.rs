@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+/// Why a key event was dropped by `KeyFilterProcessor`
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[strum(serialize_all = "kebab-case")]
+pub enum FilterReason {
+    /// Press or release within the debounce window of a key (worn or cheap switch)
+    Chatter,
+
+    /// Autorepeat suppressed (or replaced by the generated one)
+    Repeat,
+}
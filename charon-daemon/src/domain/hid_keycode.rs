@@ -6,7 +6,7 @@ use crate::error::CharonError;
 
 /// Represents a USB HID Usage ID for a key.
 /// See: https://www.usb.org/sites/default/files/hut1_3_0.pdf for details
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 #[allow(non_camel_case_types)]
 #[non_exhaustive]
@@ -144,6 +144,119 @@ impl HidKeyCode {
         }
         0
     }
+
+    /// Returns the name of the key, as accepted by `from_str`
+    pub fn name(&self) -> &'static str {
+        use HidKeyCode::*;
+        match self {
+            KEY_A => "a",
+            KEY_B => "b",
+            KEY_C => "c",
+            KEY_D => "d",
+            KEY_E => "e",
+            KEY_F => "f",
+            KEY_G => "g",
+            KEY_H => "h",
+            KEY_I => "i",
+            KEY_J => "j",
+            KEY_K => "k",
+            KEY_L => "l",
+            KEY_M => "m",
+            KEY_N => "n",
+            KEY_O => "o",
+            KEY_P => "p",
+            KEY_Q => "q",
+            KEY_R => "r",
+            KEY_S => "s",
+            KEY_T => "t",
+            KEY_U => "u",
+            KEY_V => "v",
+            KEY_W => "w",
+            KEY_X => "x",
+            KEY_Y => "y",
+            KEY_Z => "z",
+            KEY_1 => "1",
+            KEY_2 => "2",
+            KEY_3 => "3",
+            KEY_4 => "4",
+            KEY_5 => "5",
+            KEY_6 => "6",
+            KEY_7 => "7",
+            KEY_8 => "8",
+            KEY_9 => "9",
+            KEY_0 => "0",
+            KEY_ENTER => "enter",
+            KEY_ESC => "esc",
+            KEY_BACKSPACE => "backspace",
+            KEY_TAB => "tab",
+            KEY_SPACE => "space",
+            KEY_MINUS => "-",
+            KEY_EQUAL => "=",
+            KEY_LEFTBRACE => "[",
+            KEY_RIGHTBRACE => "]",
+            KEY_BACKSLASH => "backslash",
+            KEY_NON_US_BACKSLASH => "nonusbackslash",
+            KEY_SEMICOLON => ";",
+            KEY_APOSTROPHE => "apostrophe",
+            KEY_GRAVE => "grave",
+            KEY_COMMA => ",",
+            KEY_DOT => ".",
+            KEY_SLASH => "/",
+            KEY_CAPSLOCK => "capslock",
+            KEY_F1 => "f1",
+            KEY_F2 => "f2",
+            KEY_F3 => "f3",
+            KEY_F4 => "f4",
+            KEY_F5 => "f5",
+            KEY_F6 => "f6",
+            KEY_F7 => "f7",
+            KEY_F8 => "f8",
+            KEY_F9 => "f9",
+            KEY_F10 => "f10",
+            KEY_F11 => "f11",
+            KEY_F12 => "f12",
+            KEY_INSERT => "insert",
+            KEY_DELETE => "delete",
+            KEY_HOME => "home",
+            KEY_END => "end",
+            KEY_PAGEUP => "pageup",
+            KEY_PAGEDOWN => "pagedown",
+            KEY_UP => "up",
+            KEY_DOWN => "down",
+            KEY_LEFT => "left",
+            KEY_RIGHT => "right",
+            KEY_NUMLOCK => "numlock",
+            KEY_SCROLLLOCK => "scrolllock",
+            KEY_KP0 => "kp0",
+            KEY_KP1 => "kp1",
+            KEY_KP2 => "kp2",
+            KEY_KP3 => "kp3",
+            KEY_KP4 => "kp4",
+            KEY_KP5 => "kp5",
+            KEY_KP6 => "kp6",
+            KEY_KP7 => "kp7",
+            KEY_KP8 => "kp8",
+            KEY_KP9 => "kp9",
+            KEY_KPDOT => "kpdot",
+            KEY_KPENTER => "kpenter",
+            KEY_KPSLASH => "kpslash",
+            KEY_KPASTERISK => "kpasterisk",
+            KEY_KPMINUS => "kpminus",
+            KEY_KPPLUS => "kpplus",
+            KEY_KPEQUAL => "kpequal",
+            KEY_MUTE => "mute",
+            KEY_VOLUMEUP => "volumeup",
+            KEY_VOLUMEDOWN => "volumedown",
+            KEY_LEFTCTRL => "leftctrl",
+            KEY_LEFTSHIFT => "leftshift",
+            KEY_LEFTALT => "leftalt",
+            KEY_LEFTMETA => "leftmeta",
+            KEY_RIGHTCTRL => "rightctrl",
+            KEY_RIGHTSHIFT => "rightshift",
+            KEY_RIGHTALT => "rightalt",
+            KEY_RIGHTMETA => "rightmeta",
+        }
+    }
 }
 
 impl From<HidKeyCode> for u8 {
@@ -608,6 +721,34 @@ impl FromStr for HidKeyCode {
             "RIGHT" => KEY_RIGHT,
             "NUMLOCK" => KEY_NUMLOCK,
             "SCROLLLOCK" => KEY_SCROLLLOCK,
+            "KP0" => KEY_KP0,
+            "KP1" => KEY_KP1,
+            "KP2" => KEY_KP2,
+            "KP3" => KEY_KP3,
+            "KP4" => KEY_KP4,
+            "KP5" => KEY_KP5,
+            "KP6" => KEY_KP6,
+            "KP7" => KEY_KP7,
+            "KP8" => KEY_KP8,
+            "KP9" => KEY_KP9,
+            "KPDOT" => KEY_KPDOT,
+            "KPENTER" => KEY_KPENTER,
+            "KPSLASH" => KEY_KPSLASH,
+            "KPASTERISK" => KEY_KPASTERISK,
+            "KPMINUS" => KEY_KPMINUS,
+            "KPPLUS" => KEY_KPPLUS,
+            "KPEQUAL" => KEY_KPEQUAL,
+            "MUTE" => KEY_MUTE,
+            "VOLUMEUP" => KEY_VOLUMEUP,
+            "VOLUMEDOWN" => KEY_VOLUMEDOWN,
+            "LEFTCTRL" => KEY_LEFTCTRL,
+            "LEFTSHIFT" => KEY_LEFTSHIFT,
+            "LEFTALT" => KEY_LEFTALT,
+            "LEFTMETA" => KEY_LEFTMETA,
+            "RIGHTCTRL" => KEY_RIGHTCTRL,
+            "RIGHTSHIFT" => KEY_RIGHTSHIFT,
+            "RIGHTALT" => KEY_RIGHTALT,
+            "RIGHTMETA" => KEY_RIGHTMETA,
             other => return Err(CharonError::UnsupportedKeyName(other.into())),
         };
         Ok(key)
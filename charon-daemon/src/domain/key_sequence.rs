@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{fmt, str::FromStr};
+
+use crate::error::CharonError;
+
+use super::KeyShortcut;
+
+/// One or more key shortcuts that have to be pressed one after another,
+/// i.e. `ctrl+x ctrl+s` or `f7 e` (leader key followed by a letter).
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySequence(Vec<KeyShortcut>);
+
+impl KeySequence {
+    pub fn steps(&self) -> &[KeyShortcut] {
+        &self.0
+    }
+}
+
+impl From<KeyShortcut> for KeySequence {
+    fn from(shortcut: KeyShortcut) -> Self {
+        Self(vec![shortcut])
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = CharonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split_whitespace()
+            .map(KeyShortcut::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.is_empty() {
+            return Err(CharonError::InvalidKeyShortcut(s.into()));
+        }
+        Ok(Self(steps))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: Vec<String> = self.0.iter().map(|step| step.to_string()).collect();
+        write!(f, "{}", steps.join(" "))
+    }
+}
@@ -3,17 +3,55 @@ use std::{fmt, str::FromStr};
 
 use crate::{domain::HidReport, error::CharonError};
 
-use super::{HidKeyCode, Modifiers};
+use super::{HidKeyCode, KeyTrigger, Modifiers};
 
+/// Key combination used to trigger an action. Grammar (case insensitive):
+/// `[tap:|hold:]modifier+...+key`, where the key can be omitted for modifier-only
+/// shortcuts, i.e. `ctrl+q`, `rctrl+rshift+f1`, `ralt`, `tap:lshift`.
+/// Modifiers without side (`ctrl`, `shift`, `alt`, `meta`) mean the left ones.
 #[derive(Debug, Clone, PartialEq)]
 pub struct KeyShortcut {
     modifiers: Modifiers,
-    key: HidKeyCode,
+    key: Option<HidKeyCode>,
+    trigger: KeyTrigger,
 }
 
 impl KeyShortcut {
-    pub fn new(key: HidKeyCode, modifiers: Modifiers) -> Self {
-        Self { key, modifiers }
+    pub fn new(key: HidKeyCode, mut modifiers: Modifiers) -> Self {
+        if key.is_modifier() {
+            modifiers.add(key.into());
+            return Self::modifiers_only(modifiers);
+        }
+        Self {
+            key: Some(key),
+            modifiers,
+            trigger: KeyTrigger::Press,
+        }
+    }
+
+    pub fn modifiers_only(modifiers: Modifiers) -> Self {
+        Self {
+            key: None,
+            modifiers,
+            trigger: KeyTrigger::Press,
+        }
+    }
+
+    pub fn with_trigger(mut self, trigger: KeyTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    pub fn key(&self) -> Option<HidKeyCode> {
+        self.key
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub fn trigger(&self) -> KeyTrigger {
+        self.trigger
     }
 }
 
@@ -21,35 +59,49 @@ impl FromStr for KeyShortcut {
     type Err = CharonError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split('+').collect();
-        if parts.is_empty() {
-            return Err(CharonError::InvalidKeyShortcut(s.into()));
-        }
+        let invalid = || CharonError::InvalidKeyShortcut(s.into());
+        let (trigger, keys) = match s.split_once(':') {
+            Some((trigger, keys)) if !trigger.is_empty() => {
+                (KeyTrigger::from_str(trigger).map_err(|_| invalid())?, keys)
+            }
+            _ => (KeyTrigger::Press, s),
+        };
 
+        let parts: Vec<&str> = keys.split('+').collect();
         let mut modifiers = Modifiers::default();
-        let key_part = parts.last().unwrap();
-
-        if parts.len() > 1 {
-            for part in &parts[0..parts.len() - 1] {
-                match part.to_lowercase().as_str() {
-                    "ctrl" => modifiers.add(Modifiers::LEFT_CTRL),
-                    "shift" => modifiers.add(Modifiers::LEFT_SHIFT),
-                    "alt" => modifiers.add(Modifiers::LEFT_ALT),
-                    "meta" | "cmd" | "super" => modifiers.add(Modifiers::LEFT_META),
-                    _ => return Err(CharonError::InvalidKeyShortcut(s.into())),
-                }
+        let mut key = None;
+
+        for (idx, part) in parts.iter().enumerate() {
+            if let Some(modifier) = Modifiers::from_name(part) {
+                modifiers.add(modifier);
+            } else if idx == parts.len() - 1 && !part.is_empty() {
+                key = Some(HidKeyCode::from_str(part)?);
+            } else {
+                return Err(invalid());
             }
         }
 
-        let key = HidKeyCode::from_str(key_part)?;
-
-        Ok(KeyShortcut::new(key, modifiers))
+        let shortcut = match key {
+            Some(key) => KeyShortcut::new(key, modifiers),
+            None => KeyShortcut::modifiers_only(modifiers),
+        };
+        Ok(shortcut.with_trigger(trigger))
     }
 }
 
 impl fmt::Display for KeyShortcut {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}+{}", self.modifiers, self.key)
+        if self.trigger != KeyTrigger::Press {
+            write!(f, "{}:", self.trigger)?;
+        }
+        let mut parts = Vec::new();
+        if !self.modifiers.is_empty() {
+            parts.push(self.modifiers.to_string());
+        }
+        if let Some(key) = self.key {
+            parts.push(key.name().to_string());
+        }
+        write!(f, "{}", parts.join("+"))
     }
 }
 
@@ -57,7 +109,7 @@ impl From<&KeyShortcut> for u64 {
     fn from(key: &KeyShortcut) -> Self {
         let mut bytes = [0u8; 8];
         bytes[0] = key.modifiers.into();
-        bytes[2] = key.key.into();
+        bytes[2] = key.key.map_or(0, u8::from);
         u64::from_ne_bytes(bytes)
     }
 }
@@ -72,7 +124,82 @@ impl From<&KeyShortcut> for HidReport {
     fn from(key: &KeyShortcut) -> Self {
         let mut bytes = [0u8; 8];
         bytes[0] = key.modifiers.into();
-        bytes[2] = key.key.into();
+        bytes[2] = key.key.map_or(0, u8::from);
         HidReport::new(bytes)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn all_keys() -> Vec<HidKeyCode> {
+        (0..=u8::MAX)
+            .filter_map(|code| HidKeyCode::try_from(code).ok())
+            .filter(|key| !key.is_modifier())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let shortcut = KeyShortcut::from_str("ctrl+q").unwrap();
+        assert_eq!(Some(HidKeyCode::KEY_Q), shortcut.key());
+        assert_eq!(Modifiers::LEFT_CTRL, shortcut.modifiers());
+
+        let shortcut = KeyShortcut::from_str("RCtrl+RShift+F1").unwrap();
+        assert_eq!(Modifiers::new(16 | 32), shortcut.modifiers());
+
+        let shortcut = KeyShortcut::from_str("tap:lshift").unwrap();
+        assert_eq!(None, shortcut.key());
+        assert_eq!(Modifiers::LEFT_SHIFT, shortcut.modifiers());
+        assert_eq!(KeyTrigger::Tap, shortcut.trigger());
+
+        let shortcut = KeyShortcut::from_str("hold:rightalt").unwrap();
+        assert_eq!(Modifiers::RIGHT_ALT, shortcut.modifiers());
+        assert_eq!(KeyTrigger::Hold, shortcut.trigger());
+
+        assert_eq!(
+            KeyShortcut::from_str("shift+;").unwrap(),
+            KeyShortcut::new(HidKeyCode::KEY_SEMICOLON, Modifiers::LEFT_SHIFT)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for s in ["", "ctrl+", "q+ctrl", "ctrl++q", "press2:q", "foo"] {
+            assert!(KeyShortcut::from_str(s).is_err(), "{s} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_modifier_key_becomes_modifier() {
+        let shortcut = KeyShortcut::new(HidKeyCode::KEY_RIGHTALT, Modifiers::NONE);
+        assert_eq!(KeyShortcut::from_str("ralt").unwrap(), shortcut);
+        assert_eq!(
+            [0x40, 0, 0, 0, 0, 0, 0, 0],
+            HidReport::from(&shortcut).to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let triggers = [KeyTrigger::Press, KeyTrigger::Tap, KeyTrigger::Hold];
+        for modifiers in (0..=u8::MAX).map(Modifiers::new) {
+            for trigger in triggers {
+                let mut shortcuts: Vec<KeyShortcut> = all_keys()
+                    .into_iter()
+                    .map(|key| KeyShortcut::new(key, modifiers).with_trigger(trigger))
+                    .collect();
+                if !modifiers.is_empty() {
+                    shortcuts.push(KeyShortcut::modifiers_only(modifiers).with_trigger(trigger));
+                }
+                for shortcut in shortcuts {
+                    let s = shortcut.to_string();
+                    assert_eq!(shortcut, KeyShortcut::from_str(&s).unwrap(), "{s}");
+                    let s = s.to_uppercase();
+                    assert_eq!(shortcut, KeyShortcut::from_str(&s).unwrap(), "{s}");
+                }
+            }
+        }
+    }
+}
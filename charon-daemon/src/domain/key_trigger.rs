@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use strum::{Display, EnumString};
+
+/// Defines how the keys of a shortcut have to be pressed to trigger it.
+#[derive(Debug, Default, Display, EnumString, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum KeyTrigger {
+    /// Triggered as soon as the keys are pressed
+    #[default]
+    Press,
+
+    /// Triggered when the keys are pressed and released shortly after,
+    /// with no other key pressed in between
+    Tap,
+
+    /// Triggered when the keys are held for a while
+    Hold,
+}
@@ -45,6 +45,10 @@ impl KeyboardState {
         self.keys.clear();
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.modifiers == Modifiers::NONE && self.keys.is_empty()
+    }
+
     pub fn is(&self, key: HidKeyCode, modifiers: Modifiers) -> bool {
         self.modifiers == modifiers && self.keys.len() == 1 && self.keys[0] == key
     }
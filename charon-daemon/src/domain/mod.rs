@@ -1,26 +1,46 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+mod action;
 mod actor_state;
 mod charon_event;
+mod device_change;
+mod filter_reason;
 mod hid_keycode;
 mod hid_report;
+mod key_sequence;
 mod key_shortcut;
+mod key_trigger;
 mod keyboard_state;
 mod keymap;
 mod mode;
 mod modifiers;
+mod reverse_keymap;
+mod text_decoder;
 mod topic;
+mod typing_error;
+mod typing_rhythm;
+mod typing_step;
 
 pub mod qmk;
 pub mod stats;
 pub mod traits;
 
+pub use action::Action;
 pub use actor_state::ActorState;
 pub use charon_event::CharonEvent;
+pub use device_change::DeviceChange;
+pub use filter_reason::FilterReason;
 pub use hid_keycode::HidKeyCode;
 pub use hid_report::HidReport;
+pub use key_sequence::KeySequence;
 pub use key_shortcut::KeyShortcut;
+pub use key_trigger::KeyTrigger;
 pub use keyboard_state::KeyboardState;
 pub use keymap::Keymap;
 pub use mode::Mode;
 pub use modifiers::Modifiers;
+pub use reverse_keymap::ReverseKeymap;
+pub use text_decoder::TextDecoder;
 pub use topic::Topic;
+pub use typing_error::TypingError;
+pub use typing_rhythm::{Keystroke, TypingRhythm};
+pub use typing_step::TypingStep;
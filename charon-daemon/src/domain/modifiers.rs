@@ -17,6 +17,19 @@ impl Modifiers {
     pub const RIGHT_ALT: Self = Self(64);
     pub const RIGHT_META: Self = Self(128);
 
+    /// Display names, in the order of bits. Left-side modifiers use the plain names
+    /// (as "ctrl" in a shortcut means left ctrl).
+    const NAMES: [(Self, &'static str); 8] = [
+        (Self::LEFT_CTRL, "Ctrl"),
+        (Self::LEFT_SHIFT, "Shift"),
+        (Self::LEFT_ALT, "Alt"),
+        (Self::LEFT_META, "Meta"),
+        (Self::RIGHT_CTRL, "RCtrl"),
+        (Self::RIGHT_SHIFT, "RShift"),
+        (Self::RIGHT_ALT, "RAlt"),
+        (Self::RIGHT_META, "RMeta"),
+    ];
+
     pub fn new(val: u8) -> Self {
         Self(val)
     }
@@ -36,6 +49,31 @@ impl Modifiers {
     pub fn reset(&mut self) {
         self.0 = 0;
     }
+
+    pub fn contains(&self, modifiers: Modifiers) -> bool {
+        self.0 & modifiers.value() == modifiers.value()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Parses single modifier name (case insensitive), i.e. "ctrl", "lctrl", "rightctrl".
+    /// Names without side mean the left modifier.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let modifier = match name.to_lowercase().as_str() {
+            "ctrl" | "control" | "lctrl" | "leftctrl" => Self::LEFT_CTRL,
+            "shift" | "lshift" | "leftshift" => Self::LEFT_SHIFT,
+            "alt" | "lalt" | "leftalt" => Self::LEFT_ALT,
+            "meta" | "cmd" | "super" | "lmeta" | "leftmeta" => Self::LEFT_META,
+            "rctrl" | "rightctrl" => Self::RIGHT_CTRL,
+            "rshift" | "rightshift" => Self::RIGHT_SHIFT,
+            "ralt" | "rightalt" | "altgr" => Self::RIGHT_ALT,
+            "rmeta" | "rightmeta" => Self::RIGHT_META,
+            _ => return None,
+        };
+        Some(modifier)
+    }
 }
 
 impl From<Modifiers> for u8 {
@@ -58,21 +96,36 @@ impl From<HidKeyCode> for Modifiers {
 
 impl fmt::Display for Modifiers {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut mods: Vec<String> = Vec::new();
-        for i in 0..4 {
-            if self.0 & !((1 + 16) << i) != 0 {
-                mods.push(
-                    (match i {
-                        0 => "Ctrl",
-                        1 => "Shift",
-                        2 => "Alt",
-                        3 => "Meta",
-                        _ => unreachable!("There are only 4 types of modifiers"),
-                    })
-                    .to_string(),
-                );
+        let mods: Vec<&str> = Self::NAMES
+            .iter()
+            .filter(|(modifier, _)| self.contains(*modifier))
+            .map(|(_, name)| *name)
+            .collect();
+        write!(f, "{}", mods.join("+"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!("", Modifiers::NONE.to_string());
+        assert_eq!("Ctrl", Modifiers::LEFT_CTRL.to_string());
+        assert_eq!("RAlt", Modifiers::RIGHT_ALT.to_string());
+        assert_eq!("Ctrl+Shift+RMeta", Modifiers::new(1 | 2 | 128).to_string());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for value in 1..=u8::MAX {
+            let modifiers = Modifiers::new(value);
+            let mut parsed = Modifiers::NONE;
+            for name in modifiers.to_string().split('+') {
+                parsed.add(Modifiers::from_name(name).unwrap());
             }
+            assert_eq!(modifiers, parsed);
         }
-        write!(f, "{}", mods.join("+"))
     }
 }
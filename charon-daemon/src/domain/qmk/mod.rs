@@ -1,8 +1,14 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 mod qmk_event;
+mod qmk_keycode;
+mod qmk_keymap;
+mod qmk_keymap_builder;
 mod qmk_record;
 mod qmk_request;
 
-pub use qmk_event::QMKEvent;
+pub use qmk_event::{LAYER_CHUNK_SIZE, QMKEvent};
+pub use qmk_keycode::keycode_name;
+pub use qmk_keymap::QMKKeymap;
+pub use qmk_keymap_builder::QMKKeymapBuilder;
 pub use qmk_record::QMKRecord;
 pub use qmk_request::QMKRequest;
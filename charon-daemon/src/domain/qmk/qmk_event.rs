@@ -12,8 +12,25 @@ pub enum QMKEvent {
     KeyEvent(QMKRecord),
     ModeChange(Mode),
     ToggleMode,
+    /// Keycodes of a layer, starting at key position `offset` (row * cols + col)
+    LayerChunk {
+        layer: u8,
+        offset: u16,
+        keycodes: Vec<u16>,
+    },
+    KeyboardInfo {
+        rows: u8,
+        cols: u8,
+        layers: u8,
+    },
+    /// Acknowledges RGB settings change. (is_rgb_supported)
+    RgbSet(bool),
+    DefaultLayerSet(u8),
 }
 
+/// Number of keycodes sent in a single layer chunk
+pub const LAYER_CHUNK_SIZE: usize = 12;
+
 impl QMKEvent {
     pub fn to_bytes(self) -> [u8; 32] {
         use QMKEvent::*;
@@ -40,6 +57,36 @@ impl QMKEvent {
             ToggleMode => {
                 bytes[0] = 0x05;
             }
+            LayerChunk {
+                layer,
+                offset,
+                keycodes,
+            } => {
+                bytes[0] = 0x10;
+                bytes[1] = layer;
+                bytes[2..4].copy_from_slice(&offset.to_be_bytes());
+                keycodes
+                    .into_iter()
+                    .take(LAYER_CHUNK_SIZE)
+                    .enumerate()
+                    .for_each(|(i, kc)| {
+                        bytes[4 + i * 2..6 + i * 2].copy_from_slice(&kc.to_le_bytes())
+                    });
+            }
+            KeyboardInfo { rows, cols, layers } => {
+                bytes[0] = 0x11;
+                bytes[1] = rows;
+                bytes[2] = cols;
+                bytes[3] = layers;
+            }
+            RgbSet(supported) => {
+                bytes[0] = 0x12;
+                bytes[1] = supported as u8;
+            }
+            DefaultLayerSet(layer) => {
+                bytes[0] = 0x13;
+                bytes[1] = layer;
+            }
         }
         bytes
     }
@@ -61,8 +108,49 @@ impl TryFrom<[u8; 32]> for QMKEvent {
                     .ok_or_eyre(eyre!("Unrecognized Mode value: {}", bytes[1]))?,
             ),
             0x05 => QMKEvent::ToggleMode,
+            0x10 => QMKEvent::LayerChunk {
+                layer: bytes[1],
+                offset: u16::from_be_bytes([bytes[2], bytes[3]]),
+                keycodes: (0..LAYER_CHUNK_SIZE).map(|i| to_u16(4 + i * 2)).collect(),
+            },
+            0x11 => QMKEvent::KeyboardInfo {
+                rows: bytes[1],
+                cols: bytes[2],
+                layers: bytes[3],
+            },
+            0x12 => QMKEvent::RgbSet(bytes[1] != 0),
+            0x13 => QMKEvent::DefaultLayerSet(bytes[1]),
             n => return Err(eyre!("Unrecognized message id: {n}")),
         };
         Ok(event)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let events = [
+            QMKEvent::LayerChange(2, true),
+            QMKEvent::ToggleMode,
+            QMKEvent::LayerChunk {
+                layer: 3,
+                offset: 0x0102,
+                keycodes: (0..LAYER_CHUNK_SIZE as u16).map(|i| 0x7000 + i).collect(),
+            },
+            QMKEvent::KeyboardInfo {
+                rows: 6,
+                cols: 16,
+                layers: 4,
+            },
+            QMKEvent::RgbSet(true),
+            QMKEvent::DefaultLayerSet(1),
+        ];
+        for event in events {
+            let bytes = event.clone().to_bytes();
+            assert_eq!(event, QMKEvent::try_from(bytes).unwrap());
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Converts QMK 16-bit keycode into its name as used in QMK's `keymap.json`,
+/// i.e. `KC_ESC`, `MO(2)` or `S(KC_1)`. Keycodes that can't be named are
+/// returned as `ANY(0x....)`.
+pub fn keycode_name(code: u16) -> String {
+    let kc = (code & 0xFF) as u8;
+    let layer = code & 0x1F;
+    match code {
+        0x0000..=0x00FF => match basic_keycode_name(kc) {
+            Some(name) => name.to_string(),
+            None => any(code),
+        },
+        0x0100..=0x1FFF => match (mod_fn_name((code >> 8) as u8), basic_keycode_name(kc)) {
+            (Some(mods), Some(name)) => format!("{mods}({name})"),
+            _ => any(code),
+        },
+        0x4000..=0x4FFF => match basic_keycode_name(kc) {
+            Some(name) => format!("LT({},{name})", (code >> 8) & 0x0F),
+            None => any(code),
+        },
+        0x5200..=0x521F => format!("TO({layer})"),
+        0x5220..=0x523F => format!("MO({layer})"),
+        0x5240..=0x525F => format!("DF({layer})"),
+        0x5260..=0x527F => format!("TG({layer})"),
+        0x5280..=0x529F => format!("OSL({layer})"),
+        0x52A0..=0x52BF => match osm_name(layer as u8) {
+            Some(mods) => format!("OSM({mods})"),
+            None => any(code),
+        },
+        0x52C0..=0x52DF => format!("TT({layer})"),
+        0x7C00 => "QK_BOOT".into(),
+        _ => any(code),
+    }
+}
+
+fn any(code: u16) -> String {
+    format!("ANY(0x{code:04X})")
+}
+
+fn basic_keycode_name(kc: u8) -> Option<&'static str> {
+    const LETTERS: [&str; 26] = [
+        "KC_A", "KC_B", "KC_C", "KC_D", "KC_E", "KC_F", "KC_G", "KC_H", "KC_I", "KC_J", "KC_K",
+        "KC_L", "KC_M", "KC_N", "KC_O", "KC_P", "KC_Q", "KC_R", "KC_S", "KC_T", "KC_U", "KC_V",
+        "KC_W", "KC_X", "KC_Y", "KC_Z",
+    ];
+    const DIGITS: [&str; 10] = [
+        "KC_1", "KC_2", "KC_3", "KC_4", "KC_5", "KC_6", "KC_7", "KC_8", "KC_9", "KC_0",
+    ];
+    const F_KEYS: [&str; 12] = [
+        "KC_F1", "KC_F2", "KC_F3", "KC_F4", "KC_F5", "KC_F6", "KC_F7", "KC_F8", "KC_F9", "KC_F10",
+        "KC_F11", "KC_F12",
+    ];
+    const F_KEYS_EXT: [&str; 12] = [
+        "KC_F13", "KC_F14", "KC_F15", "KC_F16", "KC_F17", "KC_F18", "KC_F19", "KC_F20", "KC_F21",
+        "KC_F22", "KC_F23", "KC_F24",
+    ];
+    const KEYPAD: [&str; 10] = [
+        "KC_P1", "KC_P2", "KC_P3", "KC_P4", "KC_P5", "KC_P6", "KC_P7", "KC_P8", "KC_P9", "KC_P0",
+    ];
+    const MODIFIERS: [&str; 8] = [
+        "KC_LCTL", "KC_LSFT", "KC_LALT", "KC_LGUI", "KC_RCTL", "KC_RSFT", "KC_RALT", "KC_RGUI",
+    ];
+
+    let name = match kc {
+        0x00 => "KC_NO",
+        0x01 => "KC_TRNS",
+        0x04..=0x1D => LETTERS[(kc - 0x04) as usize],
+        0x1E..=0x27 => DIGITS[(kc - 0x1E) as usize],
+        0x28 => "KC_ENT",
+        0x29 => "KC_ESC",
+        0x2A => "KC_BSPC",
+        0x2B => "KC_TAB",
+        0x2C => "KC_SPC",
+        0x2D => "KC_MINS",
+        0x2E => "KC_EQL",
+        0x2F => "KC_LBRC",
+        0x30 => "KC_RBRC",
+        0x31 => "KC_BSLS",
+        0x32 => "KC_NUHS",
+        0x33 => "KC_SCLN",
+        0x34 => "KC_QUOT",
+        0x35 => "KC_GRV",
+        0x36 => "KC_COMM",
+        0x37 => "KC_DOT",
+        0x38 => "KC_SLSH",
+        0x39 => "KC_CAPS",
+        0x3A..=0x45 => F_KEYS[(kc - 0x3A) as usize],
+        0x46 => "KC_PSCR",
+        0x47 => "KC_SCRL",
+        0x48 => "KC_PAUS",
+        0x49 => "KC_INS",
+        0x4A => "KC_HOME",
+        0x4B => "KC_PGUP",
+        0x4C => "KC_DEL",
+        0x4D => "KC_END",
+        0x4E => "KC_PGDN",
+        0x4F => "KC_RGHT",
+        0x50 => "KC_LEFT",
+        0x51 => "KC_DOWN",
+        0x52 => "KC_UP",
+        0x53 => "KC_NUM",
+        0x54 => "KC_PSLS",
+        0x55 => "KC_PAST",
+        0x56 => "KC_PMNS",
+        0x57 => "KC_PPLS",
+        0x58 => "KC_PENT",
+        0x59..=0x62 => KEYPAD[(kc - 0x59) as usize],
+        0x63 => "KC_PDOT",
+        0x64 => "KC_NUBS",
+        0x65 => "KC_APP",
+        0x67 => "KC_PEQL",
+        0x68..=0x73 => F_KEYS_EXT[(kc - 0x68) as usize],
+        0xA8 => "KC_MUTE",
+        0xA9 => "KC_VOLU",
+        0xAA => "KC_VOLD",
+        0xAB => "KC_MNXT",
+        0xAC => "KC_MPRV",
+        0xAD => "KC_MSTP",
+        0xAE => "KC_MPLY",
+        0xE0..=0xE7 => MODIFIERS[(kc - 0xE0) as usize],
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Name of the modifier function (like `S` in `S(KC_1)`). Only single modifiers are supported.
+fn mod_fn_name(mods: u8) -> Option<&'static str> {
+    let name = match mods {
+        0x01 => "C",
+        0x02 => "S",
+        0x04 => "A",
+        0x08 => "G",
+        0x11 => "RCTL",
+        0x12 => "RSFT",
+        0x14 => "RALT",
+        0x18 => "RGUI",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn osm_name(mods: u8) -> Option<&'static str> {
+    let name = match mods {
+        0x01 => "MOD_LCTL",
+        0x02 => "MOD_LSFT",
+        0x04 => "MOD_LALT",
+        0x08 => "MOD_LGUI",
+        0x11 => "MOD_RCTL",
+        0x12 => "MOD_RSFT",
+        0x14 => "MOD_RALT",
+        0x18 => "MOD_RGUI",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keycode_names() {
+        assert_eq!("KC_NO", keycode_name(0x0000));
+        assert_eq!("KC_TRNS", keycode_name(0x0001));
+        assert_eq!("KC_A", keycode_name(0x0004));
+        assert_eq!("KC_0", keycode_name(0x0027));
+        assert_eq!("KC_F12", keycode_name(0x0045));
+        assert_eq!("KC_P0", keycode_name(0x0062));
+        assert_eq!("KC_RGUI", keycode_name(0x00E7));
+        assert_eq!("S(KC_1)", keycode_name(0x021E));
+        assert_eq!("LT(2,KC_SPC)", keycode_name(0x422C));
+        assert_eq!("MO(3)", keycode_name(0x5223));
+        assert_eq!("DF(1)", keycode_name(0x5241));
+        assert_eq!("OSM(MOD_LSFT)", keycode_name(0x52A2));
+        assert_eq!("QK_BOOT", keycode_name(0x7C00));
+        assert_eq!("ANY(0x7E40)", keycode_name(0x7E40));
+    }
+}
@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Layout name of keymaps with keys in matrix order
+pub(super) const MATRIX_ORDER: &str = "matrix";
+
+/// Keymap of QMK keyboard, compatible with QMK's `keymap.json` format
+/// (other fields of the format are ignored).
+/// Layers contain keycode names (i.e. `KC_ESC`, `MO(1)`) in order of
+/// the keyboard's physical layout.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QMKKeymap {
+    pub keyboard: String,
+    pub keymap: String,
+    pub layout: String,
+    pub layers: Vec<Vec<String>>,
+}
+
+impl QMKKeymap {
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn layer(&self, n: usize) -> Option<&[String]> {
+        self.layers.get(n).map(|v| v.as_slice())
+    }
+
+    /// Returns true if the layers are in order of the keyboard matrix rather than
+    /// the physical layout (live keymap read without `layout_matrix` configured).
+    pub fn is_matrix_order(&self) -> bool {
+        self.layout == MATRIX_ORDER
+    }
+}
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use super::{LAYER_CHUNK_SIZE, QMKKeymap, QMKRequest, keycode_name, qmk_keymap::MATRIX_ORDER};
+
+/// Assembles keymap from layer chunks downloaded from QMK keyboard.
+/// Chunks are requested one by one, layer by layer, in matrix order
+/// (`row * cols + col`).
+#[derive(Debug)]
+pub struct QMKKeymapBuilder {
+    cols: u8,
+    layers: Vec<Vec<u16>>,
+    next: Option<(u8, u16)>,
+}
+
+impl QMKKeymapBuilder {
+    pub fn new(rows: u8, cols: u8, layer_count: u8) -> Self {
+        let key_count = rows as usize * cols as usize;
+        let next = (key_count > 0 && layer_count > 0).then_some((0, 0));
+        Self {
+            cols,
+            layers: vec![vec![0; key_count]; layer_count as usize],
+            next,
+        }
+    }
+
+    /// Request for the next missing chunk, or `None` if the keymap is complete.
+    pub fn next_request(&self) -> Option<QMKRequest> {
+        self.next
+            .map(|(layer, offset)| QMKRequest::LayerChunk { layer, offset })
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next.is_none()
+    }
+
+    /// Stores keycodes of the chunk. Returns false if it wasn't the expected chunk.
+    pub fn add_chunk(&mut self, layer: u8, offset: u16, keycodes: &[u16]) -> bool {
+        if self.next != Some((layer, offset)) {
+            return false;
+        }
+        let keys = &mut self.layers[layer as usize];
+        let start = offset as usize;
+        let end = (start + LAYER_CHUNK_SIZE).min(keys.len());
+        keys[start..end].copy_from_slice(&keycodes[..end - start]);
+
+        self.next = if end < keys.len() {
+            Some((layer, end as u16))
+        } else if (layer as usize) + 1 < self.layers.len() {
+            Some((layer + 1, 0))
+        } else {
+            None
+        };
+        true
+    }
+
+    /// Builds the keymap. If `layout_matrix` (matrix position `[row, col]` of each
+    /// key of the physical layout) is provided, keys are reordered to match the layout,
+    /// otherwise they are kept in matrix order.
+    pub fn build(self, keyboard: &str, layout_matrix: Option<&[[u8; 2]]>) -> QMKKeymap {
+        let cols = self.cols as usize;
+        let layers = self
+            .layers
+            .iter()
+            .map(|keys| match layout_matrix {
+                Some(matrix) => matrix
+                    .iter()
+                    .map(|[row, col]| {
+                        let code = keys
+                            .get(*row as usize * cols + *col as usize)
+                            .copied()
+                            .unwrap_or_default();
+                        keycode_name(code)
+                    })
+                    .collect(),
+                None => keys.iter().map(|code| keycode_name(*code)).collect(),
+            })
+            .collect();
+
+        QMKKeymap {
+            keyboard: keyboard.into(),
+            keymap: "live".into(),
+            layout: if layout_matrix.is_some() {
+                "layout_matrix".into()
+            } else {
+                MATRIX_ORDER.into()
+            },
+            layers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_keymap() {
+        let mut builder = QMKKeymapBuilder::new(2, 10, 2);
+        let mut requests = 0;
+        while let Some(QMKRequest::LayerChunk { layer, offset }) = builder.next_request() {
+            let keycodes: Vec<u16> = (0..LAYER_CHUNK_SIZE as u16)
+                .map(|i| 0x04 + layer as u16 * 20 + offset + i)
+                .collect();
+            assert!(builder.add_chunk(layer, offset, &keycodes));
+            requests += 1;
+        }
+        assert_eq!(4, requests);
+        assert!(builder.is_complete());
+        assert!(!builder.add_chunk(0, 0, &[0; LAYER_CHUNK_SIZE]));
+
+        let keymap = builder.build("test", Some(&[[1, 0], [0, 0]]));
+        assert!(!keymap.is_matrix_order());
+        assert_eq!(2, keymap.layer_count());
+        assert_eq!(
+            Some(&["KC_K".to_string(), "KC_A".to_string()][..]),
+            keymap.layer(0)
+        );
+        assert_eq!(
+            Some(&["KC_5".to_string(), "KC_U".to_string()][..]),
+            keymap.layer(1)
+        );
+    }
+}
@@ -1,2 +1,121 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-pub struct QMKRequest {}
+use serde::{Deserialize, Serialize};
+
+use super::QMKEvent;
+
+/// Request sent to QMK keyboard via Raw HID. The keyboard responds with
+/// a `QMKEvent` using the same function id (byte 0).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum QMKRequest {
+    /// Health check: the keyboard sends back the same packet
+    Echo(u32),
+    /// Asks for matrix size and number of layers
+    KeyboardInfo,
+    /// Asks for keycodes of given layer, starting at given key position
+    /// (row * cols + col)
+    LayerChunk {
+        layer: u8,
+        offset: u16,
+    },
+    SetRgb {
+        enabled: bool,
+        mode: u8,
+        hue: u8,
+        saturation: u8,
+        value: u8,
+    },
+    SetDefaultLayer(u8),
+}
+
+impl QMKRequest {
+    pub fn to_bytes(&self) -> [u8; 32] {
+        use QMKRequest::*;
+        let mut bytes = [0u8; 32];
+        match self {
+            Echo(nonce) => {
+                bytes[0] = 0x01;
+                bytes[1..5].copy_from_slice(&nonce.to_le_bytes());
+            }
+            LayerChunk { layer, offset } => {
+                bytes[0] = 0x10;
+                bytes[1] = *layer;
+                bytes[2..4].copy_from_slice(&offset.to_be_bytes());
+            }
+            KeyboardInfo => {
+                bytes[0] = 0x11;
+            }
+            SetRgb {
+                enabled,
+                mode,
+                hue,
+                saturation,
+                value,
+            } => {
+                bytes[0] = 0x12;
+                bytes[1] = *enabled as u8;
+                bytes[2] = *mode;
+                bytes[3] = *hue;
+                bytes[4] = *saturation;
+                bytes[5] = *value;
+            }
+            SetDefaultLayer(layer) => {
+                bytes[0] = 0x13;
+                bytes[1] = *layer;
+            }
+        }
+        bytes
+    }
+
+    /// Returns true if the event is the keyboard's response to this request.
+    pub fn is_response(&self, event: &QMKEvent) -> bool {
+        match (self, event) {
+            (QMKRequest::Echo(_), QMKEvent::Echo(bytes)) => *bytes == self.to_bytes(),
+            (QMKRequest::KeyboardInfo, QMKEvent::KeyboardInfo { .. }) => true,
+            (
+                QMKRequest::LayerChunk { layer, offset },
+                QMKEvent::LayerChunk {
+                    layer: chunk_layer,
+                    offset: chunk_offset,
+                    ..
+                },
+            ) => layer == chunk_layer && offset == chunk_offset,
+            (QMKRequest::SetRgb { .. }, QMKEvent::RgbSet(_)) => true,
+            (QMKRequest::SetDefaultLayer(layer), QMKEvent::DefaultLayerSet(set)) => layer == set,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_responses() {
+        let request = QMKRequest::Echo(0xdeadbeef);
+        let echo = QMKEvent::try_from(request.to_bytes()).unwrap();
+        assert!(request.is_response(&echo));
+        assert!(!QMKRequest::Echo(1).is_response(&echo));
+
+        let request = QMKRequest::LayerChunk {
+            layer: 1,
+            offset: 300,
+        };
+        let mut bytes = request.to_bytes();
+        bytes[4] = 0x29; // KC_ESC
+        let chunk = QMKEvent::try_from(bytes).unwrap();
+        let QMKEvent::LayerChunk { ref keycodes, .. } = chunk else {
+            panic!("Expected layer chunk, got {chunk:?}");
+        };
+        assert_eq!(0x29, keycodes[0]);
+        assert!(request.is_response(&chunk));
+        assert!(
+            !QMKRequest::LayerChunk {
+                layer: 1,
+                offset: 0
+            }
+            .is_response(&chunk)
+        );
+        assert!(!QMKRequest::KeyboardInfo.is_response(&chunk));
+    }
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::HashMap;
+
+use super::{Keymap, Modifiers};
+
+/// Left and right modifiers are not distinguished (i.e. `Alt+A` of a keymap matches
+/// `RAlt+A`, which is how AltGr characters are usually typed)
+fn fold_sides(modifiers: u8) -> u8 {
+    (modifiers | modifiers >> 4) & 0x0F
+}
+
+/// Reverse of `Keymap`: character produced on the host by a key with given modifiers
+#[derive(Debug, Clone, Default)]
+pub struct ReverseKeymap {
+    chars: HashMap<(u8, u8), char>,
+}
+
+impl ReverseKeymap {
+    /// Character typed by the key (HID key code) with given modifiers, if any.
+    /// Shortcuts (i.e. `Ctrl+C`) don't produce characters.
+    pub fn char(&self, modifiers: Modifiers, key: u8) -> Option<char> {
+        self.chars
+            .get(&(fold_sides(modifiers.value()), key))
+            .copied()
+    }
+}
+
+impl From<&Keymap> for ReverseKeymap {
+    fn from(keymap: &Keymap) -> Self {
+        let mut chars = HashMap::new();
+        for (c, report) in &keymap.mappings {
+            let report = report.to_bytes();
+            if report[3..].iter().any(|&key| key != 0) {
+                continue;
+            }
+            // characters typed the same way are ambiguous, the lowest one wins
+            chars
+                .entry((fold_sides(report[0]), report[2]))
+                .and_modify(|prev: &mut char| *prev = (*prev).min(*c))
+                .or_insert(*c);
+        }
+        Self { chars }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::{HidKeyCode, HidReport};
+
+    fn keymap() -> Keymap {
+        let report = |modifiers: Modifiers, key: HidKeyCode| {
+            HidReport::new([modifiers.value(), 0, key.into(), 0, 0, 0, 0, 0])
+        };
+        let mappings = [
+            ('a', report(Modifiers::NONE, HidKeyCode::KEY_A)),
+            ('A', report(Modifiers::LEFT_SHIFT, HidKeyCode::KEY_A)),
+            ('ą', report(Modifiers::LEFT_ALT, HidKeyCode::KEY_A)),
+        ];
+        Keymap::new("test".into(), None, mappings.into())
+    }
+
+    #[test]
+    fn test_char() {
+        let reverse = ReverseKeymap::from(&keymap());
+        let a = HidKeyCode::KEY_A.into();
+        assert_eq!(Some('a'), reverse.char(Modifiers::NONE, a));
+        assert_eq!(Some('A'), reverse.char(Modifiers::LEFT_SHIFT, a));
+        assert_eq!(Some('A'), reverse.char(Modifiers::RIGHT_SHIFT, a));
+        assert_eq!(Some('ą'), reverse.char(Modifiers::RIGHT_ALT, a));
+        assert_eq!(None, reverse.char(Modifiers::LEFT_CTRL, a));
+        assert_eq!(
+            None,
+            reverse.char(Modifiers::NONE, HidKeyCode::KEY_B.into())
+        );
+    }
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::qmk::{QMKRecord, keycode_name};
+
+/// Key presses of a QMK keyboard, counted by physical matrix position
+/// and by firmware keycode (so layer keys, mod-taps etc. are counted as such).
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyHeatmap {
+    /// Number of presses per matrix position, where key is `"row,col"`
+    pub positions: BTreeMap<String, u64>,
+
+    /// Number of presses per keycode name, i.e. `"KC_A"` or `"MO(1)"`
+    pub keycodes: BTreeMap<String, u64>,
+}
+
+impl KeyHeatmap {
+    /// Counts the key if it's pressed (releases are ignored).
+    pub fn register(&mut self, record: &QMKRecord) {
+        if !record.pressed {
+            return;
+        }
+        *self
+            .positions
+            .entry(format!("{},{}", record.row, record.col))
+            .or_default() += 1;
+        *self
+            .keycodes
+            .entry(keycode_name(record.keycode))
+            .or_default() += 1;
+    }
+
+    pub fn position(&self, row: u8, col: u8) -> u64 {
+        self.positions
+            .get(&format!("{row},{col}"))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_register() {
+        let mut heatmap = KeyHeatmap::default();
+        heatmap.register(&QMKRecord::new(0x04, true, 2, 1));
+        heatmap.register(&QMKRecord::new(0x04, false, 2, 1));
+        heatmap.register(&QMKRecord::new(0x5221, true, 5, 7));
+        heatmap.register(&QMKRecord::new(0x5221, true, 5, 7));
+
+        assert_eq!(1, heatmap.position(2, 1));
+        assert_eq!(2, heatmap.position(5, 7));
+        assert_eq!(0, heatmap.position(0, 0));
+        assert_eq!(Some(&2), heatmap.keycodes.get("MO(1)"));
+        assert_eq!(Some(&1), heatmap.keycodes.get("KC_A"));
+    }
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Timing of a single key event on its way from the input device to the host.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyTrace {
+    pub keyboard: String,
+
+    /// Timestamp of the input (evdev) event, in nanoseconds since Unix epoch
+    pub timestamp: u64,
+
+    /// Stages the event passed (in order), with time elapsed since `timestamp`
+    /// in nanoseconds
+    pub stages: Vec<(String, u64)>,
+}
+
+impl LatencyTrace {
+    pub fn new(keyboard: &str, timestamp: u64) -> Self {
+        Self {
+            keyboard: keyboard.into(),
+            timestamp,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Records the stage as finished at `time` (nanoseconds since Unix epoch)
+    pub fn mark(&mut self, stage: &str, time: u64) {
+        self.stages
+            .push((stage.into(), time.saturating_sub(self.timestamp)));
+    }
+
+    /// Time from the input event to the end of the last stage, in nanoseconds
+    pub fn total(&self) -> u64 {
+        self.stages.last().map_or(0, |(_, elapsed)| *elapsed)
+    }
+
+    /// Duration of each stage (time since the end of the previous one), in nanoseconds
+    pub fn stage_durations(&self) -> impl Iterator<Item = (&str, u64)> {
+        let mut prev = 0;
+        self.stages.iter().map(move |(stage, elapsed)| {
+            let duration = elapsed.saturating_sub(prev);
+            prev = *elapsed;
+            (stage.as_str(), duration)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stage_durations() {
+        let mut trace = LatencyTrace::new("main", 1_000);
+        trace.mark("scanner", 1_200);
+        trace.mark("pipeline", 1_250);
+        trace.mark("hid_write", 1_900);
+
+        assert_eq!(900, trace.total());
+        assert_eq!(
+            vec![("scanner", 200), ("pipeline", 50), ("hid_write", 650)],
+            trace.stage_durations().collect::<Vec<_>>()
+        );
+    }
+}
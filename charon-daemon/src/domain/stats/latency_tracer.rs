@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use lru_time_cache::LruCache;
+use maiko::EventId;
+
+use super::LatencyTrace;
+
+/// Collects `LatencyTrace` of key events, shared by the actors the events pass through.
+/// Traces are identified by id of the input event (the correlation id of derived events).
+/// Unfinished traces (i.e. key events that didn't produce any report) expire after a while.
+/// When disabled, all the methods are no-op.
+#[derive(Clone, Default)]
+pub struct LatencyTracer {
+    traces: Option<Arc<Mutex<LruCache<EventId, LatencyTrace>>>>,
+}
+
+impl LatencyTracer {
+    pub fn new(enabled: bool) -> Self {
+        let cache = || LruCache::with_expiry_duration_and_capacity(Duration::from_secs(5), 1024);
+        Self {
+            traces: enabled.then(|| Arc::new(Mutex::new(cache()))),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.traces.is_some()
+    }
+
+    /// Starts the trace of input event with given id and timestamp (nanoseconds since epoch)
+    pub fn start(&self, id: EventId, keyboard: &str, timestamp: u64) {
+        self.with_traces(|traces| {
+            traces.insert(id, LatencyTrace::new(keyboard, timestamp));
+        });
+    }
+
+    /// Records the stage of the trace as finished now
+    pub fn mark(&self, id: EventId, stage: &str) {
+        self.with_traces(|traces| {
+            if let Some(trace) = traces.get_mut(&id) {
+                trace.mark(stage, now());
+            }
+        });
+    }
+
+    /// Records the last stage and returns the complete trace
+    pub fn finish(&self, id: EventId, stage: &str) -> Option<LatencyTrace> {
+        let mut trace = self.with_traces(|traces| traces.remove(&id)).flatten()?;
+        trace.mark(stage, now());
+        Some(trace)
+    }
+
+    fn with_traces<T>(
+        &self,
+        f: impl FnOnce(&mut LruCache<EventId, LatencyTrace>) -> T,
+    ) -> Option<T> {
+        let mut traces = self.traces.as_ref()?.lock().ok()?;
+        Some(f(&mut traces))
+    }
+}
+
+/// Nanoseconds since Unix epoch (the clock of evdev timestamps)
+pub fn now() -> u64 {
+    timestamp(SystemTime::now())
+}
+
+pub fn timestamp(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tracer() {
+        let tracer = LatencyTracer::new(true);
+        tracer.start(1, "main", now());
+        tracer.mark(1, "scanner");
+        tracer.mark(2, "scanner");
+        let trace = tracer.finish(1, "hid_write").unwrap();
+        assert_eq!(
+            vec!["scanner", "hid_write"],
+            trace.stages.iter().map(|(s, _)| s).collect::<Vec<_>>()
+        );
+        assert!(tracer.finish(1, "hid_write").is_none());
+
+        let tracer = LatencyTracer::new(false);
+        tracer.start(1, "main", now());
+        assert!(tracer.finish(1, "hid_write").is_none());
+    }
+}
@@ -1,4 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 mod current_stats;
+mod key_heatmap;
+mod latency_trace;
+mod latency_tracer;
 
 pub use current_stats::CurrentStats;
+pub use key_heatmap::KeyHeatmap;
+pub use latency_trace::LatencyTrace;
+pub use latency_tracer::{LatencyTracer, now, timestamp};
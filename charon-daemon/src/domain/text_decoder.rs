@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use super::{HidKeyCode, Modifiers, ReverseKeymap};
+
+/// Turns a stream of HID reports sent to the host into the text the host will
+/// most likely see, using the reverse of the host keymap.
+///
+/// A character is typed when its key appears in a report (autorepeat is done by the host,
+/// so it's not seen here). The state of Caps Lock is tracked, assuming it's off at start.
+#[derive(Debug, Default)]
+pub struct TextDecoder {
+    keymap: ReverseKeymap,
+    /// Keys of the previous report
+    pressed: [u8; 6],
+    caps_lock: bool,
+}
+
+impl TextDecoder {
+    pub fn new(keymap: ReverseKeymap) -> Self {
+        Self {
+            keymap,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_keymap(&mut self, keymap: ReverseKeymap) {
+        self.keymap = keymap;
+    }
+
+    /// Characters typed by the keys pressed since the previous report
+    pub fn decode(&mut self, report: &[u8; 8]) -> Vec<char> {
+        let modifiers = Modifiers::new(report[0]);
+        let mut chars = Vec::new();
+        for &key in report[2..].iter().filter(|&&key| key != 0) {
+            if self.pressed.contains(&key) {
+                continue;
+            }
+            if key == u8::from(HidKeyCode::KEY_CAPSLOCK) {
+                self.caps_lock = !self.caps_lock;
+            }
+            if let Some(c) = self.keymap.char(modifiers, key) {
+                chars.push(match self.caps_lock && c.is_alphabetic() {
+                    true => swap_case(c),
+                    false => c,
+                });
+            }
+        }
+        self.pressed.copy_from_slice(&report[2..]);
+        chars
+    }
+}
+
+fn swap_case(c: char) -> char {
+    let swapped: String = match c.is_uppercase() {
+        true => c.to_lowercase().collect(),
+        false => c.to_uppercase().collect(),
+    };
+    let mut chars = swapped.chars();
+    match (chars.next(), chars.next()) {
+        (Some(swapped), None) => swapped,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::{HidReport, Keymap};
+
+    const SHIFT: u8 = 0x02;
+    const A: u8 = HidKeyCode::KEY_A as u8;
+    const B: u8 = HidKeyCode::KEY_B as u8;
+    const CAPS: u8 = HidKeyCode::KEY_CAPSLOCK as u8;
+
+    fn decoder() -> TextDecoder {
+        let report = |modifiers, key| HidReport::new([modifiers, 0, key, 0, 0, 0, 0, 0]);
+        let mappings = [
+            ('a', report(0, A)),
+            ('A', report(SHIFT, A)),
+            ('b', report(0, B)),
+            ('1', report(0, HidKeyCode::KEY_1 as u8)),
+        ];
+        TextDecoder::new((&Keymap::new("test".into(), None, mappings.into())).into())
+    }
+
+    fn decode(decoder: &mut TextDecoder, reports: &[[u8; 8]]) -> String {
+        reports.iter().flat_map(|r| decoder.decode(r)).collect()
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut decoder = decoder();
+        let reports = [
+            [SHIFT, 0, 0, 0, 0, 0, 0, 0],
+            [SHIFT, 0, A, 0, 0, 0, 0, 0],
+            [0, 0, A, 0, 0, 0, 0, 0],
+            [0, 0, A, B, 0, 0, 0, 0],
+            [0, 0, B, 0, 0, 0, 0, 0],
+            [0, 0, B, A, 0, 0, 0, 0],
+            [0; 8],
+            [0x01, 0, A, 0, 0, 0, 0, 0], // Ctrl+A
+            [0; 8],
+        ];
+        assert_eq!("Aba", decode(&mut decoder, &reports));
+    }
+
+    #[test]
+    fn test_caps_lock() {
+        let mut decoder = decoder();
+        let reports = [
+            [0, 0, CAPS, 0, 0, 0, 0, 0],
+            [0; 8],
+            [0, 0, A, 0, 0, 0, 0, 0],
+            [0; 8],
+            [SHIFT, 0, A, 0, 0, 0, 0, 0],
+            [0; 8],
+            [0, 0, HidKeyCode::KEY_1 as u8, 0, 0, 0, 0, 0],
+            [0, 0, CAPS, 0, 0, 0, 0, 0],
+            [0, 0, A, 0, 0, 0, 0, 0],
+        ];
+        assert_eq!("Aa1a", decode(&mut decoder, &reports));
+    }
+}
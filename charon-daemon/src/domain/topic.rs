@@ -11,6 +11,7 @@ pub enum Topic {
     Monitoring,
     Telemetry,
     Keyboard,
+    Debug,
 }
 
 impl From<&CharonEvent> for Topic {
@@ -21,20 +22,43 @@ impl From<&CharonEvent> for Topic {
             KeyPress(..) => KeyInput,
             KeyRelease(..) => KeyInput,
             HidReport(_) => KeyOutput,
-            SendText(_) => TextInput,
+            SendText(..) => TextInput,
+            SendKeys(..) => TextInput,
             SendFile(..) => TextInput,
-            TextSent => Monitoring,
+            CancelTyping(_) => TextInput,
+            MacroRecorded(_) => TextInput,
+            PlayMacro => TextInput,
+            TextSent(_) => Monitoring,
+            TypingProgress(..) => Monitoring,
+            TypingFailed(..) => Monitoring,
+            TextTyped(_) => Monitoring,
             CurrentStats(_) => Stats,
+            GetKeyHeatmaps => Stats,
+            KeyHeatmaps(_) => Stats,
 
             ModeChange(_) => System,
             Sleep => System,
             WakeUp => System,
+            SwitchApp(_) => System,
+            HostProfileChange(_) => System,
+            TelemetryEnabled(_) => System,
+            ListBindings => System,
+            Bindings(_) => System,
 
             ReportSent => Telemetry,
+            LatencyTrace(_) => Debug,
+            KeyFiltered(..) => Monitoring,
 
             QMKEvent(..) => Monitoring,
+            QMKRequestTimeout(..) => Monitoring,
+            QMKRequest(..) => Keyboard,
+            GetQMKKeymap(_) => Keyboard,
+            QMKKeymap(..) => Keyboard,
 
             KeyboardAttached(..) => Keyboard,
+            KeyboardDetached(..) => Keyboard,
+            MacroKey(..) => Keyboard,
+            MacroRecording(_) => Keyboard,
         }
     }
 }
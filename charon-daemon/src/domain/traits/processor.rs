@@ -3,10 +3,27 @@ use std::pin::Pin;
 
 use crate::domain::CharonEvent;
 use maiko::Meta;
+use tokio::time::Instant;
 
 pub type ProcessorFuture<'a> = Pin<Box<dyn Future<Output = Vec<CharonEvent>> + Send + 'a>>;
 
 // #[async_trait::async_trait]
 pub trait Processor: Send + Sync {
     fn process<'a>(&'a mut self, event: CharonEvent, meta: Meta) -> ProcessorFuture<'a>;
+
+    /// Name of the processor, i.e. in latency traces
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    /// Time at which the processor needs to be called back with `on_timeout`,
+    /// even if there are no new events (i.e. to release held keys).
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    fn on_timeout<'a>(&'a mut self) -> ProcessorFuture<'a> {
+        Box::pin(async { Vec::new() })
+    }
 }
@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use serde::{Deserialize, Serialize};
+
+/// Why a typing job (see `Typist`) didn't complete, reported back to the requesting client
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+pub enum TypingError {
+    #[error("Typing cancelled")]
+    Cancelled,
+
+    #[error("Typing interrupted by mode change")]
+    Interrupted,
+
+    #[error("Invalid keys to type: {0}")]
+    InvalidKeys(String),
+
+    #[error("Couldn't read file to type: {0}")]
+    File(String),
+}
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::time::{Duration, SystemTime};
+
+use crate::config::TypingProfile;
+
+/// HID report sent to the host, followed by a pause
+pub type Keystroke = ([u8; 8], Duration);
+
+const RELEASED: [u8; 8] = [0; 8];
+
+/// Shortest pause of the safe profile
+const SAFE_MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Pause after pressing and after releasing a modifier of the safe profile, in intervals
+/// (hosts that lag may apply the modifier late, or to the next key)
+const SAFE_MODIFIER_PAUSE: u32 = 3;
+
+/// Pause after a new line of the safe profile, in intervals
+/// (i.e. for editors that auto-indent or terminals that run a command)
+const SAFE_LINE_PAUSE: u32 = 10;
+
+/// Average times of the humanized profile: key held down, modifier pressed
+/// before the key and pause before the next key
+const HUMAN_HOLD: Duration = Duration::from_millis(45);
+const HUMAN_MODIFIER_LEAD: Duration = Duration::from_millis(25);
+const HUMAN_GAP: Duration = Duration::from_millis(70);
+
+/// Turns characters (HID reports of a keymap) into keystrokes timed according
+/// to the typing profile. Used for a single text: the keys are released by `finish`.
+pub struct TypingRhythm {
+    profile: TypingProfile,
+    interval: Duration,
+    /// Report of the key left pressed (see `TypingProfile::Fast`)
+    pressed: Option<[u8; 8]>,
+    /// State of the jitter generator (xorshift)
+    seed: u64,
+}
+
+impl TypingRhythm {
+    pub fn new(profile: TypingProfile, interval: Duration) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self {
+            profile,
+            interval,
+            pressed: None,
+            seed: seed | 1,
+        }
+    }
+
+    /// Keystrokes that type a character with given report
+    pub fn keystrokes(&mut self, c: char, report: [u8; 8]) -> Vec<Keystroke> {
+        let t = self.interval;
+        let modifiers = [report[0], 0, 0, 0, 0, 0, 0, 0];
+        match self.profile {
+            TypingProfile::Fixed => vec![(report, t), (RELEASED, t)],
+            TypingProfile::Fast => {
+                let mut keystrokes = Vec::new();
+                if let Some(prev) = self.pressed.take()
+                    && (prev[0] != report[0] || prev[2..] == report[2..])
+                {
+                    keystrokes.push((RELEASED, t));
+                }
+                keystrokes.push((report, t));
+                self.pressed = Some(report);
+                keystrokes
+            }
+            TypingProfile::Safe => {
+                let t = t.max(SAFE_MIN_INTERVAL);
+                let pause = match c {
+                    '\n' => t * SAFE_LINE_PAUSE,
+                    _ => t,
+                };
+                match report[0] {
+                    0 => vec![(report, t), (RELEASED, pause)],
+                    _ => vec![
+                        (modifiers, t * SAFE_MODIFIER_PAUSE),
+                        (report, t),
+                        (modifiers, t * SAFE_MODIFIER_PAUSE),
+                        (RELEASED, pause),
+                    ],
+                }
+            }
+            TypingProfile::Humanized => {
+                let hold = self.jitter(HUMAN_HOLD).max(t);
+                let gap = match c.is_whitespace() || c.is_ascii_punctuation() {
+                    true => self.jitter(HUMAN_GAP * 2),
+                    false => self.jitter(HUMAN_GAP),
+                };
+                let mut keystrokes = Vec::new();
+                if report[0] != 0 {
+                    keystrokes.push((modifiers, self.jitter(HUMAN_MODIFIER_LEAD)));
+                }
+                keystrokes.extend([(report, hold), (RELEASED, gap.max(t))]);
+                keystrokes
+            }
+        }
+    }
+
+    /// Keystrokes that press and release a key shortcut (i.e. `{Enter}`, `{Ctrl+A}`).
+    /// Shortcuts usually trigger an action on the host, so they are never rolled over
+    /// and are followed by a pause like a new line.
+    pub fn shortcut_keystrokes(&mut self, report: [u8; 8]) -> Vec<Keystroke> {
+        let mut keystrokes = self.finish();
+        match self.profile {
+            TypingProfile::Fixed | TypingProfile::Fast => {
+                keystrokes.extend([(report, self.interval), (RELEASED, self.interval)])
+            }
+            TypingProfile::Safe | TypingProfile::Humanized => {
+                keystrokes.extend(self.keystrokes('\n', report))
+            }
+        }
+        keystrokes
+    }
+
+    /// Keystrokes that send a report as is (i.e. of a recorded macro), regardless the profile
+    pub fn report_keystrokes(&mut self, report: [u8; 8]) -> Vec<Keystroke> {
+        let mut keystrokes = self.finish();
+        keystrokes.push((report, self.interval));
+        keystrokes
+    }
+
+    /// Keystrokes that end the typing (release of the keys left pressed)
+    pub fn finish(&mut self) -> Vec<Keystroke> {
+        match self.pressed.take() {
+            Some(_) => vec![(RELEASED, self.interval)],
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the duration randomly changed by up to ±40%
+    fn jitter(&mut self, duration: Duration) -> Duration {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        let random = (self.seed % 1000) as f64 / 1000.0;
+        duration.mul_f64(0.6 + 0.8 * random)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const A: [u8; 8] = [0, 0, 0x04, 0, 0, 0, 0, 0];
+    const B: [u8; 8] = [0, 0, 0x05, 0, 0, 0, 0, 0];
+    const SHIFT_A: [u8; 8] = [0x02, 0, 0x04, 0, 0, 0, 0, 0];
+    const SHIFT: [u8; 8] = [0x02, 0, 0, 0, 0, 0, 0, 0];
+    const ENTER: [u8; 8] = [0, 0, 0x28, 0, 0, 0, 0, 0];
+
+    fn reports(rhythm: &mut TypingRhythm, text: &[(char, [u8; 8])]) -> Vec<[u8; 8]> {
+        let mut keystrokes = Vec::new();
+        for (c, report) in text {
+            keystrokes.extend(rhythm.keystrokes(*c, *report));
+        }
+        keystrokes.extend(rhythm.finish());
+        keystrokes.into_iter().map(|(report, _)| report).collect()
+    }
+
+    #[test]
+    fn test_fast() {
+        let mut rhythm = TypingRhythm::new(TypingProfile::Fast, Duration::from_millis(5));
+        let text = [('a', A), ('b', B), ('b', B), ('A', SHIFT_A), ('b', B)];
+        assert_eq!(
+            vec![A, B, RELEASED, B, RELEASED, SHIFT_A, RELEASED, B, RELEASED],
+            reports(&mut rhythm, &text)
+        );
+    }
+
+    #[test]
+    fn test_shortcut() {
+        let mut rhythm = TypingRhythm::new(TypingProfile::Fast, Duration::from_millis(5));
+        let mut reports = rhythm.keystrokes('a', A);
+        reports.extend(rhythm.shortcut_keystrokes(ENTER));
+        reports.extend(rhythm.finish());
+        assert_eq!(
+            vec![A, RELEASED, ENTER, RELEASED],
+            reports
+                .into_iter()
+                .map(|(report, _)| report)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_safe() {
+        let mut rhythm = TypingRhythm::new(TypingProfile::Safe, Duration::from_millis(1));
+        let text = [('A', SHIFT_A), ('a', A)];
+        assert_eq!(
+            vec![SHIFT, SHIFT_A, SHIFT, RELEASED, A, RELEASED],
+            reports(&mut rhythm, &text)
+        );
+        // modifier of a shifted character is given more time than the key
+        let pauses: Vec<_> = rhythm
+            .keystrokes('A', SHIFT_A)
+            .iter()
+            .map(|k| k.1)
+            .collect();
+        let modifier_pause = SAFE_MIN_INTERVAL * SAFE_MODIFIER_PAUSE;
+        assert_eq!(
+            vec![
+                modifier_pause,
+                SAFE_MIN_INTERVAL,
+                modifier_pause,
+                SAFE_MIN_INTERVAL
+            ],
+            pauses
+        );
+        assert_eq!(
+            vec![SAFE_MIN_INTERVAL, SAFE_MIN_INTERVAL],
+            rhythm
+                .keystrokes('a', A)
+                .iter()
+                .map(|k| k.1)
+                .collect::<Vec<_>>()
+        );
+        let keystrokes = rhythm.keystrokes('\n', ENTER);
+        assert_eq!(
+            vec![
+                (ENTER, SAFE_MIN_INTERVAL),
+                (RELEASED, SAFE_MIN_INTERVAL * 10)
+            ],
+            keystrokes
+        );
+    }
+
+    #[test]
+    fn test_humanized() {
+        let mut rhythm = TypingRhythm::new(TypingProfile::Humanized, Duration::from_millis(1));
+        let mut holds = Vec::new();
+        for _ in 0..20 {
+            let keystrokes = rhythm.keystrokes('a', A);
+            assert_eq!(
+                vec![A, RELEASED],
+                keystrokes.iter().map(|k| k.0).collect::<Vec<_>>()
+            );
+            let hold = keystrokes[0].1;
+            assert!(hold >= HUMAN_HOLD.mul_f64(0.6) && hold <= HUMAN_HOLD.mul_f64(1.4));
+            holds.push(hold);
+        }
+        holds.dedup();
+        assert!(holds.len() > 1, "Timing should be jittered");
+        assert_eq!(SHIFT, rhythm.keystrokes('A', SHIFT_A)[0].0);
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{str::FromStr, time::Duration};
+
+use crate::error::CharonError;
+
+use super::KeyShortcut;
+
+/// Single step of typing keys (see `CharonEvent::SendKeys`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypingStep {
+    /// Character typed with the host keymap
+    Char(char),
+    /// Named key or chord, i.e. `{Enter}`, `{Ctrl+A}`
+    Shortcut(KeyShortcut),
+    /// Pause, i.e. `{Sleep 200}` (in milliseconds)
+    Sleep(Duration),
+    /// HID report sent as is (i.e. of a recorded macro)
+    Report([u8; 8]),
+}
+
+impl TypingStep {
+    /// Parses text mixed with keys in braces: `{Tab}`, `{Ctrl+Shift+T}` (see `KeyShortcut`
+    /// for the syntax) and pauses: `{Sleep <ms>}`. `{{` is a literal `{`.
+    /// I.e. `username{Tab}password{Enter}`.
+    pub fn parse_all(keys: &str) -> Result<Vec<Self>, CharonError> {
+        let mut steps = Vec::new();
+        let mut chars = keys.chars();
+        while let Some(c) = chars.next() {
+            if c != '{' {
+                steps.push(Self::Char(c));
+                continue;
+            }
+            let rest = chars.as_str();
+            if rest.starts_with('{') {
+                chars.next();
+                steps.push(Self::Char('{'));
+                continue;
+            }
+            let end = rest
+                .find('}')
+                .ok_or_else(|| CharonError::InvalidKeys(format!("unclosed {{ in {keys}")))?;
+            steps.push(Self::from_str(&rest[..end])?);
+            chars = rest[end + 1..].chars();
+        }
+        Ok(steps)
+    }
+}
+
+impl FromStr for TypingStep {
+    type Err = CharonError;
+
+    /// Parses content of braces, i.e. `Enter` or `Sleep 200`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let mut words = s.split_whitespace();
+        if let (Some(command), Some(millis), None) = (words.next(), words.next(), words.next())
+            && command.eq_ignore_ascii_case("sleep")
+        {
+            let millis = millis
+                .parse()
+                .map_err(|_| CharonError::InvalidKeys(format!("invalid sleep time: {s}")))?;
+            return Ok(Self::Sleep(Duration::from_millis(millis)));
+        }
+        Ok(Self::Shortcut(KeyShortcut::from_str(s)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::{HidKeyCode, Modifiers};
+
+    #[test]
+    fn test_parse_all() {
+        let shortcut = |key, modifiers| TypingStep::Shortcut(KeyShortcut::new(key, modifiers));
+        let steps = TypingStep::parse_all("a{Tab}{{b}{ctrl+A}{Sleep 200}").unwrap();
+        assert_eq!(
+            vec![
+                TypingStep::Char('a'),
+                shortcut(HidKeyCode::KEY_TAB, Modifiers::NONE),
+                TypingStep::Char('{'),
+                TypingStep::Char('b'),
+                TypingStep::Char('}'),
+                shortcut(HidKeyCode::KEY_A, Modifiers::LEFT_CTRL),
+                TypingStep::Sleep(Duration::from_millis(200)),
+            ],
+            steps
+        );
+        assert_eq!(
+            vec![shortcut(HidKeyCode::KEY_ENTER, Modifiers::NONE)],
+            TypingStep::parse_all("{ENTER}").unwrap()
+        );
+
+        for keys in ["{Enter", "{}", "{Sleep}", "{Sleep 1s}", "{Foo}", "{Ctrl+}"] {
+            assert!(
+                TypingStep::parse_all(keys).is_err(),
+                "{keys} should be rejected"
+            );
+        }
+    }
+}
@@ -20,9 +20,18 @@ pub enum CharonError {
     #[error("Unsupported key name: {0}")]
     UnsupportedKeyName(String),
 
+    #[error("Invalid keys to type: {0}")]
+    InvalidKeys(String),
+
     #[error("Couldn't find requested keyboard: {0}")]
     KeyboardNotFound(String),
 
+    #[error("Invalid processor config: {0}")]
+    InvalidProcessorConfig(String),
+
+    #[error("Invalid input recording: {0}")]
+    InvalidRecording(String),
+
     #[error("QMK error: {0}")]
     QMKError(String),
 
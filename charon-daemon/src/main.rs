@@ -13,29 +13,37 @@ use crate::{
     domain::{Mode, Topic as T},
 };
 use maiko::Supervisor;
-use std::sync::Arc;
-use tokio::{self, io::unix::AsyncFd, signal::unix};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::{self, signal::unix};
 use tracing_subscriber::FmtSubscriber;
 
 use crate::{
     actor::{
-        KeyScanner, KeyWriter, Pipeline, PowerManager, QMK, Telemetry, TypingStats, Typist,
-        ipc_bridge::IPCServer,
+        DeviceMonitor, KeyScanner, KeyWriter, LatencyTraceWriter, Pipeline, PowerManager, QMK,
+        Telemetry, Transcriber, TypingStats, Typist, ipc_bridge::IPCServer,
     },
-    adapter::{EventDeviceUnix, HIDDeviceUnix, KeymapLoaderYaml, QmkAsyncHidDevice},
-    config::CharonConfig,
-    domain::{ActorState, traits::Processor},
+    adapter::{
+        DeviceWatcherInotify, EventDeviceRecorder, EventDeviceUnix, HIDDeviceUnix,
+        KeymapLoaderYaml, QmkAsyncHidDevice, SerialDevice, ViaDevice, ZmkStudioDevice,
+    },
+    config::{
+        CharonConfig,
+        keyboard::{RawHidProtocol, SerialProtocol},
+    },
+    domain::{ActorState, ReverseKeymap},
     error::CharonError,
     port::KeymapLoader,
-    processor::{KeyEventProcessor, SystemShortcutProcessor},
-    util::evdev::find_input_device,
+    processor::ProcessorRegistry,
 };
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     init_logging();
 
-    let config = Arc::new(CharonConfig::from_file().expect("Failed loading config file"));
+    let args = Args::parse()?;
+    let mut config = CharonConfig::from_file().expect("Failed loading config file");
+    config.latency_tracing |= args.latency_trace.is_some();
+    let config = Arc::new(config);
     let state = ActorState::new(Mode::PassThrough, config.clone());
     let keymap = KeymapLoaderYaml::new(&config.keymaps_dir)
         .load_keymap(&config.host_keymap)
@@ -43,21 +51,48 @@ async fn main() -> eyre::Result<()> {
 
     let mut supervisor = Supervisor::default();
 
-    for (name, config) in config.get_config_per_keyboard() {
-        supervisor.add_actor(
-            &format!("KeyScanner-{name}"),
-            |ctx| {
-                let keyboard = &config.keyboard;
-                let device_path = find_input_device(keyboard)
-                    .ok_or_else(|| CharonError::KeyboardNotFound(name.clone()))
-                    .unwrap();
-                let device = evdev::Device::open(device_path).unwrap();
-                let async_dev = AsyncFd::new(device).unwrap();
-                let input = EventDeviceUnix::new(async_dev);
-                KeyScanner::new(ctx, state.clone(), input, name)
-            },
-            [T::System],
-        )?;
+    // keyboards are discovered once, so scanners and device monitor share the aliases
+    let keyboards = config.get_config_per_keyboard();
+    let monitored: Vec<_> = keyboards
+        .iter()
+        .map(|(name, config)| (name.clone(), config.keyboard.clone()))
+        .collect();
+
+    if args.record_input.is_some() {
+        tracing::warn!("Recording input: everything typed, including passwords, is stored");
+    }
+    for (name, config) in keyboards {
+        let input = EventDeviceUnix::new(config.keyboard.clone());
+        let actor_name = format!("KeyScanner-{name}");
+        match &args.record_input {
+            Some(dir) => {
+                let path = dir.join(format!("{name}.events"));
+                let input = EventDeviceRecorder::new(input, &path, &name)?;
+                supervisor.add_actor(
+                    &actor_name,
+                    |ctx| KeyScanner::new(ctx, state.clone(), input, name),
+                    [T::System, T::Keyboard],
+                )?;
+            }
+            None => {
+                supervisor.add_actor(
+                    &actor_name,
+                    |ctx| KeyScanner::new(ctx, state.clone(), input, name),
+                    [T::System, T::Keyboard],
+                )?;
+            }
+        }
+    }
+
+    match DeviceWatcherInotify::new() {
+        Ok(watcher) => {
+            supervisor.add_actor(
+                "DeviceMonitor",
+                |ctx| DeviceMonitor::new(ctx, state.clone(), watcher, monitored),
+                [T::System],
+            )?;
+        }
+        Err(err) => tracing::error!("Couldn't watch input devices, hot-plug disabled: {err}"),
     }
 
     supervisor.add_actor(
@@ -65,27 +100,33 @@ async fn main() -> eyre::Result<()> {
         |ctx| {
             let dev_path = config.hid_keyboard.clone();
             let dev = HIDDeviceUnix::new(&dev_path);
-            KeyWriter::new(ctx, dev)
+            KeyWriter::new(ctx, state.clone(), dev)
         },
         [T::System, T::KeyOutput],
     )?;
 
+    let registry = ProcessorRegistry::default();
+    let mut pipeline_error = None;
     supervisor.add_actor(
         "KeyEventPipeline",
         |ctx| {
-            let processors: Vec<Box<dyn Processor + Send + Sync>> = vec![
-                Box::new(KeyEventProcessor::default()),
-                Box::new(SystemShortcutProcessor::new(ctx.clone(), state.clone())),
-            ];
-            Pipeline::new(ctx, processors)
+            registry
+                .build_pipeline(ctx.clone(), &state)
+                .unwrap_or_else(|err| {
+                    pipeline_error = Some(err);
+                    Pipeline::new(ctx, Vec::new())
+                })
         },
         [T::System, T::KeyInput],
     )?;
+    if let Some(err) = pipeline_error {
+        return Err(err.into());
+    }
 
     supervisor.add_actor(
         "IPCServer",
         |ctx| IPCServer::new(ctx, state.clone()),
-        [T::System, T::Stats, T::Monitoring],
+        [T::System, T::Stats, T::Monitoring, T::Keyboard, T::Debug],
     )?;
 
     if config.sleep_script.is_some() && config.awake_script.is_some() {
@@ -96,27 +137,73 @@ async fn main() -> eyre::Result<()> {
         )?;
     }
 
-    let raw_enabled = config.keyboard_info().is_some_and(|group| {
-        group.raw_hid_enabled && group.vendor_id.is_some() && group.product_id.is_some()
-    });
-    if raw_enabled {
-        // let alias = match config.keyboard {
-        //     InputConfig::Use(ref keyb) => keyb.clone(),
-        //     ref k => Err(CharonError::QMKError(format!(
-        //         "{k:?} - insufficient or wrong configuration to enable QMK actor"
-        //     )))?,
-        // };
-        let device = QmkAsyncHidDevice::async_new(&config).await;
+    for (alias, group) in config.qmk_keyboards() {
+        let device = QmkAsyncHidDevice::new(
+            group.vendor_id.unwrap_or_default(),
+            group.product_id.unwrap_or_default(),
+        );
+        match group.raw_hid_protocol {
+            RawHidProtocol::Charon => supervisor.add_actor(
+                &format!("QMK-{alias}"),
+                |ctx| QMK::new(ctx, state.clone(), alias.clone(), device),
+                [T::System, T::Keyboard],
+            )?,
+            RawHidProtocol::Via => {
+                let [rows, cols] = group.matrix_size.ok_or_else(|| {
+                    CharonError::QMKError(format!("{alias}: matrix_size is required by VIA"))
+                })?;
+                let poll_interval = Duration::from_millis(config.via_poll_interval);
+                let device = ViaDevice::new(device, rows, cols, poll_interval)?;
+                supervisor.add_actor(
+                    &format!("VIA-{alias}"),
+                    |ctx| QMK::new(ctx, state.clone(), alias.clone(), device),
+                    [T::System, T::Keyboard],
+                )?
+            }
+        };
+    }
+
+    for (alias, group) in config.serial_keyboards() {
+        let path = group.serial_port.clone().unwrap_or_default();
+        match group.serial_protocol {
+            SerialProtocol::Charon => supervisor.add_actor(
+                &format!("Serial-{alias}"),
+                |ctx| QMK::new(ctx, state.clone(), alias.clone(), SerialDevice::new(path)),
+                [T::System, T::Keyboard],
+            )?,
+            SerialProtocol::Zmk => supervisor.add_actor(
+                &format!("ZMK-{alias}"),
+                |ctx| {
+                    QMK::new(
+                        ctx,
+                        state.clone(),
+                        alias.clone(),
+                        ZmkStudioDevice::new(path),
+                    )
+                },
+                [T::System, T::Keyboard],
+            )?,
+        };
+    }
+
+    if config.text_decoding {
+        let reverse_keymap = ReverseKeymap::from(&keymap);
         supervisor.add_actor(
-            "QMK",
-            |ctx| QMK::new(ctx, state.clone(), device),
-            [T::System],
+            "Transcriber",
+            |ctx| {
+                let loader = KeymapLoaderYaml::new(&config.keymaps_dir);
+                Transcriber::new(ctx, state.clone(), reverse_keymap, loader)
+            },
+            [T::System, T::KeyOutput],
         )?;
     }
 
     supervisor.add_actor(
         "Typist",
-        |ctx| Typist::new(ctx, state.clone(), keymap),
+        |ctx| {
+            let loader = KeymapLoaderYaml::new(&config.keymaps_dir);
+            Typist::new(ctx, state.clone(), keymap, loader)
+        },
         &[T::System, T::TextInput],
     )?;
 
@@ -125,14 +212,26 @@ async fn main() -> eyre::Result<()> {
         supervisor.add_actor(
             "Telemetry",
             |_ctx| Telemetry::new(prometheus),
-            [T::System, T::Telemetry, T::KeyInput, T::Stats],
+            [
+                T::System,
+                T::Telemetry,
+                T::KeyInput,
+                T::Stats,
+                T::Monitoring,
+                T::Debug,
+            ],
         )?;
     }
 
+    if let Some(path) = args.latency_trace {
+        let writer = LatencyTraceWriter::new(&path).await?;
+        supervisor.add_actor("LatencyTraceWriter", |_ctx| writer, [T::Debug])?;
+    }
+
     supervisor.add_actor(
         "TypingStats",
         |ctx| TypingStats::new(ctx, state.clone()),
-        [T::System, T::KeyInput],
+        [T::System, T::KeyInput, T::Stats, T::Monitoring],
     )?;
 
     let mut sigterm = unix::signal(unix::SignalKind::terminate())?;
@@ -156,6 +255,36 @@ async fn main() -> eyre::Result<()> {
     Ok(())
 }
 
+/// Command line options
+#[derive(Default)]
+struct Args {
+    /// `--latency-trace <FILE>`: enables latency tracing and dumps the traces to the file
+    latency_trace: Option<PathBuf>,
+    /// `--record-input <DIR>`: records raw input events of each keyboard
+    /// to `<DIR>/<keyboard>.events` (see `EventDeviceReplay`), also after it's re-attached.
+    /// The recordings contain everything typed, including passwords
+    record_input: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> eyre::Result<Self> {
+        let mut args = std::env::args().skip(1);
+        let mut result = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || match args.next() {
+                Some(value) => Ok(PathBuf::from(value)),
+                None => Err(eyre::eyre!("{arg} requires a value")),
+            };
+            match arg.as_str() {
+                "--latency-trace" => result.latency_trace = Some(value()?),
+                "--record-input" => result.record_input = Some(value()?),
+                other => eyre::bail!("Unknown option: {other}"),
+            }
+        }
+        Ok(result)
+    }
+}
+
 fn init_logging() {
     let subscriber = FmtSubscriber::builder()
         .with_max_level(tracing::Level::DEBUG)
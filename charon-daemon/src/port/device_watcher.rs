@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use crate::domain::DeviceChange;
+
+pub trait DeviceWatcher: Send + 'static {
+    /// Names of input devices currently present in the system.
+    fn devices(&self) -> Vec<String>;
+
+    /// Waits for the next device change. Returns `None` if watching is no longer possible.
+    fn next_change(&mut self) -> impl Future<Output = Option<DeviceChange>> + Send;
+}
@@ -1,9 +1,28 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use evdev::InputEvent;
+use evdev::{InputEvent, KeyCode};
 
 pub trait EventDevice: Send + 'static {
     fn next_event(&mut self) -> impl Future<Output = Option<InputEvent>> + Send;
     fn is_grabbed(&self) -> bool;
     fn grab(&mut self) -> std::io::Result<()>;
     fn ungrab(&mut self) -> std::io::Result<()>;
+
+    /// Returns keys currently pressed according to the device itself
+    /// (as opposed to the events read so far).
+    fn key_state(&self) -> std::io::Result<Vec<KeyCode>> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Returns false if the device is not present (i.e. unplugged or not found at startup).
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    /// Opens (or re-opens) the device.
+    fn connect(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Releases the device (typically after it's been unplugged).
+    fn disconnect(&mut self) {}
 }
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use crate::domain::qmk::{QMKEvent, QMKRequest};
+
+use crate::error::CharonError;
+
+/// Side channel to the keyboard firmware (next to its HID interface), used to receive
+/// layer changes, mode switches and key events with matrix positions, and to send requests.
+/// Implemented over QMK Raw HID, VIA, CDC-ACM serial line protocol and ZMK Studio RPC.
+pub trait KeyboardSideChannel: Send + 'static {
+    fn is_connected(&self) -> bool;
+    fn connect(&mut self) -> impl Future<Output = Result<(), CharonError>> + Send;
+    fn read_event(&mut self) -> impl Future<Output = Result<Option<QMKEvent>, CharonError>> + Send;
+    fn send_request(
+        &mut self,
+        request: &QMKRequest,
+    ) -> impl Future<Output = Result<(), CharonError>> + Send;
+}
@@ -2,5 +2,5 @@
 use crate::{domain::Keymap, error::CharonError};
 
 pub trait KeymapLoader {
-    fn load_keymap(&self, name: &str) -> impl Future<Output = Result<Keymap, CharonError>>;
+    fn load_keymap(&self, name: &str) -> impl Future<Output = Result<Keymap, CharonError>> + Send;
 }
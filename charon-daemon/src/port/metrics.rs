@@ -3,11 +3,21 @@ use core::future::Future;
 
 use evdev::KeyCode;
 
-use crate::error::CharonError;
+use crate::{
+    domain::{FilterReason, qmk::QMKRecord, stats::LatencyTrace},
+    error::CharonError,
+};
 
 pub trait Metrics: Send + 'static {
     fn register_key_event(&self, key: &KeyCode, keyboard: &str);
+    /// Key press reported by QMK keyboard: matrix position and firmware keycode
+    /// on the currently active `layer`.
+    fn register_matrix_key_event(&self, record: &QMKRecord, keyboard: &str, layer: u8);
+    /// Key event dropped by the key filter (i.e. chattering switch)
+    fn register_filtered_key_event(&self, key: &KeyCode, keyboard: &str, reason: FilterReason);
     fn register_key_to_report_time(&self, time: u64);
+    /// Duration of each stage of a key event (and the total time), see `LatencyTrace`
+    fn register_latency_trace(&self, trace: &LatencyTrace);
     fn register_wpm(&self, wpm: u16);
 
     fn flush(&mut self) -> impl Future<Output = Result<(), CharonError>> + Send;
@@ -1,14 +1,16 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+mod device_watcher;
 mod event_device;
 mod hid_device;
+mod keyboard_side_channel;
 mod keymap_loader;
 mod metrics;
-mod qmk_device;
 mod raw_hid_device;
 
+pub use device_watcher::DeviceWatcher;
 pub use event_device::EventDevice;
 pub use hid_device::HIDDevice;
+pub use keyboard_side_channel::KeyboardSideChannel;
 pub use keymap_loader::KeymapLoader;
 pub use metrics::Metrics;
-pub use qmk_device::QmkDevice;
 pub use raw_hid_device::RawHidDevice;
@@ -1,6 +1,14 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 use crate::error::CharonError;
 
-pub trait RawHidDevice {
-    fn read_packet(&mut self) -> impl Future<Output = Result<(usize, [u8; 32]), CharonError>>;
+pub trait RawHidDevice: Send + 'static {
+    fn is_connected(&self) -> bool;
+    fn connect(&mut self) -> impl Future<Output = Result<(), CharonError>> + Send;
+    fn read_packet(
+        &mut self,
+    ) -> impl Future<Output = Result<(usize, [u8; 32]), CharonError>> + Send;
+    fn write_packet(
+        &mut self,
+        packet: &[u8; 32],
+    ) -> impl Future<Output = Result<(), CharonError>> + Send;
 }
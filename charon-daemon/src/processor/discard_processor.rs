@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use crate::domain::{CharonEvent, traits::ProcessorFuture};
+use maiko::Meta;
+
+use crate::domain::traits::Processor;
+
+/// Drops all key events, so they never reach the host.
+/// Used for keyboards in in-app-only role.
+pub struct DiscardProcessor;
+
+impl Processor for DiscardProcessor {
+    fn process<'a>(&'a mut self, event: CharonEvent, _meta: Meta) -> ProcessorFuture<'a> {
+        Box::pin(async move {
+            match event {
+                CharonEvent::KeyPress(..) | CharonEvent::KeyRelease(..) => Vec::new(),
+                other => vec![other],
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use evdev::KeyCode;
+    use maiko::ActorId;
+
+    use super::*;
+    use crate::domain::Mode;
+
+    fn meta() -> Meta {
+        Meta::new(ActorId::new("test".into()), None)
+    }
+
+    #[tokio::test]
+    async fn test_key_events_discarded() {
+        let mut proc = DiscardProcessor;
+        let press = CharonEvent::KeyPress(KeyCode::KEY_A, "tablet".into());
+        let release = CharonEvent::KeyRelease(KeyCode::KEY_A, "tablet".into());
+
+        assert!(proc.process(press, meta()).await.is_empty());
+        assert!(proc.process(release, meta()).await.is_empty());
+        assert_eq!(
+            vec![CharonEvent::ModeChange(Mode::InApp)],
+            proc.process(CharonEvent::ModeChange(Mode::InApp), meta())
+                .await
+        );
+    }
+}
@@ -1,42 +1,91 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-use crate::domain::{CharonEvent, traits::ProcessorFuture};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::domain::{ActorState, CharonEvent, Mode, traits::ProcessorFuture};
 use evdev::KeyCode;
 use maiko::Meta;
-use tracing::error;
+use tracing::{debug, error};
 
 use crate::domain::{HidKeyCode, KeyboardState, traits::Processor};
 
-#[derive(Default)]
+/// Turns key events into HID reports. All keys are released when the mode changes
+/// (whatever the source of the change), so a key pressed before the switch never
+/// leaks into the reports built after it.
+/// Processors of different keyboard routes share the keyboard state, so keys held
+/// on one keyboard stay in the reports built for keys of another one.
 pub struct KeyEventProcessor {
-    report: KeyboardState,
+    state: ActorState,
+    /// Mode the current report was built in
+    mode: Option<Mode>,
+    report: Arc<Mutex<KeyboardState>>,
     events: Vec<CharonEvent>,
+    remap: HashMap<HidKeyCode, HidKeyCode>,
 }
 
 impl KeyEventProcessor {
-    async fn handle_key_press(&mut self, key: &KeyCode) {
-        let key = match HidKeyCode::try_from(key) {
-            Ok(val) => val,
+    pub fn new(state: ActorState) -> Self {
+        Self::with_remap(state, Arc::default(), HashMap::new())
+    }
+
+    pub fn with_remap(
+        state: ActorState,
+        report: Arc<Mutex<KeyboardState>>,
+        remap: HashMap<HidKeyCode, HidKeyCode>,
+    ) -> Self {
+        Self {
+            state,
+            mode: None,
+            report,
+            events: Vec::new(),
+            remap,
+        }
+    }
+
+    fn hid_key(&self, key: &KeyCode) -> Option<HidKeyCode> {
+        match HidKeyCode::try_from(key) {
+            Ok(val) => Some(*self.remap.get(&val).unwrap_or(&val)),
             Err(e) => {
-                return error!("{e}");
+                error!("{e}");
+                None
             }
+        }
+    }
+
+    async fn handle_key_press(&mut self, key: &KeyCode) {
+        let Some(key) = self.hid_key(key) else {
+            return;
         };
-        self.report.update_on_press(key);
+        self.report().update_on_press(key);
         self.send_report().await;
     }
 
     async fn handle_key_release(&mut self, key: &KeyCode) {
-        let key = match HidKeyCode::try_from(key) {
-            Ok(val) => val,
-            Err(e) => {
-                return error!("{e}");
-            }
+        let Some(key) = self.hid_key(key) else {
+            return;
         };
-        self.report.update_on_release(key);
+        self.report().update_on_release(key);
         self.send_report().await;
     }
 
+    async fn sync_mode(&mut self) {
+        let mode = self.state.mode().await;
+        let changed = self.mode.replace(mode).is_some_and(|prev| prev != mode);
+        if changed && !self.report().is_empty() {
+            debug!("Mode changed to {mode}, releasing all keys");
+            self.report().reset();
+            self.send_report().await;
+        }
+    }
+
+    fn report(&self) -> MutexGuard<'_, KeyboardState> {
+        self.report.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     async fn send_report(&mut self) {
-        let report = self.report.to_report();
+        let report = self.report().to_report();
         let event = CharonEvent::HidReport(report);
         self.events.push(event);
     }
@@ -45,12 +94,57 @@ impl KeyEventProcessor {
 impl Processor for KeyEventProcessor {
     fn process<'a>(&'a mut self, event: CharonEvent, _meta: Meta) -> ProcessorFuture<'a> {
         Box::pin(async move {
+            self.sync_mode().await;
             match &event {
                 CharonEvent::KeyPress(key, _) => self.handle_key_press(key).await,
                 CharonEvent::KeyRelease(key, _) => self.handle_key_release(key).await,
                 _ => self.events.push(event),
             }
+            // i.e. release of all keys followed by release of the last one
+            self.events.dedup();
             std::mem::take(&mut self.events)
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use maiko::ActorId;
+
+    use super::*;
+    use crate::config::CharonConfig;
+
+    fn meta() -> Meta {
+        Meta::new(ActorId::new("test".into()), None)
+    }
+
+    #[tokio::test]
+    async fn test_release_on_mode_change() {
+        let mut state = ActorState::new(Mode::PassThrough, Arc::new(CharonConfig::default()));
+        let mut proc = KeyEventProcessor::new(state.clone());
+        let press = |key| CharonEvent::KeyPress(key, "main".into());
+        let release = |key| CharonEvent::KeyRelease(key, "main".into());
+
+        proc.process(press(KeyCode::KEY_LEFTSHIFT), meta()).await;
+        proc.process(press(KeyCode::KEY_A), meta()).await;
+        state.set_mode(Mode::InApp).await;
+
+        // the release of a stale key is reported before the event
+        assert_eq!(
+            vec![
+                CharonEvent::HidReport([0; 8]),
+                CharonEvent::HidReport([0, 0, 0x05, 0, 0, 0, 0, 0])
+            ],
+            proc.process(press(KeyCode::KEY_B), meta()).await
+        );
+        // the key released after the switch doesn't reappear
+        proc.process(release(KeyCode::KEY_B), meta()).await;
+        state.set_mode(Mode::PassThrough).await;
+        assert_eq!(
+            vec![CharonEvent::HidReport([0, 0, 0x06, 0, 0, 0, 0, 0])],
+            proc.process(press(KeyCode::KEY_C), meta()).await
+        );
+    }
+}
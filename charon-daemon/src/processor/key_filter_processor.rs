@@ -0,0 +1,282 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{collections::HashMap, time::Duration};
+
+use evdev::KeyCode;
+use maiko::Meta;
+use tokio::time::Instant;
+
+use crate::{
+    config::RepeatPolicy,
+    domain::{
+        CharonEvent, FilterReason,
+        traits::{Processor, ProcessorFuture},
+    },
+};
+
+/// State of a single key of a single keyboard
+#[derive(Debug, Default)]
+struct KeyState {
+    /// State reported by the device
+    pressed: bool,
+    /// State passed down the pipeline
+    reported: bool,
+    /// End of the debounce window, started by the last reported change
+    settles_at: Option<Instant>,
+    /// Time of the next generated autorepeat (see `RepeatPolicy::Regenerate`)
+    next_repeat: Option<Instant>,
+}
+
+impl KeyState {
+    fn deadline(&self) -> Option<Instant> {
+        match (self.settles_at, self.next_repeat) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.pressed && !self.reported && self.settles_at.is_none()
+    }
+}
+
+/// Filters key events of worn or cheap switches and controls autorepeat.
+///
+/// Debouncing is eager: the first change of a key's state is passed immediately and
+/// starts the debounce window, in which all events of the key are dropped. When the window
+/// ends, the state reported by the device is passed, if it differs from the one passed
+/// before (so a short tap is delayed, but never lost). Each dropped event is reported
+/// as `KeyFiltered`, so chattering switches can be spotted in telemetry.
+///
+/// Autorepeat is recognized as a key press of a key that is already pressed.
+pub struct KeyFilterProcessor {
+    debounce: Duration,
+    repeat: RepeatPolicy,
+    repeat_delay: Duration,
+    repeat_interval: Duration,
+    keys: HashMap<(String, KeyCode), KeyState>,
+}
+
+impl KeyFilterProcessor {
+    pub fn new(
+        debounce: Duration,
+        repeat: RepeatPolicy,
+        repeat_delay: Duration,
+        repeat_interval: Duration,
+    ) -> Self {
+        Self {
+            debounce,
+            repeat,
+            repeat_delay,
+            repeat_interval,
+            keys: HashMap::new(),
+        }
+    }
+
+    fn handle_key(
+        &mut self,
+        key: KeyCode,
+        keyboard: String,
+        pressed: bool,
+        now: Instant,
+    ) -> Vec<CharonEvent> {
+        let state = self.keys.entry((keyboard.clone(), key)).or_default();
+        if pressed && state.pressed {
+            return match self.repeat {
+                RepeatPolicy::Pass if state.reported => vec![CharonEvent::KeyPress(key, keyboard)],
+                RepeatPolicy::Pass => Vec::new(),
+                RepeatPolicy::Suppress => {
+                    vec![CharonEvent::KeyFiltered(
+                        key,
+                        keyboard,
+                        FilterReason::Repeat,
+                    )]
+                }
+                RepeatPolicy::Regenerate => Vec::new(),
+            };
+        }
+        state.pressed = pressed;
+        if state.settles_at.is_some_and(|settles_at| now < settles_at) {
+            return vec![CharonEvent::KeyFiltered(
+                key,
+                keyboard,
+                FilterReason::Chatter,
+            )];
+        }
+        let event = self.settle(&key, &keyboard, now);
+        event.into_iter().collect()
+    }
+
+    /// Passes the key's state reported by the device, if it changed since last time.
+    fn settle(&mut self, key: &KeyCode, keyboard: &str, now: Instant) -> Option<CharonEvent> {
+        let state = self.keys.get_mut(&(keyboard.to_string(), *key))?;
+        state.settles_at = None;
+        if state.pressed == state.reported {
+            return None;
+        }
+        state.reported = state.pressed;
+        state.settles_at = (!self.debounce.is_zero()).then(|| now + self.debounce);
+        state.next_repeat = (self.repeat == RepeatPolicy::Regenerate && state.reported)
+            .then(|| now + self.repeat_delay);
+        Some(match state.reported {
+            true => CharonEvent::KeyPress(*key, keyboard.into()),
+            false => CharonEvent::KeyRelease(*key, keyboard.into()),
+        })
+    }
+
+    fn handle_timeout(&mut self, now: Instant) -> Vec<CharonEvent> {
+        let mut events = Vec::new();
+        let expired: Vec<(String, KeyCode)> = self
+            .keys
+            .iter()
+            .filter(|(_, state)| state.deadline().is_some_and(|deadline| deadline <= now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for (keyboard, key) in expired {
+            let Some(state) = self.keys.get_mut(&(keyboard.clone(), key)) else {
+                continue;
+            };
+            if let Some(next_repeat) = state.next_repeat.filter(|time| *time <= now) {
+                state.next_repeat = Some(next_repeat + self.repeat_interval);
+                events.push(CharonEvent::KeyPress(key, keyboard.clone()));
+            }
+            if state.settles_at.is_some_and(|settles_at| settles_at <= now) {
+                events.extend(self.settle(&key, &keyboard, now));
+            }
+        }
+        self.keys.retain(|_, state| !state.is_idle());
+        events
+    }
+}
+
+impl Processor for KeyFilterProcessor {
+    fn process<'a>(&'a mut self, event: CharonEvent, _meta: Meta) -> ProcessorFuture<'a> {
+        Box::pin(async move {
+            let now = Instant::now();
+            match event {
+                CharonEvent::KeyPress(key, keyboard) => self.handle_key(key, keyboard, true, now),
+                CharonEvent::KeyRelease(key, keyboard) => {
+                    self.handle_key(key, keyboard, false, now)
+                }
+                event => vec![event],
+            }
+        })
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.keys.values().filter_map(KeyState::deadline).min()
+    }
+
+    fn on_timeout<'a>(&'a mut self) -> ProcessorFuture<'a> {
+        Box::pin(async move { self.handle_timeout(Instant::now()) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_millis(10);
+
+    fn processor(repeat: RepeatPolicy) -> KeyFilterProcessor {
+        KeyFilterProcessor::new(
+            DEBOUNCE,
+            repeat,
+            Duration::from_millis(300),
+            Duration::from_millis(50),
+        )
+    }
+
+    fn press() -> CharonEvent {
+        CharonEvent::KeyPress(KeyCode::KEY_A, "main".into())
+    }
+
+    fn release() -> CharonEvent {
+        CharonEvent::KeyRelease(KeyCode::KEY_A, "main".into())
+    }
+
+    fn chatter() -> CharonEvent {
+        CharonEvent::KeyFiltered(KeyCode::KEY_A, "main".into(), FilterReason::Chatter)
+    }
+
+    #[test]
+    fn test_debounce() {
+        let mut proc = processor(RepeatPolicy::Pass);
+        let t0 = Instant::now();
+        let ms = |ms| t0 + Duration::from_millis(ms);
+        let key = KeyCode::KEY_A;
+
+        assert_eq!(vec![press()], proc.handle_key(key, "main".into(), true, t0));
+        // each dropped event is reported
+        assert_eq!(
+            vec![chatter()],
+            proc.handle_key(key, "main".into(), false, ms(2))
+        );
+        assert_eq!(
+            vec![chatter()],
+            proc.handle_key(key, "main".into(), true, ms(3))
+        );
+        assert_eq!(Some(ms(10)), proc.deadline());
+        assert!(proc.handle_timeout(ms(10)).is_empty());
+        assert_eq!(None, proc.deadline());
+
+        // short tap: release is delayed until the window ends
+        assert_eq!(
+            vec![release()],
+            proc.handle_key(key, "main".into(), false, ms(50))
+        );
+        assert_eq!(
+            vec![press()],
+            proc.handle_key(key, "main".into(), true, ms(61))
+        );
+        assert_eq!(
+            vec![chatter()],
+            proc.handle_key(key, "main".into(), false, ms(65))
+        );
+        assert_eq!(vec![release()], proc.handle_timeout(ms(71)));
+        assert_eq!(Some(ms(81)), proc.deadline());
+        assert!(proc.handle_timeout(ms(81)).is_empty());
+        assert!(proc.keys.is_empty());
+    }
+
+    #[test]
+    fn test_repeat() {
+        let key = KeyCode::KEY_A;
+        let t0 = Instant::now();
+        let ms = |ms| t0 + Duration::from_millis(ms);
+
+        let mut proc = processor(RepeatPolicy::Pass);
+        proc.handle_key(key, "main".into(), true, t0);
+        assert_eq!(
+            vec![press()],
+            proc.handle_key(key, "main".into(), true, ms(500))
+        );
+
+        let mut proc = processor(RepeatPolicy::Suppress);
+        proc.handle_key(key, "main".into(), true, t0);
+        assert_eq!(
+            vec![CharonEvent::KeyFiltered(
+                key,
+                "main".into(),
+                FilterReason::Repeat
+            )],
+            proc.handle_key(key, "main".into(), true, ms(500))
+        );
+
+        let mut proc = processor(RepeatPolicy::Regenerate);
+        proc.handle_key(key, "main".into(), true, t0);
+        assert!(
+            proc.handle_key(key, "main".into(), true, ms(250))
+                .is_empty()
+        );
+        assert!(proc.handle_timeout(ms(10)).is_empty());
+        assert_eq!(Some(ms(300)), proc.deadline());
+        assert_eq!(vec![press()], proc.handle_timeout(ms(300)));
+        assert_eq!(Some(ms(350)), proc.deadline());
+        assert_eq!(vec![press()], proc.handle_timeout(ms(350)));
+        assert_eq!(
+            vec![release()],
+            proc.handle_key(key, "main".into(), false, ms(360))
+        );
+        assert_eq!(Some(ms(370)), proc.deadline());
+    }
+}
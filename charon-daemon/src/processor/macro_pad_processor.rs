@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::collections::HashMap;
+
+use crate::domain::{CharonEvent, HidKeyCode, traits::ProcessorFuture};
+use maiko::Meta;
+use tracing::debug;
+
+use crate::domain::traits::Processor;
+
+/// Handles keys of a keyboard in macro-pad role. The keys never reach the host:
+/// key presses with macro defined are turned into text to be typed,
+/// all other key presses are published as `MacroKey` events (i.e. for the client app).
+pub struct MacroPadProcessor {
+    macros: HashMap<HidKeyCode, String>,
+}
+
+impl MacroPadProcessor {
+    pub fn new(macros: HashMap<HidKeyCode, String>) -> Self {
+        Self { macros }
+    }
+}
+
+impl Processor for MacroPadProcessor {
+    fn process<'a>(&'a mut self, event: CharonEvent, _meta: Meta) -> ProcessorFuture<'a> {
+        Box::pin(async move {
+            match event {
+                CharonEvent::KeyPress(key, keyboard) => {
+                    let text = HidKeyCode::try_from(&key)
+                        .ok()
+                        .and_then(|hid| self.macros.get(&hid));
+                    match text {
+                        Some(text) => {
+                            debug!("Macro triggered by {key:?} on {keyboard}");
+                            vec![CharonEvent::SendText(text.clone(), None)]
+                        }
+                        None => vec![CharonEvent::MacroKey(key, keyboard)],
+                    }
+                }
+                CharonEvent::KeyRelease(..) => Vec::new(),
+                other => vec![other],
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use evdev::KeyCode;
+    use maiko::ActorId;
+
+    use super::*;
+    use crate::domain::Mode;
+
+    fn meta() -> Meta {
+        Meta::new(ActorId::new("test".into()), None)
+    }
+
+    #[tokio::test]
+    async fn test_macros() {
+        let macros = [(HidKeyCode::KEY_KP1, "hello".to_string())].into();
+        let mut proc = MacroPadProcessor::new(macros);
+        let press = |key| CharonEvent::KeyPress(key, "pad".into());
+
+        assert_eq!(
+            vec![CharonEvent::SendText("hello".into(), None)],
+            proc.process(press(KeyCode::KEY_KP1), meta()).await
+        );
+        assert_eq!(
+            vec![CharonEvent::MacroKey(KeyCode::KEY_KP2, "pad".into())],
+            proc.process(press(KeyCode::KEY_KP2), meta()).await
+        );
+        let release = CharonEvent::KeyRelease(KeyCode::KEY_KP1, "pad".into());
+        assert!(proc.process(release, meta()).await.is_empty());
+        assert_eq!(
+            vec![CharonEvent::ModeChange(Mode::InApp)],
+            proc.process(CharonEvent::ModeChange(Mode::InApp), meta())
+                .await
+        );
+    }
+}
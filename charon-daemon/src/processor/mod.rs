@@ -1,6 +1,20 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+mod discard_processor;
 mod key_event_processor;
+mod key_filter_processor;
+mod macro_pad_processor;
+mod processor_registry;
+mod script_processor;
+mod shortcut_matcher;
 mod system_shortcut_processor;
 
+pub use discard_processor::DiscardProcessor;
 pub use key_event_processor::KeyEventProcessor;
+pub use key_filter_processor::KeyFilterProcessor;
+pub use macro_pad_processor::MacroPadProcessor;
+pub use processor_registry::{
+    BoxedProcessor, ProcessorArgs, ProcessorFactory, ProcessorRegistry, routes,
+};
+pub use script_processor::ScriptProcessor;
+pub use shortcut_matcher::{MatchResult, ShortcutMatcher};
 pub use system_shortcut_processor::SystemShortcutProcessor;
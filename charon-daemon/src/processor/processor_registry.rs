@@ -0,0 +1,367 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    collections::{BTreeSet, HashMap},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use maiko::Context;
+
+use super::{
+    DiscardProcessor, KeyEventProcessor, KeyFilterProcessor, MacroPadProcessor, ScriptProcessor,
+    SystemShortcutProcessor,
+};
+use crate::{
+    actor::Pipeline,
+    config::{
+        CharonConfig, ProcessorConfig, RepeatPolicy,
+        keyboard::{KeyboardRole, KeyboardSettings},
+    },
+    domain::{ActorState, CharonEvent, HidKeyCode, KeyboardState, traits::Processor},
+    error::CharonError,
+};
+
+pub type BoxedProcessor = Box<dyn Processor + Send + Sync>;
+
+/// Everything a processor can be created from.
+pub struct ProcessorArgs<'a> {
+    pub ctx: &'a Context<CharonEvent>,
+    pub state: &'a ActorState,
+    pub config: &'a ProcessorConfig,
+    /// Alias and settings of the keyboard, if the processor is created for keyboard's route
+    pub keyboard: Option<(&'a str, &'a KeyboardSettings)>,
+    /// Keys held on all keyboards, shared by processors of all routes
+    pub report: &'a Arc<Mutex<KeyboardState>>,
+}
+
+pub type ProcessorFactory = fn(&ProcessorArgs) -> Result<BoxedProcessor, CharonError>;
+
+/// Processors available for the pipeline, by name (as used in `pipeline` config).
+pub struct ProcessorRegistry {
+    factories: HashMap<String, ProcessorFactory>,
+}
+
+impl Default for ProcessorRegistry {
+    /// Registry with all built-in processors
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register("key-filter", key_filter_processor);
+        registry.register("key-event", key_event_processor);
+        registry.register("system-shortcut", system_shortcut_processor);
+        registry.register("macro-pad", macro_pad_processor);
+        registry.register("discard", |_| Ok(Box::new(DiscardProcessor)));
+        registry.register("script", script_processor);
+        registry
+    }
+}
+
+impl ProcessorRegistry {
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: &str, factory: ProcessorFactory) {
+        self.factories.insert(name.into(), factory);
+    }
+
+    pub fn names(&self) -> BTreeSet<&str> {
+        self.factories.keys().map(String::as_str).collect()
+    }
+
+    /// Creates processors of the chain enabled for given keyboard (or for keyboards
+    /// with no dedicated route, if `keyboard` is `None`), in the configured order.
+    pub fn build_chain(
+        &self,
+        ctx: &Context<CharonEvent>,
+        state: &ActorState,
+        configs: &[ProcessorConfig],
+        keyboard: Option<(&str, &KeyboardSettings)>,
+        report: &Arc<Mutex<KeyboardState>>,
+    ) -> Result<Vec<BoxedProcessor>, CharonError> {
+        configs
+            .iter()
+            .filter(|config| config.is_enabled_for(keyboard.map(|(alias, _)| alias)))
+            .map(|config| {
+                let factory = self.factories.get(&config.processor).ok_or_else(|| {
+                    CharonError::InvalidProcessorConfig(format!(
+                        "unknown processor {}, available: {:?}",
+                        config.processor,
+                        self.names()
+                    ))
+                })?;
+                factory(&ProcessorArgs {
+                    ctx,
+                    state,
+                    config,
+                    keyboard,
+                    report,
+                })
+            })
+            .collect()
+    }
+
+    /// Creates pipeline with the default chain and routes of all keyboards
+    /// that need one (see `routes`). All chains build reports from the same keyboard state.
+    pub fn build_pipeline(
+        &self,
+        ctx: Context<CharonEvent>,
+        state: &ActorState,
+    ) -> Result<Pipeline, CharonError> {
+        let config = state.config();
+        let report = Arc::default();
+        let default_chain = self.build_chain(&ctx, state, &config.pipeline, None, &report)?;
+        let default_settings = KeyboardSettings::default();
+        let mut pipeline =
+            Pipeline::new(ctx.clone(), default_chain).with_tracer(state.tracer().clone());
+        for (alias, processors) in routes(config) {
+            let settings = config
+                .keyboard_settings
+                .get(&alias)
+                .unwrap_or(&default_settings);
+            let keyboard = Some((alias.as_str(), settings));
+            let chain = self.build_chain(&ctx, state, &processors, keyboard, &report)?;
+            pipeline = pipeline.with_route(alias, chain);
+        }
+        Ok(pipeline)
+    }
+}
+
+/// Pipelines of keyboards that need a dedicated route: keyboards with settings
+/// or mentioned in the pipeline config. Keyboard's own `pipeline` setting takes precedence
+/// over the role: pass-through keyboards use the global pipeline, macro-pads use `macro-pad`
+/// processor and in-app-only keyboards discard all keys.
+pub fn routes(config: &CharonConfig) -> Vec<(String, Vec<ProcessorConfig>)> {
+    let aliases: BTreeSet<&String> = config
+        .keyboard_settings
+        .keys()
+        .chain(config.pipeline.iter().flat_map(ProcessorConfig::aliases))
+        .collect();
+    aliases
+        .into_iter()
+        .map(|alias| {
+            let settings = config.keyboard_settings.get(alias);
+            let pipeline = match settings {
+                Some(KeyboardSettings {
+                    pipeline: Some(pipeline),
+                    ..
+                }) => pipeline.clone(),
+                Some(settings) if settings.role == KeyboardRole::MacroPad => {
+                    vec![ProcessorConfig::new("macro-pad")]
+                }
+                Some(settings) if settings.role == KeyboardRole::InAppOnly => {
+                    vec![ProcessorConfig::new("discard")]
+                }
+                _ => config.pipeline.clone(),
+            };
+            (alias.clone(), pipeline)
+        })
+        .collect()
+}
+
+/// Parses `key = value` option with `HidKeyCode` keys (i.e. `remap`, `macros`)
+fn key_map_option<T>(
+    config: &ProcessorConfig,
+    name: &str,
+    parse: fn(&str) -> Result<T, CharonError>,
+) -> Result<HashMap<HidKeyCode, T>, CharonError> {
+    config
+        .option::<HashMap<String, String>>(name)?
+        .unwrap_or_default()
+        .iter()
+        .map(|(key, value)| Ok((HidKeyCode::from_str(key)?, parse(value)?)))
+        .collect()
+}
+
+/// Options: `remap` - merged with keyboard's `remap` setting
+fn key_event_processor(args: &ProcessorArgs) -> Result<BoxedProcessor, CharonError> {
+    let mut remap = match args.keyboard {
+        Some((_, settings)) => settings.remap()?,
+        None => HashMap::new(),
+    };
+    remap.extend(key_map_option(args.config, "remap", HidKeyCode::from_str)?);
+    Ok(Box::new(KeyEventProcessor::with_remap(
+        args.state.clone(),
+        args.report.clone(),
+        remap,
+    )))
+}
+
+/// Options: `debounce` - debounce window in milliseconds (keyboard's `debounce` setting
+/// takes precedence), `repeat` - autorepeat policy, `repeat_delay` - delay of the generated
+/// autorepeat in milliseconds, `repeat_rate` - generated autorepeats per second
+fn key_filter_processor(args: &ProcessorArgs) -> Result<BoxedProcessor, CharonError> {
+    let debounce = args
+        .keyboard
+        .and_then(|(_, settings)| settings.debounce)
+        .map_or_else(
+            || args.config.option("debounce"),
+            |debounce| Ok(Some(debounce)),
+        )?
+        .unwrap_or(5);
+    let repeat_rate: u64 = args.config.option("repeat_rate")?.unwrap_or(25);
+    if !(1..=1000).contains(&repeat_rate) {
+        return Err(CharonError::InvalidProcessorConfig(
+            "key-filter.repeat_rate: must be between 1 and 1000".into(),
+        ));
+    }
+    Ok(Box::new(KeyFilterProcessor::new(
+        Duration::from_millis(debounce),
+        args.config
+            .option::<RepeatPolicy>("repeat")?
+            .unwrap_or_default(),
+        Duration::from_millis(args.config.option("repeat_delay")?.unwrap_or(500)),
+        Duration::from_millis(1000 / repeat_rate),
+    )))
+}
+
+fn system_shortcut_processor(args: &ProcessorArgs) -> Result<BoxedProcessor, CharonError> {
+    let processor = match args.keyboard {
+        Some((_, settings)) => {
+            SystemShortcutProcessor::for_keyboard(args.ctx.clone(), args.state.clone(), settings)
+        }
+        None => SystemShortcutProcessor::new(args.ctx.clone(), args.state.clone()),
+    };
+    Ok(Box::new(processor))
+}
+
+/// Options: `macros` - merged with keyboard's `macros` setting
+fn macro_pad_processor(args: &ProcessorArgs) -> Result<BoxedProcessor, CharonError> {
+    let mut macros = match args.keyboard {
+        Some((_, settings)) => settings.macros()?,
+        None => HashMap::new(),
+    };
+    macros.extend(key_map_option(args.config, "macros", |text| {
+        Ok(text.into())
+    })?);
+    Ok(Box::new(MacroPadProcessor::new(macros)))
+}
+
+/// Options: `script` - file name (in `scripts_dir`), `timeout` - time limit of a single
+/// invocation in milliseconds, `allowed_commands` - commands the script can run
+fn script_processor(args: &ProcessorArgs) -> Result<BoxedProcessor, CharonError> {
+    let config = args.state.config();
+    let script: String = args.config.option("script")?.ok_or_else(|| {
+        CharonError::InvalidProcessorConfig("script: missing `script` option".into())
+    })?;
+    let timeout = args
+        .config
+        .option("timeout")?
+        .unwrap_or(config.script_timeout);
+    let processor = ScriptProcessor::new(
+        args.ctx.clone(),
+        args.state.clone(),
+        &config.scripts_dir.join(script),
+        Duration::from_millis(timeout),
+        args.config.option("allowed_commands")?.unwrap_or_default(),
+    )?;
+    Ok(Box::new(processor))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use maiko::Supervisor;
+
+    use super::*;
+    use crate::domain::{Mode, Topic};
+
+    fn config() -> CharonConfig {
+        toml::from_str(
+            r#"
+            [[pipeline]]
+            processor = "key-event"
+            remap = { capslock = "esc" }
+
+            [[pipeline]]
+            processor = "system-shortcut"
+            disabled_for = ["laptop"]
+
+            [keyboard_settings.numpad]
+            role = "macro-pad"
+
+            [keyboard_settings.tablet]
+            pipeline = [{ processor = "discard" }]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_routes() {
+        let routes: HashMap<String, Vec<String>> = routes(&config())
+            .into_iter()
+            .map(|(alias, pipeline)| {
+                let names = pipeline
+                    .iter()
+                    .filter(|config| config.is_enabled_for(Some(&alias)))
+                    .map(|config| config.processor.clone())
+                    .collect();
+                (alias, names)
+            })
+            .collect();
+
+        assert_eq!(3, routes.len());
+        assert_eq!(vec!["key-event"], routes["laptop"]);
+        assert_eq!(vec!["macro-pad"], routes["numpad"]);
+        assert_eq!(vec!["discard"], routes["tablet"]);
+    }
+
+    #[tokio::test]
+    async fn test_build_pipeline() {
+        let mut config = config();
+        let registry = ProcessorRegistry::default();
+        let mut supervisor = Supervisor::<CharonEvent, Topic>::default();
+        let mut results = Vec::new();
+        for name in ["key-event", "unknown"] {
+            config.pipeline.push(ProcessorConfig::new(name));
+            let state = ActorState::new(Mode::PassThrough, Arc::new(config.clone()));
+            supervisor
+                .add_actor(
+                    name,
+                    |ctx| {
+                        let pipeline = registry.build_pipeline(ctx.clone(), &state);
+                        results.push(pipeline.is_ok());
+                        pipeline.unwrap_or_else(|_| Pipeline::new(ctx, Vec::new()))
+                    },
+                    [Topic::KeyInput],
+                )
+                .unwrap();
+        }
+        assert_eq!(vec![true, false], results);
+    }
+
+    #[tokio::test]
+    async fn test_repeat_rate_validated() {
+        let registry = ProcessorRegistry::default();
+        let mut supervisor = Supervisor::<CharonEvent, Topic>::default();
+        let mut results = Vec::new();
+        for rate in [0, 1, 1000, 1001] {
+            let config: CharonConfig = toml::from_str(&format!(
+                r#"
+                [[pipeline]]
+                processor = "key-filter"
+                repeat = "regenerate"
+                repeat_rate = {rate}
+                "#
+            ))
+            .unwrap();
+            let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+            supervisor
+                .add_actor(
+                    &format!("pipeline-{rate}"),
+                    |ctx| {
+                        let pipeline = registry.build_pipeline(ctx.clone(), &state);
+                        results.push(pipeline.is_ok());
+                        pipeline.unwrap_or_else(|_| Pipeline::new(ctx, Vec::new()))
+                    },
+                    [Topic::KeyInput],
+                )
+                .unwrap();
+        }
+        assert_eq!(vec![false, true, true, false], results);
+    }
+}
@@ -0,0 +1,385 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use evdev::KeyCode;
+use maiko::{Context, Meta};
+use rhai::{AST, Array, Dynamic, Engine, EvalAltResult, Scope};
+use tokio::{process::Command, time::Instant};
+use tracing::{debug, error, warn};
+
+use crate::{
+    config::TypingProfile,
+    domain::{
+        ActorState, CharonEvent, Mode,
+        traits::{Processor, ProcessorFuture},
+    },
+    error::CharonError,
+};
+
+/// Limit of operations of a single script invocation, regardless of the timeout
+const MAX_OPERATIONS: u64 = 1_000_000;
+/// Time limit of commands which output is typed (see `type_command_output`)
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Command which output should be typed, returned by the script
+#[derive(Debug, Clone)]
+struct TypeCommandOutput {
+    command: String,
+    args: Vec<String>,
+}
+
+/// Passes events through user's [Rhai](https://rhai.rs) script, which must define
+/// `fn process(event)` returning an array of events (or a single event).
+///
+/// Scripts are sandboxed (no access to files, network nor processes) and each invocation
+/// is aborted after `timeout`, in which case the event passes through unchanged.
+/// Commands from `allowed_commands` can be run with `type_command_output`.
+pub struct ScriptProcessor {
+    ctx: Context<CharonEvent>,
+    state: ActorState,
+    name: String,
+    engine: Engine,
+    ast: AST,
+    started: Arc<Mutex<Instant>>,
+    allowed_commands: Vec<String>,
+}
+
+impl ScriptProcessor {
+    pub fn new(
+        ctx: Context<CharonEvent>,
+        state: ActorState,
+        file: &Path,
+        timeout: Duration,
+        allowed_commands: Vec<String>,
+    ) -> Result<Self, CharonError> {
+        let started = Arc::new(Mutex::new(Instant::now()));
+        let engine = create_engine(started.clone(), timeout);
+        let ast = engine.compile_file(file.into()).map_err(|err| {
+            CharonError::InvalidProcessorConfig(format!("{}: {err}", file.display()))
+        })?;
+        if !ast.iter_functions().any(|f| f.name == "process") {
+            return Err(CharonError::InvalidProcessorConfig(format!(
+                "{}: missing fn process(event)",
+                file.display()
+            )));
+        }
+        Ok(Self {
+            ctx,
+            state,
+            name: file.display().to_string(),
+            engine,
+            ast,
+            started,
+            allowed_commands,
+        })
+    }
+
+    fn call(&self, event: &CharonEvent, mode: Mode) -> Result<Dynamic, Box<EvalAltResult>> {
+        if let Ok(mut started) = self.started.lock() {
+            *started = Instant::now();
+        }
+        let mut scope = Scope::new();
+        scope.push_constant("MODE", mode.to_string());
+        self.engine
+            .call_fn(&mut scope, &self.ast, "process", (event.clone(),))
+    }
+
+    /// Extracts events from script's result. Commands are started in the background.
+    fn collect(&self, result: Dynamic) -> Vec<CharonEvent> {
+        let items = match result.try_cast::<Array>() {
+            Some(items) => items,
+            None => return Vec::new(),
+        };
+        let mut events = Vec::new();
+        for item in items {
+            if item.is::<CharonEvent>() {
+                events.extend(item.try_cast::<CharonEvent>());
+            } else if let Some(command) = item.clone().try_cast::<TypeCommandOutput>() {
+                self.type_command_output(command);
+            } else {
+                warn!("{}: unexpected value returned: {item}", self.name);
+            }
+        }
+        events
+    }
+
+    fn type_command_output(&self, TypeCommandOutput { command, args }: TypeCommandOutput) {
+        if !self.allowed_commands.contains(&command) {
+            error!("{}: command {command} is not allowed", self.name);
+            return;
+        }
+        let ctx = self.ctx.clone();
+        tokio::spawn(async move {
+            let output =
+                tokio::time::timeout(COMMAND_TIMEOUT, Command::new(&command).args(&args).output())
+                    .await;
+            match output {
+                Ok(Ok(output)) if output.status.success() => {
+                    let text = String::from_utf8_lossy(&output.stdout)
+                        .trim_end()
+                        .to_string();
+                    if let Err(err) = ctx.send(CharonEvent::SendText(text, None)).await {
+                        error!("Couldn't send command output: {err}");
+                    }
+                }
+                Ok(Ok(output)) => warn!("Command {command} failed: {}", output.status),
+                Ok(Err(err)) => error!("Error while running command {command}: {err}"),
+                Err(_) => error!("Command {command} timed out"),
+            }
+        });
+    }
+}
+
+impl Processor for ScriptProcessor {
+    fn process<'a>(&'a mut self, event: CharonEvent, _meta: Meta) -> ProcessorFuture<'a> {
+        Box::pin(async move {
+            let mode = self.state.mode().await;
+            let events = match self.call(&event, mode) {
+                Ok(result) if result.is::<CharonEvent>() => {
+                    result.try_cast::<CharonEvent>().into_iter().collect()
+                }
+                Ok(result) => self.collect(result),
+                Err(err) => {
+                    warn!("{}: script failed, passing event through: {err}", self.name);
+                    return vec![event];
+                }
+            };
+            for event in &events {
+                if let CharonEvent::ModeChange(mode) = event {
+                    debug!("{}: switching mode to {mode}", self.name);
+                    self.state.set_mode(*mode).await;
+                }
+            }
+            events
+        })
+    }
+}
+
+fn create_engine(started: Arc<Mutex<Instant>>, timeout: Duration) -> Engine {
+    let mut engine = Engine::new();
+    // scripts can't load modules (i.e. files) from disk
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(1024);
+    engine.set_max_map_size(1024);
+    engine.on_progress(move |ops| {
+        let expired = ops % 256 == 0
+            && started
+                .lock()
+                .is_ok_and(|started| started.elapsed() > timeout);
+        expired.then(|| "Script timed out".into())
+    });
+    engine.on_print(|text| debug!("Script: {text}"));
+
+    engine
+        .register_type_with_name::<CharonEvent>("CharonEvent")
+        .register_fn("to_string", |event: &mut CharonEvent| format!("{event:?}"))
+        .register_get("kind", |event: &mut CharonEvent| {
+            format!("{event:?}")
+                .split(['(', ' '])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .register_get("key", |event: &mut CharonEvent| match event {
+            CharonEvent::KeyPress(key, _) | CharonEvent::KeyRelease(key, _) => format!("{key:?}"),
+            _ => String::new(),
+        })
+        .register_get("keyboard", |event: &mut CharonEvent| match event {
+            CharonEvent::KeyPress(_, keyboard) | CharonEvent::KeyRelease(_, keyboard) => {
+                keyboard.clone()
+            }
+            _ => String::new(),
+        })
+        .register_get("is_press", |event: &mut CharonEvent| {
+            matches!(event, CharonEvent::KeyPress(..))
+        })
+        .register_get("is_release", |event: &mut CharonEvent| {
+            matches!(event, CharonEvent::KeyRelease(..))
+        });
+
+    engine
+        .register_type_with_name::<TypeCommandOutput>("TypeCommandOutput")
+        .register_fn("key_press", |key: &str, keyboard: &str| {
+            key_event(key, keyboard, true)
+        })
+        .register_fn("key_release", |key: &str, keyboard: &str| {
+            key_event(key, keyboard, false)
+        })
+        .register_fn("send_text", |text: &str| {
+            CharonEvent::SendText(text.into(), None)
+        })
+        .register_fn("send_text", |text: &str, profile: &str| {
+            let profile = typing_profile(profile)?;
+            Ok::<_, Box<EvalAltResult>>(CharonEvent::SendText(text.into(), Some(profile)))
+        })
+        .register_fn("send_keys", |keys: &str| {
+            CharonEvent::SendKeys(keys.into(), None)
+        })
+        .register_fn("send_keys", |keys: &str, profile: &str| {
+            let profile = typing_profile(profile)?;
+            Ok::<_, Box<EvalAltResult>>(CharonEvent::SendKeys(keys.into(), Some(profile)))
+        })
+        .register_fn("set_mode", |mode: &str| match mode {
+            "pass-through" => Ok(CharonEvent::ModeChange(Mode::PassThrough)),
+            "in-app" => Ok(CharonEvent::ModeChange(Mode::InApp)),
+            _ => Err::<_, Box<EvalAltResult>>(format!("Unknown mode: {mode}").into()),
+        })
+        .register_fn("hid_report", |report: Array| {
+            let bytes: Vec<u8> = report
+                .into_iter()
+                .filter_map(|b| b.as_int().ok().and_then(|b| u8::try_from(b).ok()))
+                .collect();
+            let report: [u8; 8] = bytes
+                .try_into()
+                .map_err(|_| "HID report must have 8 bytes (0-255)")?;
+            Ok::<_, Box<EvalAltResult>>(CharonEvent::HidReport(report))
+        })
+        .register_fn("type_command_output", |command: &str, args: Array| {
+            TypeCommandOutput {
+                command: command.into(),
+                args: args.into_iter().map(|arg| arg.to_string()).collect(),
+            }
+        });
+    engine
+}
+
+fn typing_profile(profile: &str) -> Result<TypingProfile, Box<EvalAltResult>> {
+    TypingProfile::from_str(profile)
+        .map_err(|_| format!("Unknown typing profile: {profile}").into())
+}
+
+fn key_event(key: &str, keyboard: &str, pressed: bool) -> Result<CharonEvent, Box<EvalAltResult>> {
+    let key = KeyCode::from_str(key).map_err(|_| format!("Unknown key: {key}"))?;
+    Ok(match pressed {
+        true => CharonEvent::KeyPress(key, keyboard.into()),
+        false => CharonEvent::KeyRelease(key, keyboard.into()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn engine() -> Engine {
+        create_engine(
+            Arc::new(Mutex::new(Instant::now())),
+            Duration::from_secs(10),
+        )
+    }
+
+    fn run(engine: &Engine, script: &str, event: CharonEvent) -> Result<Dynamic, String> {
+        let ast = engine.compile(script).map_err(|e| e.to_string())?;
+        engine
+            .call_fn(&mut Scope::new(), &ast, "process", (event,))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_script_api() {
+        let engine = engine();
+        let script = r#"
+            fn process(event) {
+                if event.is_press && event.key == "KEY_CAPSLOCK" {
+                    return [key_press("KEY_ESC", event.keyboard), send_text(event.kind)];
+                }
+                [event]
+            }
+        "#;
+
+        let result = run(
+            &engine,
+            script,
+            CharonEvent::KeyPress(KeyCode::KEY_CAPSLOCK, "main".into()),
+        )
+        .unwrap();
+        let events: Vec<CharonEvent> = result
+            .try_cast::<Array>()
+            .unwrap()
+            .into_iter()
+            .filter_map(|e| e.try_cast())
+            .collect();
+        assert_eq!(
+            vec![
+                CharonEvent::KeyPress(KeyCode::KEY_ESC, "main".into()),
+                CharonEvent::SendText("KeyPress".into(), None)
+            ],
+            events
+        );
+
+        let result = run(
+            &engine,
+            "fn process(e) { hid_report([0, 0, 4]) }",
+            CharonEvent::Sleep,
+        );
+        assert!(result.is_err());
+        let result = run(
+            &engine,
+            "fn process(e) { set_mode(\"in-app\") }",
+            CharonEvent::Sleep,
+        );
+        assert_eq!(
+            Some(CharonEvent::ModeChange(Mode::InApp)),
+            result.unwrap().try_cast()
+        );
+        let result = run(
+            &engine,
+            r#"fn process(e) { send_keys("{Enter}", "safe") }"#,
+            CharonEvent::Sleep,
+        );
+        assert_eq!(
+            Some(CharonEvent::SendKeys(
+                "{Enter}".into(),
+                Some(TypingProfile::Safe)
+            )),
+            result.unwrap().try_cast()
+        );
+        let result = run(
+            &engine,
+            r#"fn process(e) { send_keys("{Enter}", "slow") }"#,
+            CharonEvent::Sleep,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let expired = create_engine(Arc::new(Mutex::new(Instant::now())), Duration::ZERO);
+        let result = run(&expired, "fn process(e) { loop {} }", CharonEvent::Sleep);
+        assert!(result.unwrap_err().contains("terminated"));
+
+        let engine = engine();
+        let result = run(&engine, "fn process(e) { loop {} }", CharonEvent::Sleep);
+        assert!(result.is_err());
+        let result = run(
+            &engine,
+            r#"fn process(e) { open_file("/etc/passwd") }"#,
+            CharonEvent::Sleep,
+        );
+        assert!(result.is_err());
+
+        let path = std::env::temp_dir().join(format!("charon-module-{}", std::process::id()));
+        std::fs::write(path.with_extension("rhai"), "export const X = 1;").unwrap();
+        let script = format!(
+            r#"fn process(e) {{ import "{}" as m; m::X }}"#,
+            path.display()
+        );
+        let result = run(&engine, &script, CharonEvent::Sleep);
+        std::fs::remove_file(path.with_extension("rhai")).unwrap();
+        assert!(result.unwrap_err().contains("not found"));
+
+        let result = run(
+            &engine,
+            "fn process(e) { let a = []; loop { a.push(1); } }",
+            CharonEvent::Sleep,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,392 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::domain::{KeySequence, KeyShortcut, KeyTrigger};
+
+/// Outcome of feeding a report to [`ShortcutMatcher`]: the action of a completed
+/// sequence (if any), and the reports that turned out not to be a part of any
+/// sequence, so they should be passed on to the host.
+#[derive(Debug, PartialEq)]
+pub struct MatchResult<T> {
+    pub action: Option<T>,
+    pub reports: Vec<[u8; 8]>,
+}
+
+impl<T> MatchResult<T> {
+    fn pending() -> Self {
+        Self {
+            action: None,
+            reports: Vec::new(),
+        }
+    }
+
+    fn matched(action: T) -> Self {
+        Self {
+            action: Some(action),
+            reports: Vec::new(),
+        }
+    }
+
+    fn pass(reports: Vec<[u8; 8]>) -> Self {
+        Self {
+            action: None,
+            reports,
+        }
+    }
+}
+
+enum Lookup<T> {
+    /// The steps match a sequence, and no longer sequence starts with them
+    Exact(T),
+    /// The steps are the beginning of a longer sequence
+    Prefix,
+    None,
+}
+
+/// Returns true if the report has no keys pressed other than modifiers.
+/// Such reports (i.e. releasing the key between sequence steps) never break a sequence.
+fn is_neutral(report: &[u8; 8]) -> bool {
+    report[2..].iter().all(|&key| key == 0)
+}
+
+/// Single step of a key sequence: HID report (as u64) and the way it's triggered
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Step {
+    report: u64,
+    trigger: KeyTrigger,
+}
+
+impl Step {
+    fn new(report: u64, trigger: KeyTrigger) -> Self {
+        Self { report, trigger }
+    }
+}
+
+impl From<&KeyShortcut> for Step {
+    fn from(shortcut: &KeyShortcut) -> Self {
+        Self::new(u64::from(shortcut), shortcut.trigger())
+    }
+}
+
+/// Matches the stream of HID reports against key sequences. While a sequence
+/// is in progress, the reports are held back. They are released once it turns out
+/// that the keys are not a part of any sequence, or when the next step doesn't come
+/// before the timeout. If a sequence is also the beginning of a longer one,
+/// it's triggered on the timeout.
+///
+/// Steps with `Tap` trigger complete when all the keys are released before
+/// the hold threshold, and steps with `Hold` trigger - when the keys are still
+/// pressed after it.
+pub struct ShortcutMatcher<T> {
+    bindings: Vec<(Vec<Step>, T)>,
+    timeout: Duration,
+    hold_threshold: Duration,
+    progress: Vec<Step>,
+    held: Vec<[u8; 8]>,
+    /// Keys pressed for a tap or hold step, with the time of the press
+    armed: Option<(u64, Instant)>,
+    deadline: Option<Instant>,
+}
+
+impl<T: Clone> ShortcutMatcher<T> {
+    pub fn new(timeout: Duration, hold_threshold: Duration) -> Self {
+        Self {
+            bindings: Vec::new(),
+            timeout,
+            hold_threshold,
+            progress: Vec::new(),
+            held: Vec::new(),
+            armed: None,
+            deadline: None,
+        }
+    }
+
+    /// Binds the sequence to the action. Returns the action previously bound
+    /// to the same sequence, if any.
+    pub fn bind(&mut self, sequence: &KeySequence, action: T) -> Option<T> {
+        let steps: Vec<Step> = sequence.steps().iter().map(Step::from).collect();
+        match self.bindings.iter_mut().find(|(seq, _)| *seq == steps) {
+            Some((_, prev)) => Some(std::mem::replace(prev, action)),
+            None => {
+                self.bindings.push((steps, action));
+                None
+            }
+        }
+    }
+
+    /// Removes all the bindings with actions matching the predicate.
+    pub fn unbind(&mut self, predicate: impl Fn(&T) -> bool) {
+        self.bindings.retain(|(_, action)| !predicate(action));
+    }
+
+    /// Time at which the sequence (or the tap/hold step) in progress expires.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn feed(&mut self, report: &[u8; 8], now: Instant) -> MatchResult<T> {
+        let value = u64::from_ne_bytes(*report);
+
+        if let Some((armed, since)) = self.armed.take() {
+            if value == 0 && now < since + self.hold_threshold {
+                self.held.push(*report);
+                return self.advance(Step::new(armed, KeyTrigger::Tap), now);
+            }
+            return self.abandon(report, now);
+        }
+
+        let press = Step::new(value, KeyTrigger::Press);
+        match self.lookup(&self.with_step(press)) {
+            Lookup::Exact(action) => {
+                self.reset();
+                return MatchResult::matched(action);
+            }
+            Lookup::Prefix => {
+                self.progress.push(press);
+                self.held.push(*report);
+                self.deadline = Some(now + self.timeout);
+                return MatchResult::pending();
+            }
+            Lookup::None => {}
+        }
+
+        if self.can_arm(value) {
+            self.held.push(*report);
+            self.armed = Some((value, now));
+            self.deadline = Some(now + self.hold_threshold);
+            return MatchResult::pending();
+        }
+
+        if self.progress.is_empty() {
+            return MatchResult::pass(vec![*report]);
+        }
+        if is_neutral(report) {
+            self.held.push(*report);
+            return MatchResult::pending();
+        }
+        self.abandon(report, now)
+    }
+
+    /// Completes or abandons the sequence in progress, if its deadline has passed.
+    pub fn expire(&mut self, now: Instant) -> MatchResult<T> {
+        if self.deadline.is_none_or(|deadline| deadline > now) {
+            return MatchResult::pending();
+        }
+        if let Some((armed, _)) = self.armed.take() {
+            return self.advance(Step::new(armed, KeyTrigger::Hold), now);
+        }
+        let action = self
+            .bindings
+            .iter()
+            .find(|(seq, _)| *seq == self.progress)
+            .map(|(_, action)| action.clone());
+        let reports = std::mem::take(&mut self.held);
+        self.reset();
+        match action {
+            Some(action) => MatchResult::matched(action),
+            None => MatchResult::pass(reports),
+        }
+    }
+
+    /// Moves the sequence forward with completed tap or hold step
+    fn advance(&mut self, step: Step, now: Instant) -> MatchResult<T> {
+        let steps = self.with_step(step);
+        match self.lookup(&steps) {
+            Lookup::Exact(action) => {
+                self.reset();
+                MatchResult::matched(action)
+            }
+            Lookup::Prefix => {
+                self.progress = steps;
+                self.deadline = Some(now + self.timeout);
+                MatchResult::pending()
+            }
+            Lookup::None => {
+                let reports = std::mem::take(&mut self.held);
+                self.reset();
+                MatchResult::pass(reports)
+            }
+        }
+    }
+
+    /// Releases the held reports and handles the report as a potential
+    /// beginning of a new sequence.
+    fn abandon(&mut self, report: &[u8; 8], now: Instant) -> MatchResult<T> {
+        let mut reports = std::mem::take(&mut self.held);
+        self.reset();
+        let mut result = self.feed(report, now);
+        reports.append(&mut result.reports);
+        result.reports = reports;
+        result
+    }
+
+    fn with_step(&self, step: Step) -> Vec<Step> {
+        let mut steps = self.progress.clone();
+        steps.push(step);
+        steps
+    }
+
+    /// Returns true if the keys can be the next tap or hold step of some sequence
+    fn can_arm(&self, report: u64) -> bool {
+        let idx = self.progress.len();
+        self.bindings.iter().any(|(seq, _)| {
+            seq.len() > idx
+                && seq.starts_with(&self.progress)
+                && seq[idx].report == report
+                && seq[idx].trigger != KeyTrigger::Press
+        })
+    }
+
+    fn lookup(&self, steps: &[Step]) -> Lookup<T> {
+        let mut exact = None;
+        for (seq, action) in &self.bindings {
+            if seq.len() > steps.len() && seq.starts_with(steps) {
+                return Lookup::Prefix;
+            }
+            if seq == steps {
+                exact = Some(action.clone());
+            }
+        }
+        exact.map_or(Lookup::None, Lookup::Exact)
+    }
+
+    fn reset(&mut self) {
+        self.progress.clear();
+        self.held.clear();
+        self.armed = None;
+        self.deadline = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const CTRL: [u8; 8] = [1, 0, 0, 0, 0, 0, 0, 0];
+    const CTRL_X: [u8; 8] = [1, 0, 0x1b, 0, 0, 0, 0, 0];
+    const CTRL_S: [u8; 8] = [1, 0, 0x16, 0, 0, 0, 0, 0];
+    const F7: [u8; 8] = [0, 0, 0x40, 0, 0, 0, 0, 0];
+    const KEY_E: [u8; 8] = [0, 0, 0x08, 0, 0, 0, 0, 0];
+    const KEY_A: [u8; 8] = [0, 0, 0x04, 0, 0, 0, 0, 0];
+    const LSHIFT: [u8; 8] = [2, 0, 0, 0, 0, 0, 0, 0];
+    const SHIFT_A: [u8; 8] = [2, 0, 0x04, 0, 0, 0, 0, 0];
+    const RALT: [u8; 8] = [0x40, 0, 0, 0, 0, 0, 0, 0];
+    const EMPTY: [u8; 8] = [0; 8];
+
+    fn matcher() -> ShortcutMatcher<&'static str> {
+        let mut matcher =
+            ShortcutMatcher::new(Duration::from_millis(500), Duration::from_millis(200));
+        let bindings = [
+            ("ctrl+x ctrl+s", "save"),
+            ("f7", "mode"),
+            ("f7 e", "edit"),
+            ("tap:lshift tap:lshift", "caps"),
+            ("hold:ralt", "layer"),
+        ];
+        for (seq, action) in bindings {
+            matcher.bind(&KeySequence::from_str(seq).unwrap(), action);
+        }
+        matcher
+    }
+
+    #[test]
+    fn test_sequence_with_modifiers() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        assert_eq!(MatchResult::pass(vec![CTRL]), matcher.feed(&CTRL, now));
+        assert_eq!(MatchResult::pending(), matcher.feed(&CTRL_X, now));
+        assert_eq!(MatchResult::pending(), matcher.feed(&CTRL, now));
+        assert_eq!(MatchResult::matched("save"), matcher.feed(&CTRL_S, now));
+        assert_eq!(None, matcher.deadline());
+    }
+
+    #[test]
+    fn test_broken_sequence_releases_keys() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        matcher.feed(&CTRL_X, now);
+        matcher.feed(&EMPTY, now);
+        assert_eq!(
+            MatchResult::pass(vec![CTRL_X, EMPTY, KEY_A]),
+            matcher.feed(&KEY_A, now)
+        );
+        assert_eq!(MatchResult::pass(vec![EMPTY]), matcher.feed(&EMPTY, now));
+    }
+
+    #[test]
+    fn test_leader_key() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        assert_eq!(MatchResult::pending(), matcher.feed(&F7, now));
+        assert_eq!(MatchResult::pending(), matcher.feed(&EMPTY, now));
+        assert_eq!(MatchResult::matched("edit"), matcher.feed(&KEY_E, now));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        matcher.feed(&F7, now);
+        assert_eq!(MatchResult::pending(), matcher.expire(now));
+        let later = now + Duration::from_millis(500);
+        assert_eq!(Some(later), matcher.deadline());
+        assert_eq!(MatchResult::matched("mode"), matcher.expire(later));
+
+        matcher.feed(&CTRL_X, now);
+        matcher.feed(&CTRL, now);
+        assert_eq!(MatchResult::pass(vec![CTRL_X, CTRL]), matcher.expire(later));
+        assert_eq!(None, matcher.deadline());
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        assert_eq!(MatchResult::pending(), matcher.feed(&LSHIFT, now));
+        assert_eq!(MatchResult::pending(), matcher.feed(&EMPTY, now));
+        assert_eq!(MatchResult::pending(), matcher.feed(&LSHIFT, now));
+        assert_eq!(MatchResult::matched("caps"), matcher.feed(&EMPTY, now));
+    }
+
+    #[test]
+    fn test_tap_interrupted_by_typing() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        matcher.feed(&LSHIFT, now);
+        assert_eq!(
+            MatchResult::pass(vec![LSHIFT, SHIFT_A]),
+            matcher.feed(&SHIFT_A, now)
+        );
+    }
+
+    #[test]
+    fn test_tap_too_long() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        matcher.feed(&LSHIFT, now);
+        let later = now + Duration::from_millis(200);
+        assert_eq!(MatchResult::pass(vec![LSHIFT]), matcher.expire(later));
+        assert_eq!(MatchResult::pass(vec![EMPTY]), matcher.feed(&EMPTY, later));
+    }
+
+    #[test]
+    fn test_hold() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+        assert_eq!(MatchResult::pending(), matcher.feed(&RALT, now));
+        let later = now + Duration::from_millis(200);
+        assert_eq!(Some(later), matcher.deadline());
+        assert_eq!(MatchResult::matched("layer"), matcher.expire(later));
+
+        // released before the threshold
+        matcher.feed(&RALT, now);
+        assert_eq!(
+            MatchResult::pass(vec![RALT, EMPTY]),
+            matcher.feed(&EMPTY, now)
+        );
+    }
+}
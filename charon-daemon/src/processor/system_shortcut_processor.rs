@@ -1,44 +1,149 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
+use std::time::Duration;
+
 use crate::domain::{CharonEvent, Mode, traits::ProcessorFuture};
 use maiko::{Context, Meta};
-use tracing::{debug, error, info};
+use tokio::{process::Command, time::Instant};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    domain::{ActorState, traits::Processor},
+    config::keyboard::KeyboardSettings,
+    domain::{Action, ActorState, traits::Processor},
+    processor::{MatchResult, ShortcutMatcher},
     util::system::wake_host_on_lan,
 };
 
+/// Matches HID reports against key bindings (see `CharonConfig::action_bindings`)
+/// and executes bound actions. Reports that trigger an action never reach the host,
+/// the ones held back as a possible beginning of a key sequence are passed on
+/// as soon as the sequence is broken or times out.
+/// New kinds of actions are added by extending `Action` and `execute` method.
 pub struct SystemShortcutProcessor {
     state: ActorState,
     events: Vec<CharonEvent>,
     ctx: Context<CharonEvent>,
+    matcher: ShortcutMatcher<Action>,
+    recording: Option<Vec<[u8; 8]>>,
 }
 
 impl SystemShortcutProcessor {
     pub fn new(ctx: Context<CharonEvent>, state: ActorState) -> Self {
+        Self::for_keyboard(ctx, state, &KeyboardSettings::default())
+    }
+
+    /// Creates processor with keyboard-specific shortcuts. The shortcuts
+    /// not defined in keyboard settings fall back to global ones.
+    pub fn for_keyboard(
+        ctx: Context<CharonEvent>,
+        state: ActorState,
+        settings: &KeyboardSettings,
+    ) -> Self {
+        let config = state.config();
+        let mut matcher = ShortcutMatcher::new(
+            Duration::from_millis(config.sequence_timeout),
+            Duration::from_millis(config.hold_threshold),
+        );
+        for binding in config.action_bindings() {
+            if let Some(prev) = matcher.bind(&binding.shortcut, binding.action) {
+                warn!("Shortcut {} was bound to {prev:?}", binding.shortcut);
+            }
+        }
+        for binding in settings.action_bindings() {
+            matcher.unbind(|action| *action == binding.action);
+            matcher.bind(&binding.shortcut, binding.action);
+        }
         Self {
             ctx,
             state,
             events: Vec::new(),
+            matcher,
+            recording: None,
         }
     }
 
-    async fn handle_report(&mut self, report: &[u8; 8]) -> bool {
-        let num: u64 = u64::from_ne_bytes(*report);
-        let config = self.state.config();
+    async fn handle_report(&mut self, report: &[u8; 8]) {
+        let result = self.matcher.feed(report, Instant::now());
+        self.handle_match(result).await;
+    }
+
+    async fn handle_match(&mut self, result: MatchResult<Action>) {
+        if !result.reports.is_empty() && self.state.mode().await == Mode::PassThrough {
+            if let Some(recording) = &mut self.recording {
+                recording.extend_from_slice(&result.reports);
+            }
+            self.events
+                .extend(result.reports.into_iter().map(CharonEvent::HidReport));
+        }
+        if let Some(action) = result.action {
+            self.execute(action).await;
+            self.reset_hid();
+        }
+    }
+
+    async fn execute(&mut self, action: Action) {
+        debug!("Executing action: {action:?}");
+        match action {
+            Action::Quit => self.ctx.stop(),
+            Action::ToggleMode => self.toggle_mode().await,
+            Action::AwakeHost => self.wake_up_host(),
+            Action::RunCommand { command, args } => Self::run_command(command, args),
+            Action::SendText { text, profile } => {
+                self.events.push(CharonEvent::SendText(text, profile))
+            }
+            Action::SendKeys { keys, profile } => {
+                self.events.push(CharonEvent::SendKeys(keys, profile))
+            }
+            Action::SwitchApp { app } => self.events.push(CharonEvent::SwitchApp(app)),
+            Action::HostProfile { profile } => self.switch_host_profile(profile),
+            Action::MacroRecord => self.toggle_macro_recording(),
+            Action::MacroPlay => self.events.push(CharonEvent::PlayMacro),
+            Action::ToggleTelemetry => self.toggle_telemetry(),
+        }
+    }
 
-        if num == u64::from(&config.quit_shortcut) {
-            self.ctx.stop();
-        } else if num == u64::from(&config.toggle_mode_shortcut) {
-            self.toggle_mode().await;
-        } else if num == u64::from(&config.awake_host_shortcut) {
-            self.wake_up_host();
+    fn run_command(command: String, args: Vec<String>) {
+        tokio::spawn(async move {
+            match Command::new(&command).args(&args).status().await {
+                Ok(status) if status.success() => debug!("Command {command} finished"),
+                Ok(status) => warn!("Command {command} failed: {status}"),
+                Err(err) => error!("Error while running command {command}: {err}"),
+            }
+        });
+    }
+
+    fn switch_host_profile(&mut self, profile: String) {
+        if self.state.config().host_profiles.contains_key(&profile) {
+            info!("Switching host profile to {profile}");
+            self.events.push(CharonEvent::HostProfileChange(profile));
         } else {
-            return self.state.mode().await == Mode::PassThrough;
+            error!("Unknown host profile: {profile}");
         }
+    }
 
-        self.reset_hid();
-        false
+    fn toggle_macro_recording(&mut self) {
+        match self.recording.take() {
+            Some(reports) => {
+                info!("Macro recording stopped ({} reports)", reports.len());
+                self.events.push(CharonEvent::MacroRecording(false));
+                self.events.push(CharonEvent::MacroRecorded(reports));
+            }
+            None => {
+                info!("Macro recording started");
+                self.recording = Some(Vec::new());
+                self.events.push(CharonEvent::MacroRecording(true));
+            }
+        }
+    }
+
+    fn toggle_telemetry(&mut self) {
+        if !self.state.config().enable_telemetry {
+            warn!("Telemetry is not enabled in configuration");
+            return;
+        }
+        let enabled = !self.state.telemetry_enabled();
+        info!("Telemetry {}", if enabled { "resumed" } else { "paused" });
+        self.state.set_telemetry_enabled(enabled);
+        self.events.push(CharonEvent::TelemetryEnabled(enabled));
     }
 
     async fn toggle_mode(&mut self) {
@@ -70,14 +175,22 @@ impl Processor for SystemShortcutProcessor {
     fn process<'a>(&'a mut self, event: CharonEvent, _meta: Meta) -> ProcessorFuture<'a> {
         Box::pin(async move {
             match &event {
-                CharonEvent::HidReport(report) => {
-                    if self.handle_report(report).await {
-                        self.events.push(event);
-                    }
-                }
+                CharonEvent::HidReport(report) => self.handle_report(report).await,
                 _ => self.events.push(event),
             }
             std::mem::take(&mut self.events)
         })
     }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.matcher.deadline()
+    }
+
+    fn on_timeout<'a>(&'a mut self) -> ProcessorFuture<'a> {
+        Box::pin(async move {
+            let result = self.matcher.expire(Instant::now());
+            self.handle_match(result).await;
+            std::mem::take(&mut self.events)
+        })
+    }
 }
@@ -1,17 +1,47 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
-//! Input device discovery utilities. Currently unused but needed for future multi-keyboard support.
+//! Input device discovery utilities.
 #![allow(dead_code)]
 
 use std::{
+    collections::HashMap,
     fs::{self, canonicalize, exists},
     path::PathBuf,
 };
 
+use evdev::{BusType, Device, KeyCode};
 use tracing::{debug, error, info};
 
 use crate::config::InputConfig;
 
-const BY_ID: &str = "/dev/input/by-id/";
+pub(crate) const BY_ID: &str = "/dev/input/by-id/";
+const INPUT_DIR: &str = "/dev/input/";
+
+/// Vendor and product id of Charon's own USB gadget (see docs/rp5-setup.md).
+/// The gadget must never be treated as an input keyboard, as that would create a loop.
+const GADGET_VENDOR_ID: u16 = 0x1d6b;
+const GADGET_PRODUCT_ID: u16 = 0x0104;
+
+/// Keys that every real keyboard must support. It filters out devices like power buttons,
+/// media remotes or mice, that also declare key capabilities.
+const REQUIRED_KEYS: [KeyCode; 5] = [
+    KeyCode::KEY_A,
+    KeyCode::KEY_Z,
+    KeyCode::KEY_SPACE,
+    KeyCode::KEY_ENTER,
+    KeyCode::KEY_LEFTSHIFT,
+];
+
+/// Keyboard found by [`discover_keyboards`]
+#[derive(Debug, Clone)]
+pub(crate) struct DiscoveredKeyboard {
+    /// Stable alias, derived from vendor id, product id and serial number
+    pub alias: String,
+
+    /// Input config pointing to the device (by-id name if available, path otherwise).
+    /// A path is matched by the name of the event node, so a keyboard plugged back in
+    /// is recognized only if it gets the same node again.
+    pub input: InputConfig,
+}
 
 pub(crate) fn find_input_device(conf: &InputConfig) -> Option<PathBuf> {
     let maybe_device = match conf {
@@ -31,6 +61,20 @@ pub(crate) fn find_input_device(conf: &InputConfig) -> Option<PathBuf> {
     maybe_device
 }
 
+/// Checks whether device of given name (as in /dev/input/by-id/, or event node name)
+/// is described by the input configuration. In auto mode the event node is opened
+/// to check its capabilities (same as in [`discover_keyboards`]), so it works only
+/// for devices still present.
+pub(crate) fn is_configured_device(conf: &InputConfig, device_name: &str) -> bool {
+    match conf {
+        InputConfig::Auto => is_event_node(device_name) && is_keyboard_device(device_name),
+        InputConfig::Path(path) => path.file_name().is_some_and(|name| name == device_name),
+        InputConfig::Name(name) => name == device_name,
+        InputConfig::OneOf(names) => names.iter().any(|name| name == device_name),
+        InputConfig::Use(_) => false,
+    }
+}
+
 fn normalize(path: &PathBuf) -> Option<PathBuf> {
     match exists(path) {
         Ok(true) => canonicalize(path).ok(),
@@ -45,14 +89,138 @@ fn from_name(name: &str) -> Option<PathBuf> {
 }
 
 pub fn find_keyboard_device() -> Option<PathBuf> {
-    let dir = "/dev/input/by-id";
-    for entry in fs::read_dir(dir).ok()? {
-        let entry = entry.ok()?;
-        let name = entry.file_name();
-        if name.to_string_lossy().ends_with("-event-kbd") {
-            let full_path = canonicalize(entry.path()).ok()?;
-            return Some(full_path);
-        }
+    discover_keyboards()
+        .into_iter()
+        .find_map(|keyboard| find_input_device(&keyboard.input))
+}
+
+/// Finds all real keyboards connected to the system, by checking key capabilities
+/// of every input device. Virtual devices and Charon's own gadget are excluded.
+/// If the same keyboard exposes more than one device with keyboard capabilities,
+/// the aliases get numeric suffixes (ordered by physical path).
+pub(crate) fn discover_keyboards() -> Vec<DiscoveredKeyboard> {
+    let by_id = by_id_names();
+    let mut found: Vec<(String, String, PathBuf)> = evdev::enumerate()
+        .filter(|(_, device)| is_keyboard(device) && !is_excluded(device))
+        .map(|(path, device)| {
+            let id = device.input_id();
+            let alias = keyboard_alias(id.vendor(), id.product(), device.unique_name());
+            let phys = device.physical_path().unwrap_or_default().to_string();
+            (alias, phys, path)
+        })
+        .collect();
+    found.sort();
+
+    let mut counters: HashMap<String, usize> = HashMap::new();
+    found
+        .into_iter()
+        .map(|(alias, _, path)| {
+            let count = counters.entry(alias.clone()).or_default();
+            *count += 1;
+            let alias = if *count > 1 {
+                format!("{alias}-{count}")
+            } else {
+                alias
+            };
+            let input = match by_id.get(&path) {
+                Some(name) => InputConfig::Name(name.clone()),
+                None => InputConfig::Path(path),
+            };
+            info!("Discovered keyboard {alias}: {input:?}");
+            DiscoveredKeyboard { alias, input }
+        })
+        .collect()
+}
+
+/// Checks whether it's a name of event node in /dev/input (i.e. `event3`)
+pub(crate) fn is_event_node(device_name: &str) -> bool {
+    device_name
+        .strip_prefix("event")
+        .is_some_and(|num| !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Checks capabilities of the event node of given name
+fn is_keyboard_device(device_name: &str) -> bool {
+    let path: PathBuf = [INPUT_DIR, device_name].iter().collect();
+    Device::open(path).is_ok_and(|device| is_keyboard(&device) && !is_excluded(&device))
+}
+
+fn is_keyboard(device: &Device) -> bool {
+    device
+        .supported_keys()
+        .is_some_and(|keys| REQUIRED_KEYS.iter().all(|key| keys.contains(*key)))
+}
+
+fn is_excluded(device: &Device) -> bool {
+    let id = device.input_id();
+    id.bus_type() == BusType::BUS_VIRTUAL
+        || (id.vendor() == GADGET_VENDOR_ID && id.product() == GADGET_PRODUCT_ID)
+}
+
+fn keyboard_alias(vendor_id: u16, product_id: u16, serial: Option<&str>) -> String {
+    let mut alias = format!("{vendor_id:04x}-{product_id:04x}");
+    let serial: String = serial
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if !serial.is_empty() {
+        alias.push('-');
+        alias.push_str(&serial);
+    }
+    alias
+}
+
+/// Maps device paths (i.e. /dev/input/event3) to their names in /dev/input/by-id/
+fn by_id_names() -> HashMap<PathBuf, String> {
+    let Ok(entries) = fs::read_dir(BY_ID) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = canonicalize(entry.path()).ok()?;
+            Some((path, entry.file_name().to_string_lossy().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keyboard_alias() {
+        assert_eq!("3434-01a1", keyboard_alias(0x3434, 0x01A1, None));
+        assert_eq!("3434-01a1", keyboard_alias(0x3434, 0x01A1, Some("")));
+        assert_eq!(
+            "046d-c52b-AB12CD",
+            keyboard_alias(0x046D, 0xC52B, Some("AB:12:CD"))
+        );
+    }
+
+    #[test]
+    fn test_is_configured_device() {
+        let path = InputConfig::Path("/dev/input/event5".into());
+        assert!(is_configured_device(&path, "event5"));
+        assert!(!is_configured_device(&path, "event15"));
+
+        let name = InputConfig::Name("usb-Keychron_K2-event-kbd".into());
+        assert!(is_configured_device(&name, "usb-Keychron_K2-event-kbd"));
+        assert!(!is_configured_device(&name, "event5"));
+
+        assert!(!is_configured_device(
+            &InputConfig::Auto,
+            "usb-Keychron_K2-event-kbd"
+        ));
+    }
+
+    #[test]
+    fn test_is_event_node() {
+        assert!(is_event_node("event0"));
+        assert!(is_event_node("event12"));
+        assert!(!is_event_node("event"));
+        assert!(!is_event_node("mouse0"));
+        assert!(!is_event_node("usb-Keychron_K2-event-kbd"));
     }
-    None
 }
@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! Keyboards attached and detached by `DeviceMonitor`, as devices are plugged in and out.
+use std::sync::{Arc, Mutex};
+
+use maiko::{ActorId, Envelope, Supervisor, testing::Harness};
+use tokio::time::{Duration, sleep};
+
+use charond::{
+    actor::DeviceMonitor,
+    adapter::mock::{DeviceWatcherMock, DeviceWatcherState},
+    config::{CharonConfig, InputConfig},
+    domain::{ActorState, CharonEvent, Mode, Topic as CharonTopic},
+};
+
+/// A no-op actor that subscribes to events for test observation.
+struct Sink;
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, _: &Envelope<Self::Event>) -> maiko::Result<()> {
+        Ok(())
+    }
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    test: Harness<CharonEvent, CharonTopic>,
+    devices: Arc<Mutex<DeviceWatcherState>>,
+    sink: ActorId,
+}
+
+/// Monitors two keyboards: `laptop` by name, and `KeyScanner` matching any of two devices
+/// (like the auto-detected keyboard, when no keyboard was discovered at startup)
+async fn setup(present: &[&str]) -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let state = ActorState::new(Mode::PassThrough, Arc::new(CharonConfig::default()));
+    let keyboards = vec![
+        ("laptop".into(), InputConfig::Name("laptop-kbd".into())),
+        (
+            "KeyScanner".into(),
+            InputConfig::OneOf(vec!["event3".into(), "event5".into()]),
+        ),
+    ];
+
+    let mut sup = Supervisor::default();
+    let test = Harness::new(&mut sup).await;
+
+    let watcher = DeviceWatcherMock::default();
+    let devices = watcher.state().clone();
+    devices.lock().unwrap().devices = present.iter().map(|name| name.to_string()).collect();
+    sup.add_actor(
+        "DeviceMonitor",
+        |ctx| DeviceMonitor::new(ctx, state.clone(), watcher, keyboards),
+        [System],
+    )?;
+    let sink = sup.add_actor("Sink", |_ctx| Sink, [Keyboard])?;
+
+    test.start_recording().await;
+    sup.start().await?;
+    Ok(TestContext {
+        sup,
+        test,
+        devices,
+        sink,
+    })
+}
+
+impl TestContext {
+    async fn attach(&self, name: &str) {
+        self.devices.lock().unwrap().simulate_attach(name);
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    async fn detach(&self, name: &str) {
+        self.devices.lock().unwrap().simulate_detach(name);
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    /// Stops the test, returns events received by the sink
+    async fn stop(&mut self) -> eyre::Result<Vec<CharonEvent>> {
+        self.test.stop_recording().await;
+        self.sup.stop().await?;
+        Ok(self
+            .test
+            .events()
+            .received_by(&self.sink)
+            .collect()
+            .iter()
+            .map(|entry| entry.payload().clone())
+            .collect())
+    }
+}
+
+fn attached(alias: &str) -> CharonEvent {
+    CharonEvent::KeyboardAttached(alias.into())
+}
+
+fn detached(alias: &str) -> CharonEvent {
+    CharonEvent::KeyboardDetached(alias.into())
+}
+
+#[tokio::test]
+async fn test_keyboard_plugged_in_and_out() -> eyre::Result<()> {
+    let mut ctx = setup(&[]).await?;
+    ctx.attach("mouse").await;
+    ctx.attach("laptop-kbd").await;
+    ctx.detach("mouse").await;
+    ctx.detach("laptop-kbd").await;
+    let events = ctx.stop().await?;
+
+    assert_eq!(vec![attached("laptop"), detached("laptop")], events);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_present_devices_attached_at_start() -> eyre::Result<()> {
+    let mut ctx = setup(&["laptop-kbd", "event3"]).await?;
+    sleep(Duration::from_millis(10)).await;
+    let events = ctx.stop().await?;
+
+    assert_eq!(vec![attached("laptop"), attached("KeyScanner")], events);
+    Ok(())
+}
+
+/// Unplugging a second keyboard matching the alias doesn't detach the first one
+#[tokio::test]
+async fn test_second_device_of_alias_ignored() -> eyre::Result<()> {
+    let mut ctx = setup(&[]).await?;
+    ctx.attach("event3").await;
+    ctx.attach("event5").await;
+    ctx.detach("event5").await;
+    let events = ctx.stop().await?;
+
+    assert_eq!(vec![attached("KeyScanner")], events);
+    Ok(())
+}
+
+/// The device still present takes over, when the attached one is unplugged
+#[tokio::test]
+async fn test_second_device_of_alias_takes_over() -> eyre::Result<()> {
+    let mut ctx = setup(&[]).await?;
+    ctx.attach("event3").await;
+    ctx.attach("event5").await;
+    ctx.detach("event3").await;
+    ctx.detach("event5").await;
+    let events = ctx.stop().await?;
+
+    assert_eq!(
+        vec![
+            attached("KeyScanner"),
+            detached("KeyScanner"),
+            attached("KeyScanner"),
+            detached("KeyScanner"),
+        ],
+        events
+    );
+    Ok(())
+}
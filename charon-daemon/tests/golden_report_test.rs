@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! Golden-file tests of HID reports that reach the host.
+//!
+//! Each case is a script in `tests/golden/<name>.script`, with one step per line:
+//! `press <KEY>`, `release <KEY>`, `tap <KEY>` (evdev key names) or `text <string>`
+//! (typed by `Typist`, `text-<profile> <string>` with given `TypingProfile`)
+//! or `keys <string>` (text with named keys, see `TypingStep`).
+//! Lines starting with `#` are comments. The reports written to the (mock) HID device
+//! are compared with `tests/golden/<name>.reports`.
+//! Run with `UPDATE_GOLDEN=1` to (re)generate the expectations.
+use std::{
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        Arc, Mutex as StdMutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use evdev::KeyCode;
+use maiko::{ActorId, Envelope, Supervisor, testing::Harness};
+use tokio::{
+    sync::Mutex,
+    time::{Duration, Instant, sleep},
+};
+
+use charond::{
+    actor::{KeyScanner, KeyWriter, Typist},
+    adapter::{
+        KeymapLoaderYaml,
+        mock::{EventDeviceMock, EventDeviceState, HIDDeviceMock, HIDDeviceState},
+    },
+    config::{CharonConfig, TypingProfile},
+    domain::{ActorState, CharonEvent, Mode, Topic as CharonTopic},
+    port::KeymapLoader,
+    processor::ProcessorRegistry,
+};
+
+/// Time without new reports after which a step is considered complete
+const QUIET_TIME: Duration = Duration::from_millis(30);
+
+/// Time to wait for a typing job to end
+const TYPING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sender of `SendText` events, counting typing jobs that ended.
+struct Sink {
+    jobs_ended: Arc<AtomicUsize>,
+}
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        if matches!(
+            envelope.event(),
+            CharonEvent::TextSent(_) | CharonEvent::TypingFailed(..)
+        ) {
+            self.jobs_ended.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+enum Step {
+    Press(KeyCode),
+    Release(KeyCode),
+    Text(String, Option<TypingProfile>),
+    Keys(String),
+}
+
+fn parse_script(script: &str) -> Vec<Step> {
+    let key = |name: &str| KeyCode::from_str(name).unwrap_or_else(|_| panic!("Unknown key {name}"));
+    let mut steps = Vec::new();
+    for line in script.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "press" => steps.push(Step::Press(key(arg))),
+            "release" => steps.push(Step::Release(key(arg))),
+            "tap" => steps.extend([Step::Press(key(arg)), Step::Release(key(arg))]),
+            "text" => steps.push(Step::Text(arg.into(), None)),
+            "keys" => steps.push(Step::Keys(arg.into())),
+            _ if command.starts_with("text-") => {
+                let profile = TypingProfile::from_str(&command[5..])
+                    .unwrap_or_else(|_| panic!("Unknown typing profile: {command}"));
+                steps.push(Step::Text(arg.into(), Some(profile)))
+            }
+            _ => panic!("Unknown step: {line}"),
+        }
+    }
+    steps
+}
+
+fn format_reports(reports: &[[u8; 8]]) -> String {
+    reports
+        .iter()
+        .map(|report| {
+            let bytes: Vec<String> = report.iter().map(|b| format!("{b:02x}")).collect();
+            bytes.join(" ") + "\n"
+        })
+        .collect()
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    test: Harness<CharonEvent, CharonTopic>,
+    keyboard: Arc<Mutex<EventDeviceState>>,
+    host: Arc<StdMutex<HIDDeviceState>>,
+    sink: ActorId,
+    jobs_ended: Arc<AtomicUsize>,
+}
+
+async fn setup() -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let config = CharonConfig {
+        typing_interval: 1,
+        ..Default::default()
+    };
+    let loader = KeymapLoaderYaml::new(&config.keymaps_dir);
+    let keymap = loader.load_keymap(&config.host_keymap).await?;
+    let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+
+    let mut sup = Supervisor::default();
+    let test = Harness::new(&mut sup).await;
+
+    let input = EventDeviceMock::default();
+    let keyboard = input.state().clone();
+    sup.add_actor(
+        "KeyScanner",
+        |ctx| KeyScanner::new(ctx, state.clone(), input, "test-keyboard".into()),
+        [System, Keyboard],
+    )?;
+
+    let registry = ProcessorRegistry::default();
+    sup.add_actor(
+        "KeyEventPipeline",
+        |ctx| {
+            registry
+                .build_pipeline(ctx, &state)
+                .expect("Couldn't build the pipeline")
+        },
+        [System, KeyInput],
+    )?;
+
+    let output = HIDDeviceMock::default();
+    let host = output.state().clone();
+    sup.add_actor(
+        "KeyWriter",
+        |ctx| KeyWriter::new(ctx, state.clone(), output),
+        [System, KeyOutput],
+    )?;
+
+    sup.add_actor(
+        "Typist",
+        |ctx| Typist::new(ctx, state.clone(), keymap, loader),
+        [System, TextInput],
+    )?;
+
+    let jobs_ended = Arc::new(AtomicUsize::new(0));
+    let sink = sup.add_actor(
+        "Sink",
+        |_ctx| Sink {
+            jobs_ended: jobs_ended.clone(),
+        },
+        [Monitoring],
+    )?;
+
+    Ok(TestContext {
+        sup,
+        test,
+        keyboard,
+        host,
+        sink,
+        jobs_ended,
+    })
+}
+
+impl TestContext {
+    fn report_count(&self) -> usize {
+        self.host.lock().unwrap().reports.len()
+    }
+
+    /// Waits until no new reports are written for `QUIET_TIME`
+    async fn settle(&self) {
+        EventDeviceState::drain(&self.keyboard).await;
+        let mut count = self.report_count();
+        let mut changed = Instant::now();
+        while changed.elapsed() < QUIET_TIME {
+            sleep(Duration::from_millis(2)).await;
+            let current = self.report_count();
+            if current != count {
+                count = current;
+                changed = Instant::now();
+            }
+        }
+    }
+
+    /// Waits until the typing job sent as the given (0-based) one ends.
+    /// Pauses of some typing profiles are longer than `QUIET_TIME`.
+    async fn wait_for_job(&self, job: usize) {
+        let deadline = Instant::now() + TYPING_TIMEOUT;
+        while self.jobs_ended.load(Ordering::Relaxed) <= job && Instant::now() < deadline {
+            sleep(Duration::from_millis(2)).await;
+        }
+    }
+
+    async fn run(&self, steps: &[Step]) -> eyre::Result<()> {
+        let mut jobs = 0;
+        for step in steps {
+            match step {
+                Step::Press(key) => self.keyboard.lock().await.simulate_key_press(*key),
+                Step::Release(key) => self.keyboard.lock().await.simulate_key_release(*key),
+                Step::Keys(keys) => {
+                    self.test
+                        .send_as(&self.sink, CharonEvent::SendKeys(keys.clone(), None))
+                        .await?;
+                    self.wait_for_job(jobs).await;
+                    jobs += 1;
+                }
+                Step::Text(text, profile) => {
+                    self.test
+                        .send_as(&self.sink, CharonEvent::SendText(text.clone(), *profile))
+                        .await?;
+                    self.wait_for_job(jobs).await;
+                    jobs += 1;
+                }
+            }
+            self.settle().await;
+        }
+        Ok(())
+    }
+}
+
+async fn assert_golden(name: &str) -> eyre::Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let script = fs::read_to_string(dir.join(format!("{name}.script")))?;
+
+    let mut ctx = setup().await?;
+    ctx.sup.start().await?;
+    ctx.settle().await;
+    ctx.run(&parse_script(&script)).await?;
+    let actual = format_reports(&ctx.host.lock().unwrap().report_bytes());
+    ctx.sup.stop().await?;
+
+    let expected_file = dir.join(format!("{name}.reports"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&expected_file, &actual)?;
+        return Ok(());
+    }
+    let expected = fs::read_to_string(&expected_file)?;
+    assert_eq!(
+        expected, actual,
+        "Reports of {name} differ from golden file"
+    );
+    Ok(())
+}
+
+/// Modifiers and keys of `KeyboardState`
+#[tokio::test]
+async fn test_modifiers() -> eyre::Result<()> {
+    assert_golden("modifiers").await
+}
+
+/// Overlapping key presses (roll-over)
+#[tokio::test]
+async fn test_rollover() -> eyre::Result<()> {
+    assert_golden("rollover").await
+}
+
+/// Toggle mode shortcut: keys typed in in-app mode don't reach the host
+#[tokio::test]
+async fn test_toggle_mode_shortcut() -> eyre::Result<()> {
+    assert_golden("toggle_mode").await
+}
+
+/// Text typed by `Typist` with the default (en_us) keymap
+#[tokio::test]
+async fn test_typist() -> eyre::Result<()> {
+    assert_golden("typist").await
+}
+
+/// Text typed with fast (roll-over) and safe (separate modifiers) typing profiles
+#[tokio::test]
+async fn test_typist_profiles() -> eyre::Result<()> {
+    assert_golden("typist_profiles").await
+}
+
+/// Text mixed with named keys, chords and pauses
+#[tokio::test]
+async fn test_typist_keys() -> eyre::Result<()> {
+    assert_golden("typist_keys").await
+}
@@ -40,6 +40,10 @@ impl MockKeyboard {
         self.state.lock().await.simulate_key_release(key_code);
     }
 
+    async fn lost_key_release(&self, key_code: KeyCode) {
+        self.state.lock().await.simulate_lost_key_release(key_code);
+    }
+
     async fn drain(&self) {
         EventDeviceState::drain(&self.state).await;
     }
@@ -69,8 +73,11 @@ async fn setup() -> eyre::Result<TestContext> {
 }
 
 async fn setup_with_mode(initial_mode: Mode) -> eyre::Result<TestContext> {
+    setup_with_config(initial_mode, CharonConfig::default()).await
+}
+
+async fn setup_with_config(initial_mode: Mode, config: CharonConfig) -> eyre::Result<TestContext> {
     use CharonTopic::*;
-    let config = CharonConfig::default();
     let state = ActorState::new(initial_mode, Arc::new(config));
 
     let mut sup = Supervisor::default();
@@ -82,7 +89,7 @@ async fn setup_with_mode(initial_mode: Mode) -> eyre::Result<TestContext> {
         sup.add_actor(
             "KeyScanner",
             |ctx| KeyScanner::new(ctx, state.clone(), input, "test-keyboard".into()),
-            [System],
+            [System, Keyboard],
         )?;
         keyboard
     };
@@ -105,6 +112,12 @@ impl TestContext {
         tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
         Ok(())
     }
+
+    async fn send(&self, event: CharonEvent) -> maiko::Result<()> {
+        self.test.send_as(&self.sink, event).await?;
+        tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+        Ok(())
+    }
 }
 
 /// Tests that grab/ungrab is delayed until all keys are released.
@@ -270,3 +283,153 @@ async fn test_key_press_during_pending_ungrab() -> eyre::Result<()> {
     ctx.sup.stop().await?;
     Ok(())
 }
+
+/// Tests that keys held when the keyboard gets unplugged are released (so they don't
+/// get stuck on the host), and that the device is grabbed again when re-attached.
+#[tokio::test]
+async fn test_keys_released_on_keyboard_detach() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.sup.start().await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+
+    ctx.keyboard.key_press(KeyCode::KEY_A).await;
+    ctx.keyboard.drain().await;
+
+    ctx.test.start_recording().await;
+    ctx.send(CharonEvent::KeyboardDetached("test-keyboard".into()))
+        .await?;
+    ctx.test.stop_recording().await;
+
+    let releases = ctx
+        .test
+        .events()
+        .matching_event(|e| matches!(e, CharonEvent::KeyRelease(KeyCode::KEY_A, _)))
+        .count();
+    assert_eq!(1, releases, "Held key should be released on detach");
+    assert!(!ctx.keyboard.is_grabbed().await);
+
+    // Events for other keyboards should be ignored
+    ctx.send(CharonEvent::KeyboardAttached("other-keyboard".into()))
+        .await?;
+    assert!(!ctx.keyboard.is_grabbed().await);
+
+    ctx.send(CharonEvent::KeyboardAttached("test-keyboard".into()))
+        .await?;
+    assert!(
+        ctx.keyboard.is_grabbed().await,
+        "Device should be grabbed again after re-attaching in PassThrough mode"
+    );
+    assert_eq!(2, ctx.keyboard.grab_calls().await);
+
+    ctx.sup.stop().await?;
+    Ok(())
+}
+
+fn count_releases(ctx: &TestContext, key: KeyCode) -> usize {
+    ctx.test
+        .events()
+        .matching_event(move |e| matches!(e, CharonEvent::KeyRelease(k, _) if *k == key))
+        .count()
+}
+
+/// Tests that a key released without the scanner noticing (lost event) is released
+/// on mode change, so it doesn't block the ungrab, nor stays stuck on the host.
+#[tokio::test]
+async fn test_stale_key_released_on_mode_change() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.sup.start().await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+
+    ctx.keyboard.key_press(KeyCode::KEY_LEFTSHIFT).await;
+    ctx.keyboard.key_press(KeyCode::KEY_A).await;
+    ctx.keyboard.drain().await;
+    ctx.keyboard.lost_key_release(KeyCode::KEY_A).await;
+
+    ctx.test.start_recording().await;
+    ctx.switch_mode(Mode::InApp).await?;
+    ctx.test.stop_recording().await;
+
+    assert_eq!(1, count_releases(&ctx, KeyCode::KEY_A));
+    assert_eq!(0, count_releases(&ctx, KeyCode::KEY_LEFTSHIFT));
+    assert!(
+        ctx.keyboard.is_grabbed().await,
+        "Device should remain grabbed while Shift is still held"
+    );
+
+    ctx.keyboard.key_release(KeyCode::KEY_LEFTSHIFT).await;
+    ctx.keyboard.drain().await;
+    assert!(!ctx.keyboard.is_grabbed().await);
+
+    ctx.sup.stop().await?;
+    Ok(())
+}
+
+/// Tests that keys held without autorepeat are released after `stuck_key_timeout`,
+/// unless the device reports them as still pressed.
+#[tokio::test]
+async fn test_stuck_key_watchdog() -> eyre::Result<()> {
+    let config = CharonConfig {
+        stuck_key_timeout: 30,
+        ..Default::default()
+    };
+    let mut ctx = setup_with_config(Mode::PassThrough, config).await?;
+    ctx.sup.start().await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+
+    ctx.test.start_recording().await;
+    ctx.keyboard.key_press(KeyCode::KEY_LEFTCTRL).await;
+    ctx.keyboard.key_press(KeyCode::KEY_A).await;
+    ctx.keyboard.drain().await;
+    ctx.keyboard.lost_key_release(KeyCode::KEY_A).await;
+    ctx.switch_mode(Mode::InApp).await?;
+    ctx.keyboard.key_press(KeyCode::KEY_B).await;
+    ctx.keyboard.drain().await;
+    ctx.keyboard.lost_key_release(KeyCode::KEY_B).await;
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(80)).await;
+    ctx.test.stop_recording().await;
+
+    assert_eq!(1, count_releases(&ctx, KeyCode::KEY_A));
+    assert_eq!(1, count_releases(&ctx, KeyCode::KEY_B));
+    assert_eq!(
+        0,
+        count_releases(&ctx, KeyCode::KEY_LEFTCTRL),
+        "Key reported as pressed by the device should not be released"
+    );
+    assert!(ctx.keyboard.is_grabbed().await);
+
+    ctx.keyboard.key_release(KeyCode::KEY_LEFTCTRL).await;
+    ctx.keyboard.drain().await;
+    assert!(!ctx.keyboard.is_grabbed().await);
+
+    ctx.sup.stop().await?;
+    Ok(())
+}
+
+/// Tests that held keys are not released after `stuck_key_timeout` when the device
+/// can't report its key state (they may be still pressed).
+#[tokio::test]
+async fn test_stuck_key_watchdog_without_key_state() -> eyre::Result<()> {
+    let config = CharonConfig {
+        stuck_key_timeout: 30,
+        ..Default::default()
+    };
+    let mut ctx = setup_with_config(Mode::PassThrough, config).await?;
+    ctx.keyboard.state.lock().await.key_state_unsupported = true;
+    ctx.sup.start().await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+
+    ctx.test.start_recording().await;
+    ctx.keyboard.key_press(KeyCode::KEY_LEFTSHIFT).await;
+    ctx.keyboard.drain().await;
+    tokio::time::sleep(tokio::time::Duration::from_millis(80)).await;
+    ctx.keyboard.key_release(KeyCode::KEY_LEFTSHIFT).await;
+    ctx.keyboard.drain().await;
+    tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+    ctx.test.stop_recording().await;
+
+    assert_eq!(1, count_releases(&ctx, KeyCode::KEY_LEFTSHIFT));
+
+    ctx.sup.stop().await?;
+    Ok(())
+}
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! Reports written by `KeyWriter` while the host is unavailable (see `ReportBufferPolicy`).
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use maiko::{Context, Envelope, EventId, Supervisor};
+use tokio::time::{Duration, Instant, sleep};
+
+use charond::{
+    actor::KeyWriter,
+    adapter::mock::{HIDDeviceMock, HIDDeviceState},
+    config::{CharonConfig, ReportBufferPolicy},
+    domain::{ActorState, CharonEvent, Mode, Topic as CharonTopic},
+};
+
+const KEY_A: [u8; 8] = [0, 0, 0x04, 0, 0, 0, 0, 0];
+const KEY_B: [u8; 8] = [0, 0, 0x05, 0, 0, 0, 0, 0];
+const CTRL_A: [u8; 8] = [1, 0, 0x04, 0, 0, 0, 0, 0];
+const EMPTY: [u8; 8] = [0; 8];
+
+/// Time to wait for the expected reports
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Sender of HID reports, counting the ones handled by `KeyWriter` (see `ReportSent`).
+struct Sink {
+    handled: Arc<AtomicUsize>,
+}
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, envelope: &Envelope<Self::Event>) -> maiko::Result<()> {
+        if *envelope.event() == CharonEvent::ReportSent {
+            self.handled.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    sender: Context<CharonEvent>,
+    host: Arc<Mutex<HIDDeviceState>>,
+    handled: Arc<AtomicUsize>,
+    sent: usize,
+}
+
+async fn setup(policy: ReportBufferPolicy) -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let config = CharonConfig {
+        report_buffer_policy: policy,
+        host_retry_interval: 5,
+        ..Default::default()
+    };
+    let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+
+    let mut sup = Supervisor::default();
+
+    let output = HIDDeviceMock::default();
+    let host = output.state().clone();
+    sup.add_actor(
+        "KeyWriter",
+        |ctx| KeyWriter::new(ctx, state.clone(), output),
+        [System, KeyOutput],
+    )?;
+    let handled = Arc::new(AtomicUsize::new(0));
+    let mut sender = None;
+    sup.add_actor(
+        "Sink",
+        |ctx| {
+            sender = Some(ctx);
+            Sink {
+                handled: handled.clone(),
+            }
+        },
+        [Telemetry],
+    )?;
+
+    sup.start().await?;
+    Ok(TestContext {
+        sup,
+        sender: sender.expect("Sink should be created"),
+        host,
+        handled,
+        sent: 0,
+    })
+}
+
+impl TestContext {
+    /// Sends the reports (correlated, so `KeyWriter` confirms them)
+    /// and waits until they are handled: written or buffered
+    async fn send(&mut self, reports: &[[u8; 8]]) -> eyre::Result<()> {
+        for report in reports {
+            self.sent += 1;
+            let id = self.sent as EventId;
+            self.sender
+                .send_with_correlation(CharonEvent::HidReport(*report), id)
+                .await?;
+        }
+        let deadline = Instant::now() + TIMEOUT;
+        while self.handled.load(Ordering::Relaxed) < self.sent && Instant::now() < deadline {
+            sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(self.sent, self.handled.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    fn set_host_available(&self, available: bool) {
+        self.host.lock().unwrap().host_unavailable = !available;
+    }
+
+    fn set_host_suspended(&self, suspended: bool) {
+        self.host.lock().unwrap().host_suspended = suspended;
+    }
+
+    fn reports(&self) -> Vec<[u8; 8]> {
+        self.host.lock().unwrap().report_bytes()
+    }
+
+    /// Waits until the reports written to the host are complete (or timeout), returns them
+    async fn wait_for_reports(&self, complete: impl Fn(&[[u8; 8]]) -> bool) -> Vec<[u8; 8]> {
+        let deadline = Instant::now() + TIMEOUT;
+        while !complete(&self.reports()) && Instant::now() < deadline {
+            sleep(Duration::from_millis(1)).await;
+        }
+        self.reports()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        self.sup.stop().await?;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_reports_replayed_when_host_is_back() -> eyre::Result<()> {
+    let mut ctx = setup(ReportBufferPolicy::Buffer).await?;
+    ctx.send(&[KEY_A]).await?;
+    ctx.set_host_available(false);
+    ctx.send(&[EMPTY, CTRL_A, EMPTY, KEY_B]).await?;
+    assert_eq!(vec![EMPTY, KEY_A], ctx.reports());
+
+    ctx.set_host_available(true);
+    let reports = ctx.wait_for_reports(|reports| reports.len() >= 7).await;
+    ctx.stop().await?;
+    // replay is followed by an empty report (all keys released)
+    assert_eq!(
+        vec![EMPTY, KEY_A, EMPTY, CTRL_A, EMPTY, KEY_B, EMPTY],
+        reports
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reports_replayed_when_host_resumes() -> eyre::Result<()> {
+    let mut ctx = setup(ReportBufferPolicy::Buffer).await?;
+    ctx.send(&[KEY_A]).await?;
+    ctx.set_host_suspended(true);
+    ctx.send(&[EMPTY, KEY_B]).await?;
+    assert_eq!(vec![EMPTY, KEY_A], ctx.reports());
+
+    ctx.set_host_suspended(false);
+    let reports = ctx.wait_for_reports(|reports| reports.len() >= 5).await;
+    ctx.stop().await?;
+    assert_eq!(vec![EMPTY, KEY_A, EMPTY, KEY_B, EMPTY], reports);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_new_reports_queued_until_flushed() -> eyre::Result<()> {
+    let mut ctx = setup(ReportBufferPolicy::Buffer).await?;
+    ctx.set_host_available(false);
+    ctx.send(&[KEY_A]).await?;
+    ctx.set_host_available(true);
+    ctx.send(&[EMPTY, KEY_B, EMPTY]).await?;
+    let reports = ctx
+        .wait_for_reports(|reports| reports.contains(&KEY_B) && reports.last() == Some(&EMPTY))
+        .await;
+    ctx.stop().await?;
+
+    // the initial reset and KEY_A were buffered, so nothing is written out of order
+    let key_a = reports.iter().position(|r| *r == KEY_A);
+    let key_b = reports.iter().position(|r| *r == KEY_B);
+    assert!(key_a.is_some() && key_a < key_b, "Reports: {reports:?}");
+    assert_eq!(Some(&EMPTY), reports.last());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_typing_only_policy() -> eyre::Result<()> {
+    let mut ctx = setup(ReportBufferPolicy::TypingOnly).await?;
+    ctx.set_host_available(false);
+    ctx.send(&[CTRL_A, EMPTY, KEY_A, EMPTY]).await?;
+    ctx.set_host_available(true);
+    let reports = ctx.wait_for_reports(|reports| reports.len() >= 4).await;
+    ctx.stop().await?;
+
+    assert!(!reports.contains(&CTRL_A));
+    assert_eq!(vec![EMPTY, KEY_A, EMPTY, EMPTY], reports);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_drop_policy() -> eyre::Result<()> {
+    let mut ctx = setup(ReportBufferPolicy::Drop).await?;
+    ctx.set_host_available(false);
+    ctx.send(&[KEY_A, EMPTY]).await?;
+    ctx.set_host_available(true);
+    // the probe (empty report) detects the host
+    ctx.wait_for_reports(|reports| !reports.is_empty()).await;
+    ctx.send(&[KEY_B]).await?;
+    let reports = ctx.wait_for_reports(|reports| reports.len() >= 2).await;
+    ctx.stop().await?;
+
+    // new reports are written directly
+    assert_eq!(vec![EMPTY, KEY_B], reports);
+    Ok(())
+}
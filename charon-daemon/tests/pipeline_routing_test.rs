@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! Routing of key events by keyboard alias, through the pipeline built from the config.
+use std::sync::Arc;
+
+use evdev::KeyCode;
+use maiko::{ActorId, Envelope, Supervisor, testing::Harness};
+use tokio::time::{Duration, sleep};
+
+use charond::{
+    config::CharonConfig,
+    domain::{ActorState, CharonEvent, Mode, Topic as CharonTopic},
+    processor::ProcessorRegistry,
+};
+
+/// A no-op actor, used as a source of key events and observer of the results.
+struct Sink;
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, _: &Envelope<Self::Event>) -> maiko::Result<()> {
+        Ok(())
+    }
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    test: Harness<CharonEvent, CharonTopic>,
+    sink: ActorId,
+}
+
+async fn setup() -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let config: CharonConfig = toml::from_str(
+        r#"
+        [keyboard_settings.laptop]
+        remap = { capslock = "esc" }
+
+        [keyboard_settings.numpad]
+        role = "macro-pad"
+        macros = { kp1 = "hello" }
+
+        [keyboard_settings.tablet]
+        role = "in-app-only"
+        "#,
+    )?;
+    let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+
+    let mut sup = Supervisor::default();
+    let test = Harness::new(&mut sup).await;
+    let registry = ProcessorRegistry::default();
+    sup.add_actor(
+        "KeyEventPipeline",
+        |ctx| {
+            registry
+                .build_pipeline(ctx, &state)
+                .expect("Couldn't build the pipeline")
+        },
+        [System, KeyInput],
+    )?;
+    let sink = sup.add_actor("Sink", |_ctx| Sink, [KeyOutput, TextInput, Keyboard])?;
+
+    sup.start().await?;
+    test.start_recording().await;
+    Ok(TestContext { sup, test, sink })
+}
+
+impl TestContext {
+    async fn press(&self, key: KeyCode, keyboard: &str) -> eyre::Result<()> {
+        let event = CharonEvent::KeyPress(key, keyboard.into());
+        self.test.send_as(&self.sink, event).await?;
+        Ok(())
+    }
+
+    async fn release(&self, key: KeyCode, keyboard: &str) -> eyre::Result<()> {
+        let event = CharonEvent::KeyRelease(key, keyboard.into());
+        self.test.send_as(&self.sink, event).await?;
+        Ok(())
+    }
+
+    /// Stops the test, returns events produced by the pipeline
+    async fn stop(&mut self) -> eyre::Result<Vec<CharonEvent>> {
+        sleep(Duration::from_millis(20)).await;
+        self.test.stop_recording().await;
+        self.sup.stop().await?;
+        Ok(self
+            .test
+            .events()
+            .received_by(&self.sink)
+            .collect()
+            .iter()
+            .map(|entry| entry.payload().clone())
+            .collect())
+    }
+}
+
+#[tokio::test]
+async fn test_keyboards_share_report() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.press(KeyCode::KEY_LEFTSHIFT, "laptop").await?;
+    ctx.press(KeyCode::KEY_A, "external").await?;
+    ctx.release(KeyCode::KEY_A, "external").await?;
+    ctx.release(KeyCode::KEY_LEFTSHIFT, "laptop").await?;
+    let events = ctx.stop().await?;
+
+    assert_eq!(
+        vec![
+            CharonEvent::HidReport([0x02, 0, 0, 0, 0, 0, 0, 0]),
+            CharonEvent::HidReport([0x02, 0, 0x04, 0, 0, 0, 0, 0]),
+            CharonEvent::HidReport([0x02, 0, 0, 0, 0, 0, 0, 0]),
+            CharonEvent::HidReport([0; 8]),
+        ],
+        events
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_remap_per_keyboard() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.press(KeyCode::KEY_CAPSLOCK, "laptop").await?;
+    ctx.release(KeyCode::KEY_CAPSLOCK, "laptop").await?;
+    ctx.press(KeyCode::KEY_CAPSLOCK, "external").await?;
+    let events = ctx.stop().await?;
+
+    assert_eq!(
+        vec![
+            CharonEvent::HidReport([0, 0, 0x29, 0, 0, 0, 0, 0]),
+            CharonEvent::HidReport([0; 8]),
+            CharonEvent::HidReport([0, 0, 0x39, 0, 0, 0, 0, 0]),
+        ],
+        events
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_macro_pad() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.press(KeyCode::KEY_KP1, "numpad").await?;
+    ctx.release(KeyCode::KEY_KP1, "numpad").await?;
+    ctx.press(KeyCode::KEY_KP2, "numpad").await?;
+    ctx.release(KeyCode::KEY_KP2, "numpad").await?;
+    let events = ctx.stop().await?;
+
+    assert_eq!(
+        vec![
+            CharonEvent::SendText("hello".into(), None),
+            CharonEvent::MacroKey(KeyCode::KEY_KP2, "numpad".into()),
+        ],
+        events
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_in_app_only_keyboard_discarded() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.press(KeyCode::KEY_A, "tablet").await?;
+    ctx.release(KeyCode::KEY_A, "tablet").await?;
+    let events = ctx.stop().await?;
+
+    assert!(events.is_empty());
+    Ok(())
+}
@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! Replays input sessions (`tests/sessions/<name>.events`) through `KeyScanner`
+//! and the pipeline. The sessions are written by hand in the format of
+//! `charond --record-input <DIR>`, to reproduce what real keyboards send.
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex as StdMutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use evdev::KeyCode;
+use maiko::{ActorId, Envelope, Supervisor, testing::Harness};
+use tokio::time::{Duration, Instant, sleep};
+
+use charond::{
+    actor::{KeyScanner, KeyWriter},
+    adapter::{
+        EventDeviceReplay,
+        mock::{HIDDeviceMock, HIDDeviceState},
+    },
+    config::CharonConfig,
+    domain::{ActorState, CharonEvent, Mode, Topic as CharonTopic},
+    processor::ProcessorRegistry,
+};
+
+/// A no-op actor that subscribes to events for test observation.
+struct Sink;
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, _: &Envelope<Self::Event>) -> maiko::Result<()> {
+        Ok(())
+    }
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    test: Harness<CharonEvent, CharonTopic>,
+    state: ActorState,
+    host: Arc<StdMutex<HIDDeviceState>>,
+    sink: ActorId,
+    finished: Arc<AtomicBool>,
+}
+
+async fn setup(session: &str, speed: f64) -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let state = ActorState::new(Mode::PassThrough, Arc::new(CharonConfig::default()));
+
+    let mut sup = Supervisor::default();
+    let test = Harness::new(&mut sup).await;
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/sessions")
+        .join(format!("{session}.events"));
+    let input = EventDeviceReplay::from_file(&path, speed)?;
+    let finished = input.finished();
+    sup.add_actor(
+        "KeyScanner",
+        |ctx| KeyScanner::new(ctx, state.clone(), input, "main".into()),
+        [System, Keyboard],
+    )?;
+
+    let registry = ProcessorRegistry::default();
+    sup.add_actor(
+        "KeyEventPipeline",
+        |ctx| {
+            registry
+                .build_pipeline(ctx, &state)
+                .expect("Couldn't build the pipeline")
+        },
+        [System, KeyInput],
+    )?;
+
+    let output = HIDDeviceMock::default();
+    let host = output.state().clone();
+    sup.add_actor(
+        "KeyWriter",
+        |ctx| KeyWriter::new(ctx, state.clone(), output),
+        [System, KeyOutput],
+    )?;
+
+    let sink = sup.add_actor("Sink", |_ctx| Sink, [KeyInput])?;
+
+    Ok(TestContext {
+        sup,
+        test,
+        state,
+        host,
+        sink,
+        finished,
+    })
+}
+
+impl TestContext {
+    /// Replays the whole session, returns key events that reached the pipeline
+    async fn replay(&mut self) -> eyre::Result<Vec<CharonEvent>> {
+        self.start().await?;
+        self.finish().await
+    }
+
+    async fn start(&mut self) -> eyre::Result<()> {
+        self.test.start_recording().await;
+        self.sup.start().await?;
+        Ok(())
+    }
+
+    /// Switches the mode the way `SystemShortcutProcessor` does
+    async fn switch_mode(&mut self, mode: Mode) -> eyre::Result<()> {
+        self.state.set_mode(mode).await;
+        let event = CharonEvent::ModeChange(mode);
+        self.test.send_as(&self.sink, event).await?;
+        Ok(())
+    }
+
+    /// Waits until the session is replayed, returns key events that reached the pipeline
+    async fn finish(&mut self) -> eyre::Result<Vec<CharonEvent>> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !self.finished.load(Ordering::Relaxed) && Instant::now() < deadline {
+            sleep(Duration::from_millis(1)).await;
+        }
+        assert!(self.finished.load(Ordering::Relaxed), "Replay timed out");
+        sleep(Duration::from_millis(10)).await;
+        self.test.stop_recording().await;
+        self.sup.stop().await?;
+
+        Ok(self
+            .test
+            .events()
+            .received_by(&self.sink)
+            .collect()
+            .iter()
+            .map(|entry| entry.payload().clone())
+            .collect())
+    }
+}
+
+fn press(key: KeyCode) -> CharonEvent {
+    CharonEvent::KeyPress(key, "main".into())
+}
+
+fn release(key: KeyCode) -> CharonEvent {
+    CharonEvent::KeyRelease(key, "main".into())
+}
+
+/// Asserts that every key pressed in the session ends up released
+fn assert_all_released(events: &[CharonEvent]) {
+    let mut pressed = Vec::new();
+    for event in events {
+        match event {
+            CharonEvent::KeyPress(key, _) if !pressed.contains(key) => pressed.push(*key),
+            CharonEvent::KeyRelease(key, _) => pressed.retain(|k| k != key),
+            _ => {}
+        }
+    }
+    assert!(pressed.is_empty(), "Keys left pressed: {pressed:?}");
+}
+
+/// Misc (scan code) and sync events are skipped, autorepeat is passed as key press
+#[tokio::test]
+async fn test_replay_shift_typing() -> eyre::Result<()> {
+    let mut ctx = setup("shift_typing", 0.0).await?;
+    let events = ctx.replay().await?;
+
+    let expected = vec![
+        press(KeyCode::KEY_LEFTSHIFT),
+        press(KeyCode::KEY_H),
+        release(KeyCode::KEY_H),
+        release(KeyCode::KEY_LEFTSHIFT),
+        press(KeyCode::KEY_I),
+        release(KeyCode::KEY_I),
+        press(KeyCode::KEY_SPACE),
+        release(KeyCode::KEY_SPACE),
+        press(KeyCode::KEY_W),
+        press(KeyCode::KEY_E),
+        release(KeyCode::KEY_W),
+        press(KeyCode::KEY_E),
+        press(KeyCode::KEY_E),
+        press(KeyCode::KEY_E),
+        release(KeyCode::KEY_E),
+    ];
+    assert_eq!(expected, events);
+    assert_all_released(&events);
+    Ok(())
+}
+
+/// Overlapping key presses keep their order, also when replayed at (accelerated) real speed
+#[tokio::test]
+async fn test_replay_rollover_timed() -> eyre::Result<()> {
+    let mut ctx = setup("rollover", 10.0).await?;
+    let started = Instant::now();
+    let events = ctx.replay().await?;
+
+    // the session is 565ms long
+    assert!(started.elapsed() >= Duration::from_millis(56));
+    let expected = vec![
+        press(KeyCode::KEY_A),
+        press(KeyCode::KEY_S),
+        release(KeyCode::KEY_A),
+        release(KeyCode::KEY_S),
+        press(KeyCode::KEY_LEFTCTRL),
+        press(KeyCode::KEY_A),
+        release(KeyCode::KEY_A),
+        release(KeyCode::KEY_LEFTCTRL),
+    ];
+    assert_eq!(expected, events);
+    assert_all_released(&events);
+    Ok(())
+}
+
+/// Modifier released in in-app mode doesn't stay pressed on the host
+#[tokio::test]
+async fn test_replay_mode_switch_modifier() -> eyre::Result<()> {
+    let mut ctx = setup("mode_switch_modifier", 1.0).await?;
+    ctx.start().await?;
+    // shift is held when switched, released at 400ms
+    sleep(Duration::from_millis(250)).await;
+    ctx.switch_mode(Mode::InApp).await?;
+    // "b" is typed at 700ms
+    sleep(Duration::from_millis(300)).await;
+    ctx.switch_mode(Mode::PassThrough).await?;
+    let events = ctx.finish().await?;
+
+    assert_all_released(&events);
+    let reports = ctx.host.lock().unwrap().report_bytes();
+    let shifted_a = [0x02, 0, 0x04, 0, 0, 0, 0, 0];
+    let b = [0, 0, 0x05, 0, 0, 0, 0, 0];
+    assert!(reports.contains(&shifted_a));
+    assert!(reports.contains(&b), "Shift stuck: {reports:?}");
+    assert_eq!(Some(&[0; 8]), reports.last());
+    Ok(())
+}
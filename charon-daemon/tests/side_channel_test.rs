@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+use std::sync::Arc;
+
+use charond::domain::{
+    CharonEvent, Mode,
+    qmk::{QMKEvent, QMKKeymap, QMKRequest},
+};
+use maiko::{Envelope, Supervisor, testing::Harness};
+use tokio::{
+    sync::Mutex,
+    time::{Duration, sleep},
+};
+
+use charond::{
+    actor::QMK,
+    adapter::mock::{SideChannelMock, SideChannelState},
+    config::CharonConfig,
+    domain::{ActorState, Topic as CharonTopic},
+};
+
+/// A no-op actor that subscribes to events for test observation.
+struct Sink;
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, _: &Envelope<Self::Event>) -> maiko::Result<()> {
+        Ok(())
+    }
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    test: Harness<CharonEvent, CharonTopic>,
+    keyboard: Arc<Mutex<SideChannelState>>,
+    state: ActorState,
+}
+
+async fn setup() -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let config = CharonConfig {
+        qmk_reconnect_interval: 10,
+        ..Default::default()
+    };
+    let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+
+    let mut sup = Supervisor::default();
+    let test = Harness::new(&mut sup).await;
+
+    let device = SideChannelMock::default();
+    let keyboard = device.state().clone();
+    sup.add_actor(
+        "QMK-split",
+        |ctx| QMK::new(ctx, state.clone(), "split".into(), device),
+        [System, Keyboard],
+    )?;
+    sup.add_actor("Sink", |_ctx| Sink, [System, Monitoring])?;
+
+    Ok(TestContext {
+        sup,
+        test,
+        keyboard,
+        state,
+    })
+}
+
+impl TestContext {
+    async fn send_event(&self, event: QMKEvent) {
+        self.keyboard.lock().await.send_event(event);
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_side_channel_events() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.sup.start().await?;
+    sleep(Duration::from_millis(10)).await;
+
+    {
+        let keyboard = ctx.keyboard.lock().await;
+        assert!(keyboard.connected);
+        assert_eq!(vec![QMKRequest::KeyboardInfo], keyboard.requests);
+    }
+
+    ctx.test.start_recording().await;
+    ctx.send_event(QMKEvent::LayerChange(2, false)).await;
+    ctx.send_event(QMKEvent::ToggleMode).await;
+    ctx.test.stop_recording().await;
+
+    let layer_changes = ctx
+        .test
+        .events()
+        .matching_event(|e| {
+            matches!(e, CharonEvent::QMKEvent(QMKEvent::LayerChange(2, false), alias) if alias == "split")
+        })
+        .count();
+    assert!(layer_changes > 0);
+    assert!(
+        ctx.test
+            .events()
+            .any(|e| matches!(e.payload(), CharonEvent::ModeChange(Mode::InApp)))
+    );
+    assert_eq!(Mode::InApp, ctx.state.mode().await);
+
+    ctx.sup.stop().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_side_channel_reconnect() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.sup.start().await?;
+    sleep(Duration::from_millis(10)).await;
+
+    ctx.keyboard.lock().await.disconnect();
+    sleep(Duration::from_millis(50)).await;
+
+    let keyboard = ctx.keyboard.lock().await;
+    assert!(keyboard.connected);
+    assert_eq!(2, keyboard.connect_calls);
+    assert_eq!(2, keyboard.requests.len());
+    drop(keyboard);
+
+    ctx.sup.stop().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keymap_of_requested_keyboard() -> eyre::Result<()> {
+    use CharonTopic::*;
+    let dir = std::env::temp_dir().join(format!("charon-keymaps-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let keymap = |keyboard: &str| QMKKeymap {
+        keyboard: keyboard.into(),
+        keymap: "default".into(),
+        layout: "LAYOUT".into(),
+        layers: vec![vec!["KC_A".into()]],
+    };
+    for alias in ["split", "macropad"] {
+        std::fs::write(
+            dir.join(format!("{alias}.json")),
+            serde_json::to_string(&keymap(alias))?,
+        )?;
+    }
+    let config = CharonConfig {
+        qmk_keymap_dir: dir.clone(),
+        ..Default::default()
+    };
+    let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+
+    let mut sup = Supervisor::default();
+    let mut test = Harness::new(&mut sup).await;
+    for alias in ["split", "macropad"] {
+        sup.add_actor(
+            &format!("QMK-{alias}"),
+            |ctx| QMK::new(ctx, state.clone(), alias.into(), SideChannelMock::default()),
+            [System, Keyboard],
+        )?;
+    }
+    let sink = sup.add_actor("Sink", |_ctx| Sink, [Keyboard])?;
+    sup.start().await?;
+    sleep(Duration::from_millis(10)).await;
+
+    test.start_recording().await;
+    test.send_as(&sink, CharonEvent::GetQMKKeymap("split".into()))
+        .await?;
+    sleep(Duration::from_millis(20)).await;
+    test.stop_recording().await;
+    sup.stop().await?;
+    std::fs::remove_dir_all(&dir)?;
+
+    let keymaps: Vec<_> = test
+        .events()
+        .received_by(&sink)
+        .collect()
+        .iter()
+        .filter_map(|entry| match entry.payload() {
+            CharonEvent::QMKKeymap(keymap, alias) => Some((keymap.clone(), alias.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(vec![(keymap("split"), "split".to_string())], keymaps);
+    Ok(())
+}
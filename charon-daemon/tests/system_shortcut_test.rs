@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! Actions of key bindings, executed by `SystemShortcutProcessor` in the pipeline.
+use std::sync::Arc;
+
+use evdev::KeyCode;
+use maiko::{ActorId, Envelope, Supervisor, testing::Harness};
+use tokio::time::{Duration, sleep};
+
+use charond::{
+    actor::Typist,
+    adapter::KeymapLoaderYaml,
+    config::CharonConfig,
+    domain::{ActorState, CharonEvent, Mode, Topic as CharonTopic},
+    port::KeymapLoader,
+    processor::ProcessorRegistry,
+};
+
+const A: [u8; 8] = [0, 0, 0x04, 0, 0, 0, 0, 0];
+const B: [u8; 8] = [0, 0, 0x05, 0, 0, 0, 0, 0];
+const RELEASED: [u8; 8] = [0; 8];
+
+/// A no-op actor, used as a source of key events and observer of the results.
+struct Sink;
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, _: &Envelope<Self::Event>) -> maiko::Result<()> {
+        Ok(())
+    }
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    test: Harness<CharonEvent, CharonTopic>,
+    state: ActorState,
+    sink: ActorId,
+}
+
+async fn setup() -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let mut config: CharonConfig = toml::from_str(
+        r#"
+        [[bindings]]
+        shortcut = "f9"
+        action = "send-text"
+        text = "hello"
+
+        [[bindings]]
+        shortcut = "f10"
+        action = "macro-record"
+
+        [[bindings]]
+        shortcut = "f11"
+        action = "macro-play"
+        "#,
+    )?;
+    config.typing_interval = 1;
+    let loader = KeymapLoaderYaml::new(&config.keymaps_dir);
+    let keymap = loader.load_keymap(&config.host_keymap).await?;
+    let state = ActorState::new(Mode::PassThrough, Arc::new(config));
+
+    let mut sup = Supervisor::default();
+    let test = Harness::new(&mut sup).await;
+    let registry = ProcessorRegistry::default();
+    sup.add_actor(
+        "KeyEventPipeline",
+        |ctx| {
+            registry
+                .build_pipeline(ctx, &state)
+                .expect("Couldn't build the pipeline")
+        },
+        [System, KeyInput],
+    )?;
+    sup.add_actor(
+        "Typist",
+        |ctx| Typist::new(ctx, state.clone(), keymap, loader),
+        [System, TextInput],
+    )?;
+    let sink = sup.add_actor(
+        "Sink",
+        |_ctx| Sink,
+        [System, KeyOutput, TextInput, Keyboard],
+    )?;
+
+    sup.start().await?;
+    test.start_recording().await;
+    Ok(TestContext {
+        sup,
+        test,
+        state,
+        sink,
+    })
+}
+
+impl TestContext {
+    async fn tap(&self, key: KeyCode) -> eyre::Result<()> {
+        for event in [
+            CharonEvent::KeyPress(key, "test-keyboard".into()),
+            CharonEvent::KeyRelease(key, "test-keyboard".into()),
+        ] {
+            self.test.send_as(&self.sink, event).await?;
+        }
+        sleep(Duration::from_millis(10)).await;
+        Ok(())
+    }
+
+    /// Stops the test, returns events received by the sink
+    async fn stop(&mut self) -> eyre::Result<Vec<CharonEvent>> {
+        sleep(Duration::from_millis(50)).await;
+        self.test.stop_recording().await;
+        self.sup.stop().await?;
+        Ok(self
+            .test
+            .events()
+            .received_by(&self.sink)
+            .collect()
+            .iter()
+            .map(|entry| entry.payload().clone())
+            .collect())
+    }
+}
+
+fn reports(events: &[CharonEvent]) -> Vec<[u8; 8]> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            CharonEvent::HidReport(report) => Some(*report),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_send_text() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.tap(KeyCode::KEY_F9).await?;
+    sleep(Duration::from_millis(100)).await;
+    let events = ctx.stop().await?;
+
+    assert!(events.contains(&CharonEvent::SendText("hello".into(), None)));
+    // the shortcut never reaches the host, the text is typed in pass-through mode
+    let reports = reports(&events);
+    assert!(reports.iter().all(|report| report[2] != 0x42));
+    let typed: Vec<_> = [0x0b, 0x08, 0x0f, 0x0f, 0x12]
+        .into_iter()
+        .flat_map(|key| [[0, 0, key, 0, 0, 0, 0, 0], RELEASED])
+        .collect();
+    assert!(reports.ends_with(&typed), "Reports: {reports:?}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_toggle_mode() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.tap(KeyCode::KEY_F7).await?;
+    let mode = ctx.state.mode().await;
+    let events = ctx.stop().await?;
+
+    assert_eq!(Mode::InApp, mode);
+    assert!(events.contains(&CharonEvent::ModeChange(Mode::InApp)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_macro_playback() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.tap(KeyCode::KEY_F10).await?;
+    ctx.tap(KeyCode::KEY_A).await?;
+    ctx.tap(KeyCode::KEY_B).await?;
+    ctx.tap(KeyCode::KEY_F10).await?;
+    ctx.tap(KeyCode::KEY_F11).await?;
+    let events = ctx.stop().await?;
+
+    assert!(events.contains(&CharonEvent::MacroRecording(true)));
+    // the recording starts with release of the shortcut
+    assert!(events.contains(&CharonEvent::MacroRecorded(vec![
+        RELEASED, A, RELEASED, B, RELEASED
+    ])));
+    let played = events
+        .iter()
+        .position(|event| *event == CharonEvent::PlayMacro)
+        .expect("Macro should be played");
+    assert!(reports(&events[played..]).ends_with(&[A, RELEASED, B, RELEASED]));
+    Ok(())
+}
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+//! Typing jobs of `Typist`: progress, completion, queueing, cancellation and errors.
+use std::sync::Arc;
+
+use maiko::{ActorId, Envelope, EventId, Supervisor, testing::Harness};
+use tokio::time::{Duration, sleep};
+
+use charond::{
+    actor::Typist,
+    adapter::KeymapLoaderYaml,
+    config::CharonConfig,
+    domain::{ActorState, CharonEvent, Mode, Topic as CharonTopic, TypingError},
+    port::KeymapLoader,
+};
+
+/// A no-op actor, used as a requester of typing jobs.
+struct Sink;
+
+impl maiko::Actor for Sink {
+    type Event = CharonEvent;
+    async fn handle_event(&mut self, _: &Envelope<Self::Event>) -> maiko::Result<()> {
+        Ok(())
+    }
+}
+
+struct TestContext {
+    sup: Supervisor<CharonEvent, CharonTopic>,
+    test: Harness<CharonEvent, CharonTopic>,
+    state: ActorState,
+    sink: ActorId,
+}
+
+async fn setup() -> eyre::Result<TestContext> {
+    setup_with_mode(Mode::InApp).await
+}
+
+async fn setup_with_mode(mode: Mode) -> eyre::Result<TestContext> {
+    use CharonTopic::*;
+    let config = CharonConfig {
+        typing_interval: 2,
+        ..Default::default()
+    };
+    let loader = KeymapLoaderYaml::new(&config.keymaps_dir);
+    let keymap = loader.load_keymap(&config.host_keymap).await?;
+    let state = ActorState::new(mode, Arc::new(config));
+
+    let mut sup = Supervisor::default();
+    let test = Harness::new(&mut sup).await;
+    sup.add_actor(
+        "Typist",
+        |ctx| Typist::new(ctx, state.clone(), keymap, loader),
+        [System, TextInput],
+    )?;
+    let sink = sup.add_actor("Sink", |_ctx| Sink, [Monitoring, KeyOutput])?;
+
+    sup.start().await?;
+    test.start_recording().await;
+    Ok(TestContext {
+        sup,
+        test,
+        state,
+        sink,
+    })
+}
+
+impl TestContext {
+    async fn send(&self, event: CharonEvent) -> eyre::Result<EventId> {
+        Ok(self.test.send_as(&self.sink, event).await?)
+    }
+
+    /// Stops the test, returns events received by the sink
+    async fn stop(&mut self) -> eyre::Result<Vec<CharonEvent>> {
+        sleep(Duration::from_millis(20)).await;
+        self.test.stop_recording().await;
+        self.sup.stop().await?;
+        Ok(self
+            .test
+            .events()
+            .received_by(&self.sink)
+            .collect()
+            .iter()
+            .map(|entry| entry.payload().clone())
+            .collect())
+    }
+}
+
+fn reports(events: &[CharonEvent]) -> Vec<[u8; 8]> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            CharonEvent::HidReport(report) => Some(*report),
+            _ => None,
+        })
+        .collect()
+}
+
+fn job_events(events: &[CharonEvent]) -> Vec<CharonEvent> {
+    events
+        .iter()
+        .filter(|event| !matches!(event, CharonEvent::HidReport(_)))
+        .cloned()
+        .collect()
+}
+
+#[tokio::test]
+async fn test_progress_and_completion() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    let id = ctx.send(CharonEvent::SendText("abc".into(), None)).await?;
+    sleep(Duration::from_millis(100)).await;
+    let events = ctx.stop().await?;
+
+    assert_eq!(6, reports(&events).len());
+    assert_eq!(
+        vec![
+            CharonEvent::TypingProgress(id, 0, 3),
+            CharonEvent::TypingProgress(id, 3, 3),
+            CharonEvent::TextSent(id),
+        ],
+        job_events(&events)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_jobs_queued() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    let first = ctx.send(CharonEvent::SendText("ab".into(), None)).await?;
+    let second = ctx
+        .send(CharonEvent::SendKeys("{Enter}".into(), None))
+        .await?;
+    sleep(Duration::from_millis(100)).await;
+    let events = ctx.stop().await?;
+
+    let done: Vec<_> = job_events(&events)
+        .into_iter()
+        .filter(|event| matches!(event, CharonEvent::TextSent(_)))
+        .collect();
+    assert_eq!(
+        vec![CharonEvent::TextSent(first), CharonEvent::TextSent(second)],
+        done
+    );
+    // a, release, b, release, enter, release
+    assert_eq!(6, reports(&events).len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancel() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    let text = "a".repeat(200);
+    let running = ctx.send(CharonEvent::SendText(text.clone(), None)).await?;
+    let queued = ctx.send(CharonEvent::SendText(text, None)).await?;
+    sleep(Duration::from_millis(50)).await;
+    ctx.send(CharonEvent::CancelTyping(Some(running))).await?;
+    sleep(Duration::from_millis(50)).await;
+    ctx.send(CharonEvent::CancelTyping(None)).await?;
+    let events = ctx.stop().await?;
+
+    let events = job_events(&events);
+    assert!(events.contains(&CharonEvent::TypingFailed(running, TypingError::Cancelled)));
+    assert!(events.contains(&CharonEvent::TypingFailed(queued, TypingError::Cancelled)));
+    assert!(
+        !events
+            .iter()
+            .any(|event| matches!(event, CharonEvent::TextSent(_)))
+    );
+    // the queued job started when the running one was cancelled
+    assert!(events.contains(&CharonEvent::TypingProgress(queued, 0, 200)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keys_released_when_cancelled() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    ctx.send(CharonEvent::SendText("a".repeat(200), None))
+        .await?;
+    sleep(Duration::from_millis(30)).await;
+    ctx.send(CharonEvent::CancelTyping(None)).await?;
+    let events = ctx.stop().await?;
+
+    assert_eq!(Some(&[0; 8]), reports(&events).last());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_errors_reported() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    let keys = ctx
+        .send(CharonEvent::SendKeys("{Foo}".into(), None))
+        .await?;
+    let file = ctx
+        .send(CharonEvent::SendFile("/nonexistent".into(), true, None))
+        .await?;
+    let events = ctx.stop().await?;
+
+    assert!(reports(&events).is_empty());
+    let events = job_events(&events);
+    assert!(matches!(
+        events[0],
+        CharonEvent::TypingFailed(id, TypingError::InvalidKeys(_)) if id == keys
+    ));
+    assert!(matches!(
+        events[1],
+        CharonEvent::TypingFailed(id, TypingError::File(_)) if id == file
+    ));
+    Ok(())
+}
+
+/// Jobs requested in pass-through mode (i.e. by key bindings) are typed in that mode
+#[tokio::test]
+async fn test_typed_in_pass_through_mode() -> eyre::Result<()> {
+    let mut ctx = setup_with_mode(Mode::PassThrough).await?;
+    let id = ctx.send(CharonEvent::SendText("abc".into(), None)).await?;
+    sleep(Duration::from_millis(100)).await;
+    let events = ctx.stop().await?;
+
+    assert_eq!(6, reports(&events).len());
+    assert!(job_events(&events).contains(&CharonEvent::TextSent(id)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_interrupted_on_mode_change() -> eyre::Result<()> {
+    let mut ctx = setup().await?;
+    let id = ctx
+        .send(CharonEvent::SendText("a".repeat(200), None))
+        .await?;
+    sleep(Duration::from_millis(30)).await;
+    ctx.state.set_mode(Mode::PassThrough).await;
+    let events = ctx.stop().await?;
+
+    assert!(job_events(&events).contains(&CharonEvent::TypingFailed(id, TypingError::Interrupted)));
+    assert_eq!(Some(&[0; 8]), reports(&events).last());
+    Ok(())
+}
@@ -15,6 +15,8 @@ enum CharonSendMessage {
     CHARON_MSG_TOGGLE_MODE = 0x05,
     CHARON_MSG_LAYER_CHUNK = 0x10,
     CHARON_MSG_SEND_KEYBOARD_INFO = 0x11,
+    CHARON_MSG_RGB_SET = 0x12,
+    CHARON_MSG_DEFAULT_LAYER_SET = 0x13,
 };
 
 void charon_send_layer_change(layer_state_t state, bool is_default) {
@@ -56,6 +58,7 @@ void charon_send_keyboard_info(void) {
     data[0] = CHARON_MSG_SEND_KEYBOARD_INFO;
     data[1] = MATRIX_ROWS;
     data[2] = MATRIX_COLS;
+    data[3] = keymap_layer_count();
     raw_hid_send(data, sizeof(data));
 }
 
@@ -96,8 +99,34 @@ enum CharonRecvMessage {
     CHARON_REQ_ECHO = 0x01,
     CHARON_REQ_LAYER_CHUNK = 0x10,
     CHARON_REQ_KEYBOARD_INFO = 0x11,
+    CHARON_REQ_SET_RGB = 0x12,
+    CHARON_REQ_SET_DEFAULT_LAYER = 0x13,
 };
 
+void charon_set_rgb(uint8_t *data) {
+    uint8_t response[32] = {0};
+    response[0] = CHARON_MSG_RGB_SET;
+#ifdef RGB_MATRIX_ENABLE
+    if (data[1]) {
+        rgb_matrix_enable_noeeprom();
+        rgb_matrix_mode_noeeprom(data[2]);
+        rgb_matrix_sethsv_noeeprom(data[3], data[4], data[5]);
+    } else {
+        rgb_matrix_disable_noeeprom();
+    }
+    response[1] = 1;
+#endif
+    raw_hid_send(response, sizeof(response));
+}
+
+void charon_set_default_layer(uint8_t layer) {
+    uint8_t response[32] = {0};
+    response[0] = CHARON_MSG_DEFAULT_LAYER_SET;
+    response[1] = layer;
+    default_layer_set((layer_state_t)1 << layer);
+    raw_hid_send(response, sizeof(response));
+}
+
 void charon_raw_hid_receive(uint8_t *data, uint8_t len) {
     if (len < 1) return;
 
@@ -116,5 +145,13 @@ void charon_raw_hid_receive(uint8_t *data, uint8_t len) {
             charon_send_keyboard_info();
             break;
         }
+        case CHARON_REQ_SET_RGB: {
+            charon_set_rgb(data);
+            break;
+        }
+        case CHARON_REQ_SET_DEFAULT_LAYER: {
+            charon_set_default_layer(data[1]);
+            break;
+        }
     }
 }
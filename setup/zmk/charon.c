@@ -0,0 +1,54 @@
+/* Charon module for ZMK: sends layer changes and key events over CDC-ACM serial port,
+ * using Charon line protocol (see docs/qmk.md).
+ *
+ * Add the file to your zmk-config module and point `charon,uart` to the CDC-ACM port, i.e.:
+ *
+ *   / {
+ *       chosen {
+ *           charon,uart = &cdc_acm_uart;
+ *       };
+ *   };
+ *
+ * On split keyboards it must be built only for the central half.
+ */
+
+#include <stdio.h>
+#include <zephyr/device.h>
+#include <zephyr/drivers/uart.h>
+#include <zmk/event_manager.h>
+#include <zmk/events/layer_state_changed.h>
+#include <zmk/events/position_state_changed.h>
+#include <zmk/keymap.h>
+
+static const struct device *charon_uart = DEVICE_DT_GET(DT_CHOSEN(charon_uart));
+
+static void charon_send(const char *line) {
+    if (!device_is_ready(charon_uart)) {
+        return;
+    }
+    for (const char *c = line; *c != '\0'; c++) {
+        uart_poll_out(charon_uart, *c);
+    }
+}
+
+static int charon_listener(const zmk_event_t *eh) {
+    char line[32];
+
+    if (as_zmk_layer_state_changed(eh) != NULL) {
+        snprintf(line, sizeof(line), "layer %d\n", zmk_keymap_highest_layer_active());
+        charon_send(line);
+        return ZMK_EV_EVENT_BUBBLE;
+    }
+
+    const struct zmk_position_state_changed *pos = as_zmk_position_state_changed(eh);
+    if (pos != NULL && pos->position < 256) {
+        /* ZMK has no matrix positions nor QMK keycodes: key position is sent as column */
+        snprintf(line, sizeof(line), "key 0 %d 0 %d\n", pos->state ? 1 : 0, pos->position);
+        charon_send(line);
+    }
+    return ZMK_EV_EVENT_BUBBLE;
+}
+
+ZMK_LISTENER(charon, charon_listener);
+ZMK_SUBSCRIPTION(charon, zmk_layer_state_changed);
+ZMK_SUBSCRIPTION(charon, zmk_position_state_changed);